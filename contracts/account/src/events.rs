@@ -0,0 +1,114 @@
+use soroban_sdk::{contractevent, Address, BytesN, Symbol, Vec};
+
+use crate::SignerKey;
+
+#[contractevent(topics = ["signer", "added"], data_format = "single-value")]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SignerAdded {
+    pub signer: SignerKey,
+}
+
+#[contractevent(topics = ["signer", "removed"], data_format = "single-value")]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SignerRemoved {
+    pub signer: SignerKey,
+}
+
+#[contractevent(topics = ["signer", "replaced"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SignersReplaced {
+    pub removed: Vec<SignerKey>,
+    pub added: Vec<SignerKey>,
+    pub threshold: u32,
+}
+
+#[contractevent(topics = ["signer", "swapped"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SignerSwapped {
+    pub old: SignerKey,
+    pub new: SignerKey,
+}
+
+#[contractevent(topics = ["signer", "admin_set"], data_format = "single-value")]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AdminSet {
+    pub admin: Address,
+}
+
+#[contractevent(topics = ["threshold", "set"], data_format = "single-value")]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ThresholdSet {
+    pub threshold: u32,
+}
+
+#[contractevent(topics = ["admin", "proposed"], data_format = "single-value")]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AdminProposed {
+    pub admin: Address,
+}
+
+#[contractevent(topics = ["recovery", "initiated"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RecoveryInitiated {
+    pub new_signer: BytesN<32>,
+    pub effective_ledger: u32,
+}
+
+#[contractevent(topics = ["recovery", "cancelled"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RecoveryCancelled {}
+
+#[contractevent(topics = ["account", "frozen"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Frozen {}
+
+#[contractevent(topics = ["account", "unfrozen"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Unfrozen {}
+
+#[contractevent(topics = ["account", "token_frozen"], data_format = "single-value")]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TokenFrozen {
+    pub token: Address,
+}
+
+#[contractevent(topics = ["account", "token_unfrozen"], data_format = "single-value")]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TokenUnfrozen {
+    pub token: Address,
+}
+
+#[contractevent(topics = ["account", "sessions_revoked"], data_format = "single-value")]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SessionsRevoked {
+    pub epoch: u32,
+}
+
+#[contractevent(topics = ["contract", "closed"], data_format = "single-value")]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Closed {
+    pub admin: Address,
+}
+
+#[contractevent(topics = ["contract", "upgraded"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Upgraded {
+    pub new_wasm_hash: BytesN<32>,
+    pub admin: Address,
+}
+
+#[contractevent(topics = ["auth", "rejected"], data_format = "single-value")]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AuthRejected {
+    #[topic]
+    pub reason: Symbol,
+    pub signer: SignerKey,
+}
+
+/// Published by every authorization that succeeds, naming by fingerprint
+/// the signers that counted towards it
+#[contractevent(topics = ["auth", "success"], data_format = "single-value")]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AuthSucceeded {
+    pub signers: Vec<BytesN<4>>,
+}
@@ -2,89 +2,3505 @@
 
 use soroban_sdk::{
     auth::{Context, CustomAccountInterface},
-    contract, contracterror, contractimpl, contracttype,
+    contract, contracterror, contractimpl, contractmeta, contracttype,
     crypto::Hash,
-    Address, BytesN, Env, Vec,
+    panic_with_error, symbol_short, vec,
+    xdr::ToXdr,
+    Address, Bytes, BytesN, Env, Executable, Map, MuxedAddress, String, Symbol, TryFromVal, Val,
+    Vec,
 };
 
+mod events;
+#[cfg(any(test, feature = "testutils"))]
+pub mod testutils;
+mod webauthn;
+
 #[contract]
-struct Account;
+pub struct Account;
 
 #[derive(Clone)]
 #[contracttype]
 pub enum DataKey {
     Admin,
+    /// Superseded by `Signers`, only read when migrating
     Signer(BytesN<32>),
+    Threshold,
+    /// Superseded by `Signers`, only read when migrating
+    Secp256r1Signer(BytesN<65>),
+    SignerList,
+    PendingAdmin,
+    /// Superseded by `SignerRecord::scope`, only read when migrating
+    SignerScope(SignerKey),
+    /// Superseded by `SignerRecord::expiry`, only read when migrating
+    SignerExpiry(SignerKey),
+    Nonce,
+    MaxSignatures,
+    Version,
+    SpendLimit(BytesN<32>),
+    SpendHistory(BytesN<32>),
+    Recovery,
+    /// Superseded by `PendingRecoveries`, only read when migrating
+    PendingRecovery,
+    Frozen,
+    ExactSignatures,
+    SignerLabel(BytesN<32>),
+    MaxContexts,
+    UniformVerification,
+    SessionEpoch,
+    /// Superseded by `SignerRecord::session`, only read when migrating
+    SignerSession(SignerKey),
+    DomainSeparator,
+    MasterSigner,
+    RateLimit,
+    RateWindow,
+    MaxSigners,
+    LastAuth,
+    /// Superseded by `PersistentSigner`, only read when migrating
+    Signers,
+    Revoked(BytesN<32>),
+    /// A signer's record, kept in persistent storage with its own TTL
+    PersistentSigner(SignerKey),
+    /// Addresses any one of which may start a recovery
+    RecoveryGroup,
+    /// Last ledger a rotated-out signer keeps working
+    SignerGrace(BytesN<32>),
+    /// Set once the constructor has run
+    Initialized,
+    /// Authorizations a count-limited signer has left
+    SignerUses(BytesN<32>),
+    MaxSigBytes,
+    /// `(contract, function)` pairs an ed25519 signer may authorize calls to
+    SignerFnScope(BytesN<32>),
+    /// `(contract, function)` pairs an ed25519 signer's authorizations must
+    /// each include
+    SignerRequiredFns(BytesN<32>),
+    /// Set for each address that may freeze the account, and do nothing else
+    Guardian(Address),
+    /// The ed25519 signer that must sign alongside an ed25519 signer
+    CoSigner(BytesN<32>),
+    /// Ledgers that must pass between signer changes
+    ChangeCooldown,
+    /// Ledger sequence of the last signer change
+    LastChange,
+    /// Set for each token the account may not transfer
+    FrozenToken(Address),
+    /// Set while admin functions answer to the account's own authorization
+    SelfAuth,
+    /// Set while signatures must commit to every context being authorized
+    FullContext,
+    /// Most a signer may transfer in one authorization unless a signer
+    /// trusted with more also signs
+    SignerTxCap(BytesN<32>),
+    /// Recoveries waiting out their delay, oldest first
+    PendingRecoveries,
+    MaxRecoveryRequests,
+    MaxDelegationDepth,
+}
+
+/// Who the admin functions answer to, chosen at construction
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum AdminMode {
+    /// A separate admin address, which can later be transferred or renounced
+    External(Address),
+    /// The account itself, so every admin function needs the signers to meet
+    /// the threshold
+    SelfAuth,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SignerKey {
+    Ed25519(BytesN<32>),
+    Secp256r1(BytesN<65>),
+    /// Another account, typically a contract, whose own authorization
+    /// stands in for a signature
+    Delegate(Address),
+}
+
+/// Everything checked when a signer authorizes, kept under one key so each
+/// signature costs a single storage read
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SignerRecord {
+    pub weight: u32,
+    /// Ledger sequence after which the signer stops working
+    pub expiry: Option<u32>,
+    /// Contracts the signer may authorize invocations of
+    pub scope: Option<Vec<Address>>,
+    /// Session epoch the signer is tied to
+    pub session: Option<u32>,
+}
+
+/// The signature scheme a signer verifies with
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SignerKind {
+    Ed25519,
+    Secp256r1,
+    Delegate,
+}
+
+/// `Signature::algorithm` tag of an ed25519 signature
+pub const ALGORITHM_ED25519: u32 = 0;
+/// `Signature::algorithm` tag of a secp256r1 signature
+pub const ALGORITHM_SECP256R1: u32 = 1;
+
+impl SignerKind {
+    /// The kind of signer a `Signature::algorithm` tag names, or `None` for
+    /// tags this release doesn't know
+    pub fn from_algorithm(algorithm: u32) -> Option<SignerKind> {
+        match algorithm {
+            ALGORITHM_ED25519 => Some(SignerKind::Ed25519),
+            ALGORITHM_SECP256R1 => Some(SignerKind::Secp256r1),
+            _ => None,
+        }
+    }
+
+    /// The kind's name, as keyed in `signer_kind_counts`
+    pub fn symbol(&self) -> Symbol {
+        match self {
+            SignerKind::Ed25519 => symbol_short!("ed25519"),
+            SignerKind::Secp256r1 => symbol_short!("secp256r1"),
+            SignerKind::Delegate => symbol_short!("delegate"),
+        }
+    }
+}
+
+impl SignerKey {
+    pub fn kind(&self) -> SignerKind {
+        match self {
+            SignerKey::Ed25519(_) => SignerKind::Ed25519,
+            SignerKey::Secp256r1(_) => SignerKind::Secp256r1,
+            SignerKey::Delegate(_) => SignerKind::Delegate,
+        }
+    }
+
+    /// Where the signer's record was kept before signers shared one map.
+    /// Delegates were introduced after, and never had a key of their own.
+    fn data_key(&self) -> Option<DataKey> {
+        match self {
+            SignerKey::Ed25519(public_key) => Some(DataKey::Signer(public_key.clone())),
+            SignerKey::Secp256r1(public_key) => Some(DataKey::Secp256r1Signer(public_key.clone())),
+            SignerKey::Delegate(_) => None,
+        }
+    }
 }
 
+/// The code version of this release, bumped with every released change
+pub const VERSION: u32 = 1;
+
+// Embedded in the WASM for deployment tooling. The version must stay in step
+// with `VERSION`.
+contractmeta!(key = "name", val = "account");
+contractmeta!(key = "version", val = "1");
+contractmeta!(key = "sep", val = "45");
+
+/// The storage layout version written by this release of the contract
+const STORAGE_VERSION: u32 = 6;
+
 trait Upgradable {
-    fn upgrade(e: Env, new_wasm_hash: BytesN<32>);
+    fn upgrade(
+        e: Env,
+        new_wasm_hash: BytesN<32>,
+        expected_version: Option<u32>,
+    ) -> Result<(), AccountError>;
+    fn migrate(e: Env);
 }
 
 #[contractimpl]
 impl Upgradable for Account {
-    fn upgrade(env: Env, new_wasm_hash: BytesN<32>) {
-        let admin: Address = env.storage().instance().get(&DataKey::Admin).unwrap();
-        admin.require_auth();
+    /// Replaces the contract code, optionally asserting the storage version
+    /// it is upgrading from
+    fn upgrade(
+        env: Env,
+        new_wasm_hash: BytesN<32>,
+        expected_version: Option<u32>,
+    ) -> Result<(), AccountError> {
+        let admin = require_admin(&env);
+
+        if env.current_contract_address().executable()
+            == Some(Executable::Wasm(new_wasm_hash.clone()))
+        {
+            return Err(AccountError::SameWasmHash);
+        }
+
+        if let Some(expected_version) = expected_version {
+            if storage_version(&env) != expected_version {
+                return Err(AccountError::VersionMismatch);
+            }
+        }
+
+        env.deployer()
+            .update_current_contract_wasm(new_wasm_hash.clone());
+        events::Upgraded {
+            new_wasm_hash,
+            admin,
+        }
+        .publish(&env);
+        Ok(())
+    }
+
+    /// Brings storage written by an earlier release up to `STORAGE_VERSION`
+    ///
+    /// Accounts created before storage was versioned report version 0.
+    fn migrate(env: Env) {
+        require_admin(&env);
+
+        let storage = env.storage().instance();
+        let version = storage_version(&env);
+        if version < 1 {
+            // Threshold was introduced alongside versioning, and unversioned
+            // accounts implicitly required a single signature
+            if !storage.has(&DataKey::Threshold) {
+                storage.set(&DataKey::Threshold, &1u32);
+            }
+        }
+        if version < 2 {
+            // Signers were stored as a bare weight with per-field keys beside it
+            for signer in signer_list(&env).iter() {
+                let Some(data_key) = signer.data_key() else {
+                    continue;
+                };
+                let Some(value) = storage.get::<_, Val>(&data_key) else {
+                    continue;
+                };
+                let Ok(weight) = u32::try_from_val(&env, &value) else {
+                    continue;
+                };
+                let expiry = DataKey::SignerExpiry(signer.clone());
+                let scope = DataKey::SignerScope(signer.clone());
+                let session = DataKey::SignerSession(signer.clone());
+                let record = SignerRecord {
+                    weight,
+                    expiry: storage.get(&expiry),
+                    scope: storage.get(&scope),
+                    session: storage.get(&session),
+                };
+                storage.set(&data_key, &record);
+                storage.remove(&expiry);
+                storage.remove(&scope);
+                storage.remove(&session);
+            }
+        }
+        if version < 3 {
+            // Each signer's record sat under its own key
+            let mut signers: Map<SignerKey, SignerRecord> = storage
+                .get(&DataKey::Signers)
+                .unwrap_or_else(|| Map::new(&env));
+            for signer in signer_list(&env).iter() {
+                let Some(data_key) = signer.data_key() else {
+                    continue;
+                };
+                if let Some(record) = storage.get::<_, SignerRecord>(&data_key) {
+                    signers.set(signer.clone(), record);
+                    storage.remove(&data_key);
+                }
+            }
+            storage.set(&DataKey::Signers, &signers);
+        }
+        if version < 4 {
+            // Signer records shared a single map in instance storage
+            let signers: Map<SignerKey, SignerRecord> = storage
+                .get(&DataKey::Signers)
+                .unwrap_or_else(|| Map::new(&env));
+            for (signer, record) in signers.iter() {
+                set_signer_record(&env, &signer, &record);
+            }
+            storage.remove(&DataKey::Signers);
+        }
+        if version < 5 {
+            // Accounts were constructed before the constructor marked them so
+            storage.set(&DataKey::Initialized, &());
+        }
+        if version < 6 {
+            // At most one recovery was pending, under its own key
+            if let Some(request) = storage.get::<_, RecoveryRequest>(&DataKey::PendingRecovery) {
+                storage.set(&DataKey::PendingRecoveries, &vec![&env, request]);
+                storage.remove(&DataKey::PendingRecovery);
+            }
+        }
 
-        env.deployer().update_current_contract_wasm(new_wasm_hash);
+        storage.set(&DataKey::Version, &STORAGE_VERSION);
     }
 }
 
+fn storage_version(env: &Env) -> u32 {
+    env.storage().instance().get(&DataKey::Version).unwrap_or(0)
+}
+
+/// Caps the amount of `token` a limited signer may transfer per window
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SpendLimit {
+    pub token: Address,
+    pub limit: i128,
+    pub window_ledgers: u32,
+}
+
+/// The address allowed to start a recovery, and how long it must wait
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Recovery {
+    pub recovery: Address,
+    pub delay_ledgers: u32,
+}
+
+/// A signer set replacement waiting out its challenge period
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RecoveryRequest {
+    pub new_signer: BytesN<32>,
+    pub effective_ledger: u32,
+}
+
+/// Caps how many authorizations succeed within each window
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RateLimit {
+    pub max_ops: u32,
+    pub window_ledgers: u32,
+}
+
+/// Authorizations counted in the current rate limit window
 #[contracttype]
 #[derive(Clone)]
+pub struct RateWindow {
+    pub start_ledger: u32,
+    pub count: u32,
+}
+
+/// An account's security posture at a glance, as returned by `policy`
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AccountPolicy {
+    pub threshold: u32,
+    /// The combined weight of the signers currently able to sign
+    pub total_weight: u32,
+    pub signer_count: u32,
+    pub frozen: bool,
+    pub has_master_signer: bool,
+    pub has_recovery: bool,
+}
+
+/// What a limited signer has left, as returned by `signer_status`. Each
+/// field is `None` when the signer isn't limited that way.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SignerStatus {
+    pub remaining_uses: Option<u32>,
+    /// How much of its spend limit's token it may still transfer in the
+    /// current window
+    pub remaining_spend: Option<i128>,
+    /// The last ledger it may sign in
+    pub expiry: Option<u32>,
+}
+
+/// A transfer authorized by a limited signer
+#[contracttype]
+#[derive(Clone)]
+pub struct SpendRecord {
+    pub ledger: u32,
+    pub amount: i128,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct Signature {
+    /// Which scheme the signature was made with, `ALGORITHM_ED25519` for
+    /// the ed25519 key in `public_key`
+    pub algorithm: u32,
     pub public_key: BytesN<32>,
     pub signature: BytesN<64>,
+    pub nonce: u64,
+}
+
+/// A WebAuthn (passkey) assertion
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Secp256r1Signature {
+    pub public_key: BytesN<65>,
+    pub authenticator_data: Bytes,
+    pub client_data_json: Bytes,
+    pub signature: BytesN<64>,
+    pub nonce: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum AccountSignature {
+    Ed25519(Signature),
+    Secp256r1(Secp256r1Signature),
+    /// Names a delegate signer, which must authorize the signed message itself
+    Delegate(Address),
+}
+
+/// Algorithm tag, public key, signature and nonce
+const ED25519_SIGNATURE_BYTES: u32 = 4 + 32 + 64 + 8;
+/// Public key, signature and nonce, before the authenticator data and
+/// client data
+const SECP256R1_SIGNATURE_BYTES: u32 = 65 + 64 + 8;
+/// The delegate's address
+const DELEGATE_SIGNATURE_BYTES: u32 = 32;
+
+impl AccountSignature {
+    fn signer_key(&self) -> SignerKey {
+        match self {
+            AccountSignature::Ed25519(signature) => {
+                SignerKey::Ed25519(signature.public_key.clone())
+            }
+            AccountSignature::Secp256r1(signature) => {
+                SignerKey::Secp256r1(signature.public_key.clone())
+            }
+            AccountSignature::Delegate(delegate) => SignerKey::Delegate(delegate.clone()),
+        }
+    }
+
+    /// Whether the signature was made for the account's current `nonce`.
+    /// A delegate's authorization carries the host's own replay protection.
+    fn nonce_matches(&self, nonce: u64) -> bool {
+        match self {
+            AccountSignature::Ed25519(signature) => signature.nonce == nonce,
+            AccountSignature::Secp256r1(signature) => signature.nonce == nonce,
+            AccountSignature::Delegate(_) => true,
+        }
+    }
+
+    /// Approximate encoded size of the signature in bytes: its fixed-size
+    /// fields plus any variable-length passkey data
+    fn approx_len(&self) -> u32 {
+        match self {
+            AccountSignature::Ed25519(_) => ED25519_SIGNATURE_BYTES,
+            AccountSignature::Secp256r1(signature) => SECP256R1_SIGNATURE_BYTES
+                .saturating_add(signature.authenticator_data.len())
+                .saturating_add(signature.client_data_json.len()),
+            AccountSignature::Delegate(_) => DELEGATE_SIGNATURE_BYTES,
+        }
+    }
+
+    /// Checks a signature's algorithm tag is known and names the kind of
+    /// signer it is for
+    fn check_algorithm(&self) -> Result<(), AccountError> {
+        let AccountSignature::Ed25519(signature) = self else {
+            return Ok(());
+        };
+        match SignerKind::from_algorithm(signature.algorithm) {
+            None => Err(AccountError::UnsupportedAlgorithm),
+            Some(SignerKind::Ed25519) => Ok(()),
+            Some(_) => Err(AccountError::MalformedSignature),
+        }
+    }
+
+    /// Whether the signature's fields have the lengths and encodings
+    /// verification expects
+    ///
+    /// Fixed-size fields are already enforced when the signature is decoded,
+    /// leaving a passkey's variable-length data and point encoding to check.
+    fn well_formed(&self) -> bool {
+        match self {
+            AccountSignature::Ed25519(_) | AccountSignature::Delegate(_) => true,
+            AccountSignature::Secp256r1(signature) => webauthn::well_formed(
+                &signature.public_key,
+                &signature.authenticator_data,
+                &signature.client_data_json,
+            ),
+        }
+    }
+
+    /// Verifies the signature for an authorization at the account's current
+    /// `nonce`. Keys sign `message` bound to the nonce, so a signature can't
+    /// be resubmitted for a later one, while a delegate authorizes `message`
+    /// itself under the host's own replay protection.
+    fn verify_at(
+        &self,
+        env: &Env,
+        message: &BytesN<32>,
+        nonce: u64,
+        depth: u32,
+    ) -> Result<(), AccountError> {
+        match self {
+            AccountSignature::Delegate(_) => self.verify(env, message, depth),
+            _ => self.verify(env, &bind_nonce(env, message.clone(), nonce), depth),
+        }
+    }
+
+    /// Verifies the signature over `message`, the payload signers sign, for
+    /// an account checking auth `depth` delegations down
+    fn verify(&self, env: &Env, message: &BytesN<32>, depth: u32) -> Result<(), AccountError> {
+        match self {
+            AccountSignature::Ed25519(signature) => {
+                env.crypto().ed25519_verify(
+                    &signature.public_key,
+                    message.as_ref(),
+                    &signature.signature,
+                );
+            }
+            AccountSignature::Secp256r1(signature) => {
+                if !webauthn::client_data_matches(message.as_ref(), &signature.client_data_json) {
+                    return Err(AccountError::InvalidClientData);
+                }
+                if !webauthn::user_present(&signature.authenticator_data) {
+                    return Err(AccountError::UserNotPresent);
+                }
+
+                let digest = webauthn::signed_digest(
+                    env,
+                    &signature.authenticator_data,
+                    &signature.client_data_json,
+                );
+                env.crypto()
+                    .secp256r1_verify(&signature.public_key, &digest, &signature.signature);
+            }
+            AccountSignature::Delegate(delegate) => {
+                // The delegate authorizes this account's `__check_auth` for
+                // `message`, so a delegate that is itself an account runs
+                // its own `__check_auth` in turn, one level deeper. The host
+                // forbids re-entry, so a delegation cycle back to an account
+                // already checking auth fails rather than recursing.
+                delegate.require_auth_for_args(vec![env, message.to_val(), (depth + 1).into()]);
+            }
+        }
+
+        Ok(())
+    }
 }
 
 #[contracterror]
-#[derive(Clone)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum AccountError {
     UnknownSigner = 1,
     TooManySignatures = 2,
+    ThresholdNotMet = 3,
+    DuplicateSigner = 4,
+    InvalidClientData = 5,
+    LastSigner = 6,
+    NoPendingAdmin = 7,
+    ContextNotAllowed = 8,
+    SignerExpired = 9,
+    BadNonce = 10,
+    NoSignatures = 11,
+    VersionMismatch = 12,
+    NoSigners = 13,
+    SpendLimitExceeded = 14,
+    NoRecovery = 15,
+    NoPendingRecovery = 16,
+    RecoveryNotReady = 17,
+    AccountFrozen = 18,
+    NotAuthorized = 19,
+    TooManyContexts = 20,
+    SessionRevoked = 21,
+    SameWasmHash = 22,
+    RateLimited = 23,
+    NoAdmin = 24,
+    MalformedSignature = 25,
+    TooManySigners = 26,
+    SignerRevoked = 27,
+    SelfDelegation = 28,
+    ThresholdUnsatisfiable = 29,
+    AccountClosed = 30,
+    AlreadyInitialized = 31,
+    UnsupportedAlgorithm = 32,
+    SignerExhausted = 33,
+    SignaturesTooLarge = 34,
+    InvalidPublicKey = 35,
+    ContextMismatch = 36,
+    WeightOverflow = 37,
+    CoSignerRequired = 38,
+    ChangeTooSoon = 39,
+    TokenFrozen = 40,
+    TooManyRecoveryRequests = 41,
+    InvalidRecoveryDelay = 42,
+    DelegationTooDeep = 43,
+    UserNotPresent = 44,
 }
 
-#[contractimpl]
-impl Account {
-    pub fn __constructor(env: Env, admin: Address, signer: BytesN<32>) {
-        env.storage().instance().set(&DataKey::Admin, &admin);
-        env.storage().instance().set(&DataKey::Signer(signer), &());
+/// A short description of the `AccountError` with discriminant `code`, for
+/// client tooling to show in place of the bare number
+pub fn error_message(code: u32) -> &'static str {
+    match code {
+        1 => "signer is not registered",
+        2 => "too many signatures",
+        3 => "signature threshold not met",
+        4 => "signer signed more than once",
+        5 => "client data does not match the payload",
+        6 => "cannot remove the last signer",
+        7 => "no admin transfer is pending",
+        8 => "signer may not authorize this invocation",
+        9 => "signer has expired",
+        10 => "signature nonce is not the account's current nonce",
+        11 => "no signatures provided",
+        12 => "storage version does not match",
+        13 => "at least one signer is required",
+        14 => "spend limit exceeded",
+        15 => "no recovery is configured",
+        16 => "no recovery is pending",
+        17 => "recovery delay has not elapsed",
+        18 => "account is frozen",
+        19 => "caller is not authorized",
+        20 => "too many invocations in one authorization",
+        21 => "signer's session has been revoked",
+        22 => "contract is already running this WASM",
+        23 => "authorization rate limit reached",
+        24 => "account has no admin",
+        25 => "signature is malformed",
+        26 => "too many signers",
+        27 => "signer has been revoked",
+        28 => "account cannot delegate to itself",
+        29 => "threshold is out of reach of the signers",
+        30 => "account has been closed",
+        31 => "account is already initialized",
+        32 => "signature algorithm is not supported",
+        33 => "signer has no authorizations left",
+        34 => "signatures exceed the size cap",
+        35 => "public key is not a usable ed25519 key",
+        36 => "authorization lacks a call the signer requires",
+        37 => "signer weights exceed the maximum total",
+        38 => "signer's co-signer did not sign",
+        39 => "signer change cooldown has not elapsed",
+        40 => "transfers of the token are frozen",
+        41 => "too many recoveries are pending",
+        42 => "recovery delay is out of range",
+        43 => "delegation chain is too deep",
+        44 => "authenticator did not confirm user presence",
+        _ => "unknown error",
     }
 }
 
-#[contractimpl]
-impl CustomAccountInterface for Account {
-    type Error = AccountError;
-    type Signature = Vec<Signature>;
+const DAY_IN_LEDGERS: u32 = 17280;
+/// Instance storage is extended to this many ledgers whenever it's used
+const INSTANCE_BUMP_AMOUNT: u32 = 30 * DAY_IN_LEDGERS;
+/// Instance storage is only extended once its TTL drops below this
+const INSTANCE_LIFETIME_THRESHOLD: u32 = INSTANCE_BUMP_AMOUNT - DAY_IN_LEDGERS;
 
-    fn __check_auth(
-        env: Env,
-        signature_payload: Hash<32>,
-        signatures: Self::Signature,
-        _auth_context: Vec<Context>,
-    ) -> Result<(), AccountError> {
-        if signatures.len() > 1 {
-            return Err(AccountError::TooManySignatures);
+fn extend_instance_ttl(env: &Env) {
+    env.storage()
+        .instance()
+        .extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT);
+}
+
+/// Shortest recovery delay, leaving the signers time to veto
+const MIN_RECOVERY_DELAY: u32 = DAY_IN_LEDGERS;
+/// Longest recovery delay, so recovery stays within reach of the instance
+/// storage it waits in
+const MAX_RECOVERY_DELAY: u32 = INSTANCE_BUMP_AMOUNT;
+
+/// A signer's record is extended to this many ledgers whenever it's written
+/// or authorizes
+const SIGNER_BUMP_AMOUNT: u32 = 30 * DAY_IN_LEDGERS;
+/// A signer's record is only extended once its TTL drops below this
+const SIGNER_LIFETIME_THRESHOLD: u32 = SIGNER_BUMP_AMOUNT - DAY_IN_LEDGERS;
+
+fn extend_signer_ttl(env: &Env, signer: &SignerKey) {
+    env.storage().persistent().extend_ttl(
+        &DataKey::PersistentSigner(signer.clone()),
+        SIGNER_LIFETIME_THRESHOLD,
+        SIGNER_BUMP_AMOUNT,
+    );
+}
+
+/// The current admin, the account itself in `SelfAuth` mode, or `None` once
+/// it has been renounced
+fn current_admin(env: &Env) -> Option<Address> {
+    let storage = env.storage().instance();
+    if storage.has(&DataKey::SelfAuth) {
+        return Some(env.current_contract_address());
+    }
+    storage.get(&DataKey::Admin)
+}
+
+/// The current admin, failing with `NoAdmin` once it has been renounced
+fn stored_admin(env: &Env) -> Address {
+    let Some(admin) = current_admin(env) else {
+        panic_with_error!(env, AccountError::NoAdmin);
+    };
+    admin
+}
+
+/// Requires the admin's authorization, keeping the account alive as a side
+/// effect of any admin mutation
+fn require_admin(env: &Env) -> Address {
+    let admin = stored_admin(env);
+    admin.require_auth();
+
+    extend_instance_ttl(env);
+    admin
+}
+
+fn signer_list(env: &Env) -> Vec<SignerKey> {
+    env.storage()
+        .instance()
+        .get(&DataKey::SignerList)
+        .unwrap_or(Vec::new(env))
+}
+
+/// A signer's record, read from persistent storage
+///
+/// Each signer is its own ledger entry, so a signer in regular use keeps
+/// itself alive without extending the others, and the instance entry no
+/// longer grows with the signer set. A signer left unused for longer than
+/// its TTL is archived and must be restored before it can sign again.
+fn signer_record(env: &Env, signer: &SignerKey) -> Option<SignerRecord> {
+    env.storage()
+        .persistent()
+        .get(&DataKey::PersistentSigner(signer.clone()))
+}
+
+fn set_signer_record(env: &Env, signer: &SignerKey, record: &SignerRecord) {
+    env.storage()
+        .persistent()
+        .set(&DataKey::PersistentSigner(signer.clone()), record);
+    extend_signer_ttl(env, signer);
+}
+
+/// Canonical encodings of the ed25519 points of small order, including the
+/// all-zero key. No one holds a private key for them, and signatures under
+/// them can be forged without one.
+const WEAK_ED25519_KEYS: [[u8; 32]; 8] = [
+    [
+        0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00,
+    ],
+    [
+        0xec, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+        0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+        0xff, 0x7f,
+    ],
+    [
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00,
+    ],
+    [
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x80,
+    ],
+    [
+        0xc7, 0x17, 0x6a, 0x70, 0x3d, 0x4d, 0xd8, 0x4f, 0xba, 0x3c, 0x0b, 0x76, 0x0d, 0x10, 0x67,
+        0x0f, 0x2a, 0x20, 0x53, 0xfa, 0x2c, 0x39, 0xcc, 0xc6, 0x4e, 0xc7, 0xfd, 0x77, 0x92, 0xac,
+        0x03, 0x7a,
+    ],
+    [
+        0xc7, 0x17, 0x6a, 0x70, 0x3d, 0x4d, 0xd8, 0x4f, 0xba, 0x3c, 0x0b, 0x76, 0x0d, 0x10, 0x67,
+        0x0f, 0x2a, 0x20, 0x53, 0xfa, 0x2c, 0x39, 0xcc, 0xc6, 0x4e, 0xc7, 0xfd, 0x77, 0x92, 0xac,
+        0x03, 0xfa,
+    ],
+    [
+        0x26, 0xe8, 0x95, 0x8f, 0xc2, 0xb2, 0x27, 0xb0, 0x45, 0xc3, 0xf4, 0x89, 0xf2, 0xef, 0x98,
+        0xf0, 0xd5, 0xdf, 0xac, 0x05, 0xd3, 0xc6, 0x33, 0x39, 0xb1, 0x38, 0x02, 0x88, 0x6d, 0x53,
+        0xfc, 0x05,
+    ],
+    [
+        0x26, 0xe8, 0x95, 0x8f, 0xc2, 0xb2, 0x27, 0xb0, 0x45, 0xc3, 0xf4, 0x89, 0xf2, 0xef, 0x98,
+        0xf0, 0xd5, 0xdf, 0xac, 0x05, 0xd3, 0xc6, 0x33, 0x39, 0xb1, 0x38, 0x02, 0x88, 0x6d, 0x53,
+        0xfc, 0x85,
+    ],
+];
+
+/// Checks `public_key` isn't a key no one could hold, which can never sign
+/// safely and would waste a signer slot
+fn check_public_key(public_key: &BytesN<32>) -> Result<(), AccountError> {
+    if WEAK_ED25519_KEYS.contains(&public_key.to_array()) {
+        return Err(AccountError::InvalidPublicKey);
+    }
+    Ok(())
+}
+
+/// Most signers `list_signers_paged` returns at once
+pub const MAX_SIGNERS_PAGE: u32 = 16;
+
+/// Signers an account may hold unless configured otherwise
+const DEFAULT_MAX_SIGNERS: u32 = 20;
+
+fn max_signers(env: &Env) -> u32 {
+    env.storage()
+        .instance()
+        .get(&DataKey::MaxSigners)
+        .unwrap_or(DEFAULT_MAX_SIGNERS)
+}
+
+fn register_signer(
+    env: &Env,
+    signer: &SignerKey,
+    weight: u32,
+    expiry: Option<u32>,
+) -> Result<(), AccountError> {
+    let storage = env.storage().instance();
+    if let SignerKey::Ed25519(public_key) = signer {
+        check_public_key(public_key)?;
+        if storage.has(&DataKey::Revoked(public_key.clone())) {
+            return Err(AccountError::SignerRevoked);
         }
+        // Registering a rotated-out key again keeps it for good
+        storage.remove(&DataKey::SignerGrace(public_key.clone()));
+    }
+    let previous = signer_record(env, signer).map_or(0, |record| record.weight);
+    if total_weight(env)
+        .saturating_sub(previous)
+        .checked_add(weight)
+        .is_none_or(|total| total > MAX_TOTAL_WEIGHT)
+    {
+        return Err(AccountError::WeightOverflow);
+    }
+    let record = match signer_record(env, signer) {
+        Some(record) => SignerRecord {
+            weight,
+            expiry,
+            ..record
+        },
+        None => {
+            let mut signers = signer_list(env);
+            if signers.len() >= max_signers(env) {
+                return Err(AccountError::TooManySigners);
+            }
+            signers.push_back(signer.clone());
+            storage.set(&DataKey::SignerList, &signers);
 
-        let signature = signatures.get_unchecked(0);
+            events::SignerAdded {
+                signer: signer.clone(),
+            }
+            .publish(env);
 
-        if env
-            .storage()
-            .instance()
-            .get::<_, ()>(&DataKey::Signer(signature.public_key.clone()))
-            .is_none()
-        {
-            return Err(AccountError::UnknownSigner);
+            SignerRecord {
+                weight,
+                expiry,
+                scope: None,
+                session: None,
+            }
         }
+    };
+    set_signer_record(env, signer, &record);
+    Ok(())
+}
 
-        env.crypto().ed25519_verify(
-            &signature.public_key,
-            &signature_payload.into(),
-            &signature.signature,
-        );
+fn unregister_signer(env: &Env, signer: &SignerKey) -> Result<(), AccountError> {
+    let storage = env.storage().instance();
+    if signer_record(env, signer).is_none() {
+        return Err(AccountError::UnknownSigner);
+    }
 
-        Ok(())
+    // Removing the last signer would leave the account unable to authorize
+    let mut signers = signer_list(env);
+    if signers.len() <= 1 {
+        return Err(AccountError::LastSigner);
+    }
+
+    if let Some(index) = signers.first_index_of(signer) {
+        signers.remove(index);
+    }
+    storage.set(&DataKey::SignerList, &signers);
+    forget_signer(env, signer);
+
+    let threshold: u32 = storage.get(&DataKey::Threshold).unwrap();
+    check_satisfiable(env, threshold)
+}
+
+/// Most weight the signers may hold together, keeping every sum of their
+/// weights well clear of overflow
+pub const MAX_TOTAL_WEIGHT: u32 = 1_000_000;
+
+/// The combined weight of every registered signer
+fn total_weight(env: &Env) -> u32 {
+    signer_list(env)
+        .iter()
+        .filter(|signer| !past_grace(env, signer))
+        .filter_map(|signer| signer_record(env, &signer))
+        .fold(0, |total: u32, record| total.saturating_add(record.weight))
+}
+
+/// Checks the signers could still meet `threshold` together, so the account
+/// can't lock itself out
+fn check_satisfiable(env: &Env, threshold: u32) -> Result<(), AccountError> {
+    if total_weight(env) < threshold {
+        return Err(AccountError::ThresholdUnsatisfiable);
+    }
+    Ok(())
+}
+
+/// Drops everything stored about `signer`, leaving the signer list untouched
+fn forget_signer(env: &Env, signer: &SignerKey) {
+    erase_signer(env, signer);
+
+    events::SignerRemoved {
+        signer: signer.clone(),
+    }
+    .publish(env);
+}
+
+/// `forget_signer` without the event, for callers reporting the change
+/// their own way
+fn erase_signer(env: &Env, signer: &SignerKey) {
+    let storage = env.storage().instance();
+    env.storage()
+        .persistent()
+        .remove(&DataKey::PersistentSigner(signer.clone()));
+    if let SignerKey::Ed25519(public_key) = signer {
+        storage.remove(&DataKey::SpendLimit(public_key.clone()));
+        storage.remove(&DataKey::SpendHistory(public_key.clone()));
+        storage.remove(&DataKey::SignerLabel(public_key.clone()));
+        storage.remove(&DataKey::SignerGrace(public_key.clone()));
+        storage.remove(&DataKey::SignerUses(public_key.clone()));
+        storage.remove(&DataKey::SignerFnScope(public_key.clone()));
+        storage.remove(&DataKey::SignerRequiredFns(public_key.clone()));
+        storage.remove(&DataKey::CoSigner(public_key.clone()));
+        storage.remove(&DataKey::SignerTxCap(public_key.clone()));
+    }
+}
+
+/// Whether every invocation being authorized is a call of this account's own
+/// `fn_name`
+fn only_calls_self(env: &Env, auth_context: &Vec<Context>, fn_name: &str) -> bool {
+    !auth_context.is_empty()
+        && auth_context.iter().all(|context| {
+            matches!(
+                context,
+                Context::Contract(context)
+                    if context.contract == env.current_contract_address()
+                        && context.fn_name == Symbol::new(env, fn_name)
+            )
+        })
+}
+
+/// Whether every invocation being authorized is this account vetoing a
+/// recovery, which any single registered signer may do regardless of the
+/// threshold
+fn only_cancels_recovery(env: &Env, auth_context: &Vec<Context>) -> bool {
+    only_calls_self(env, auth_context, "cancel_recovery")
+}
+
+/// Checks a scoped signer is only authorizing calls to its permitted contracts
+fn check_scope(record: &SignerRecord, auth_context: &Vec<Context>) -> Result<(), AccountError> {
+    let Some(contracts) = &record.scope else {
+        return Ok(());
+    };
+
+    for context in auth_context.iter() {
+        if !scope_permits(contracts, &context) {
+            return Err(AccountError::ContextNotAllowed);
+        }
+    }
+
+    Ok(())
+}
+
+fn scope_permits(contracts: &Vec<Address>, context: &Context) -> bool {
+    matches!(context, Context::Contract(context) if contracts.contains(&context.contract))
+}
+
+fn signer_fn_scope(env: &Env, signer: &SignerKey) -> Option<Vec<(Address, Symbol)>> {
+    let SignerKey::Ed25519(public_key) = signer else {
+        return None;
+    };
+    env.storage()
+        .instance()
+        .get(&DataKey::SignerFnScope(public_key.clone()))
+}
+
+/// Checks a function-scoped signer is only authorizing calls to its
+/// permitted contract functions
+fn check_fn_scope(
+    env: &Env,
+    signer: &SignerKey,
+    auth_context: &Vec<Context>,
+) -> Result<(), AccountError> {
+    let Some(functions) = signer_fn_scope(env, signer) else {
+        return Ok(());
+    };
+
+    for context in auth_context.iter() {
+        if !fn_scope_permits(&functions, &context) {
+            return Err(AccountError::ContextNotAllowed);
+        }
+    }
+
+    Ok(())
+}
+
+fn fn_scope_permits(functions: &Vec<(Address, Symbol)>, context: &Context) -> bool {
+    matches!(
+        context,
+        Context::Contract(context)
+            if functions.contains(&(context.contract.clone(), context.fn_name.clone()))
+    )
+}
+
+/// Whether a signer's limits depend on the invocations it authorizes, and
+/// so can't be applied to bare data
+fn context_restricted(env: &Env, signer: &SignerKey, record: &SignerRecord) -> bool {
+    let limited = match signer {
+        SignerKey::Ed25519(public_key) => {
+            let storage = env.storage().instance();
+            storage.has(&DataKey::SpendLimit(public_key.clone()))
+                || storage.has(&DataKey::SignerTxCap(public_key.clone()))
+        }
+        _ => false,
+    };
+    record.scope.is_some()
+        || signer_fn_scope(env, signer).is_some()
+        || signer_required_fns(env, signer).is_some()
+        || limited
+}
+
+fn co_signer(env: &Env, signer: &SignerKey) -> Option<SignerKey> {
+    let SignerKey::Ed25519(public_key) = signer else {
+        return None;
+    };
+    env.storage()
+        .instance()
+        .get(&DataKey::CoSigner(public_key.clone()))
+        .map(SignerKey::Ed25519)
+}
+
+fn signer_required_fns(env: &Env, signer: &SignerKey) -> Option<Vec<(Address, Symbol)>> {
+    let SignerKey::Ed25519(public_key) = signer else {
+        return None;
+    };
+    env.storage()
+        .instance()
+        .get(&DataKey::SignerRequiredFns(public_key.clone()))
+}
+
+/// Checks every contract function a signer requires is among the calls
+/// being authorized
+fn check_required_fns(
+    env: &Env,
+    signer: &SignerKey,
+    auth_context: &Vec<Context>,
+) -> Result<(), AccountError> {
+    let Some(functions) = signer_required_fns(env, signer) else {
+        return Ok(());
+    };
+
+    for (contract, fn_name) in functions.iter() {
+        let present = auth_context.iter().any(|context| {
+            matches!(
+                context,
+                Context::Contract(context)
+                    if context.contract == contract && context.fn_name == fn_name
+            )
+        });
+        if !present {
+            return Err(AccountError::ContextMismatch);
+        }
+    }
+
+    Ok(())
+}
+
+/// Decodes the `(from, to, amount)` of a SEP-41 token `transfer` invocation,
+/// or `None` for any other context
+///
+/// A muxed `to` is reduced to its underlying address.
+pub(crate) fn decode_transfer(context: &Context) -> Option<(Address, Address, i128)> {
+    let Context::Contract(context) = context else {
+        return None;
+    };
+    if context.fn_name != symbol_short!("transfer") || context.args.len() != 3 {
+        return None;
+    }
+
+    let env = context.args.env();
+    let from = Address::try_from_val(env, &context.args.get(0)?).ok()?;
+    let to = MuxedAddress::try_from_val(env, &context.args.get(1)?).ok()?;
+    let amount = i128::try_from_val(env, &context.args.get(2)?).ok()?;
+    Some((from, to.address(), amount))
+}
+
+/// Checks none of the invocations is a transfer of a frozen token
+fn check_frozen_tokens(env: &Env, auth_context: &Vec<Context>) -> Result<(), AccountError> {
+    let storage = env.storage().instance();
+    for context in auth_context.iter() {
+        if let Context::Contract(contract_context) = &context {
+            if decode_transfer(&context).is_some()
+                && storage.has(&DataKey::FrozenToken(contract_context.contract.clone()))
+            {
+                return Err(AccountError::TokenFrozen);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The cap on what `signer` may transfer in one authorization, `None` when it
+/// has none
+fn signer_tx_cap(env: &Env, signer: &SignerKey) -> Option<i128> {
+    let SignerKey::Ed25519(public_key) = signer else {
+        return None;
+    };
+    env.storage()
+        .instance()
+        .get(&DataKey::SignerTxCap(public_key.clone()))
+}
+
+/// Checks the transfers being authorized stay within the highest cap of the
+/// counted signers, unless one of them is uncapped
+fn check_tx_caps(
+    env: &Env,
+    satisfied: &Map<SignerKey, bool>,
+    auth_context: &Vec<Context>,
+) -> Result<(), AccountError> {
+    let mut cap: Option<i128> = None;
+    for (signer, counted) in satisfied.iter() {
+        if !counted {
+            continue;
+        }
+        let Some(signer_cap) = signer_tx_cap(env, &signer) else {
+            return Ok(());
+        };
+        cap = Some(cap.map_or(signer_cap, |cap| cap.max(signer_cap)));
+    }
+    let Some(cap) = cap else {
+        return Ok(());
+    };
+
+    let mut amount: i128 = 0;
+    for context in auth_context.iter() {
+        if let Some((_, _, transferred)) = decode_transfer(&context) {
+            amount = amount
+                .checked_add(transferred.max(0))
+                .ok_or(AccountError::SpendLimitExceeded)?;
+        }
+    }
+    if amount > cap {
+        return Err(AccountError::SpendLimitExceeded);
+    }
+    Ok(())
+}
+
+/// Transfers a limited signer authorized inside its rolling window, and
+/// their total
+fn spend_window(
+    env: &Env,
+    public_key: &BytesN<32>,
+    policy: &SpendLimit,
+) -> Result<(Vec<SpendRecord>, i128), AccountError> {
+    let now = env.ledger().sequence();
+    let mut history: Vec<SpendRecord> = Vec::new(env);
+    let mut spent: i128 = 0;
+    for record in env
+        .storage()
+        .instance()
+        .get::<_, Vec<SpendRecord>>(&DataKey::SpendHistory(public_key.clone()))
+        .unwrap_or_else(|| Vec::new(env))
+        .iter()
+    {
+        if now.saturating_sub(record.ledger) < policy.window_ledgers {
+            spent = spent
+                .checked_add(record.amount)
+                .ok_or(AccountError::SpendLimitExceeded)?;
+            history.push_back(record);
+        }
+    }
+    Ok((history, spent))
+}
+
+/// Checks a limited signer is only authorizing transfers of its token, and
+/// that they fit within the remaining allowance for the rolling window
+///
+/// Returns the allowance the signer would have left if charged, or `None`
+/// when it is not spend-limited. Nothing is charged here, see
+/// `charged_signer`.
+fn check_spend_limit(
+    env: &Env,
+    signer: &SignerKey,
+    auth_context: &Vec<Context>,
+) -> Result<Option<i128>, AccountError> {
+    let SignerKey::Ed25519(public_key) = signer else {
+        return Ok(None);
+    };
+    let storage = env.storage().instance();
+    let Some(policy) = storage.get::<_, SpendLimit>(&DataKey::SpendLimit(public_key.clone()))
+    else {
+        return Ok(None);
+    };
+
+    let mut amount: i128 = 0;
+    for context in auth_context.iter() {
+        let Context::Contract(contract_context) = &context else {
+            return Err(AccountError::ContextNotAllowed);
+        };
+        if contract_context.contract != policy.token {
+            return Err(AccountError::ContextNotAllowed);
+        }
+        let (_, _, transferred) = decode_transfer(&context)
+            .filter(|(_, _, transferred)| *transferred >= 0)
+            .ok_or(AccountError::ContextNotAllowed)?;
+        amount = amount
+            .checked_add(transferred)
+            .ok_or(AccountError::SpendLimitExceeded)?;
     }
+
+    // Only transfers inside the window count towards the limit
+    let (_, spent) = spend_window(env, public_key, &policy)?;
+    let spent = spent
+        .checked_add(amount)
+        .ok_or(AccountError::SpendLimitExceeded)?;
+    if spent > policy.limit {
+        return Err(AccountError::SpendLimitExceeded);
+    }
+    Ok(Some(policy.limit - spent))
+}
+
+/// Records the transfers being authorized against a limited signer's
+/// window, once `check_spend_limit` has passed them
+fn record_spend(env: &Env, signer: &SignerKey, auth_context: &Vec<Context>) {
+    let SignerKey::Ed25519(public_key) = signer else {
+        return;
+    };
+    let storage = env.storage().instance();
+    let Some(policy) = storage.get::<_, SpendLimit>(&DataKey::SpendLimit(public_key.clone()))
+    else {
+        return;
+    };
+    let Ok((mut history, _)) = spend_window(env, public_key, &policy) else {
+        return;
+    };
+    let amount = auth_context
+        .iter()
+        .filter_map(|context| decode_transfer(&context))
+        .fold(0i128, |amount, (_, _, transferred)| {
+            amount.saturating_add(transferred)
+        });
+    history.push_back(SpendRecord {
+        ledger: env.ledger().sequence(),
+        amount,
+    });
+    storage.set(&DataKey::SpendHistory(public_key.clone()), &history);
+}
+
+/// The one counted signer a limited authorization is charged to, given
+/// what each would have left once charged, `None` meaning unlimited
+///
+/// An unlimited signer takes precedence, so nobody is charged when one
+/// signs. Otherwise the signer with the most left is charged, the first in
+/// key order on a tie.
+fn charged_signer(
+    satisfied: &Map<SignerKey, bool>,
+    remaining: impl Fn(&SignerKey) -> Option<i128>,
+) -> Option<SignerKey> {
+    let mut charged: Option<(SignerKey, i128)> = None;
+    for (signer, counted) in satisfied.iter() {
+        if !counted {
+            continue;
+        }
+        let left = remaining(&signer)?;
+        if charged.as_ref().is_none_or(|(_, most)| left > *most) {
+            charged = Some((signer, left));
+        }
+    }
+    charged.map(|(signer, _)| signer)
+}
+
+/// Counts an authorization against the rate limit, starting a new window
+/// once the current one has passed
+fn check_rate_limit(env: &Env) -> Result<(), AccountError> {
+    let storage = env.storage().instance();
+    let Some(limit) = storage.get::<_, RateLimit>(&DataKey::RateLimit) else {
+        return Ok(());
+    };
+
+    let now = env.ledger().sequence();
+    let window = match storage.get::<_, RateWindow>(&DataKey::RateWindow) {
+        Some(window) if now.saturating_sub(window.start_ledger) < limit.window_ledgers => window,
+        _ => RateWindow {
+            start_ledger: now,
+            count: 0,
+        },
+    };
+    if window.count >= limit.max_ops {
+        return Err(AccountError::RateLimited);
+    }
+
+    storage.set(
+        &DataKey::RateWindow,
+        &RateWindow {
+            count: window.count + 1,
+            ..window
+        },
+    );
+    Ok(())
 }
+
+/// Publishes why `signer`'s signature was turned away, for monitoring keys
+/// probing the account, and passes the error through
+///
+/// The event costs every rejected authorization some extra fees, and is only
+/// visible in the failed transaction's diagnostics since rejecting the
+/// authorization rolls back its events. Signatures that fail cryptographic
+/// verification trap in the host before any event can be published.
+fn rejected(env: &Env, signer: &SignerKey, error: AccountError) -> AccountError {
+    let reason = match error {
+        AccountError::UnknownSigner => symbol_short!("unknown"),
+        AccountError::DuplicateSigner => symbol_short!("duplicate"),
+        AccountError::BadNonce => symbol_short!("nonce"),
+        AccountError::SignerExpired => symbol_short!("expired"),
+        AccountError::SessionRevoked => symbol_short!("revoked"),
+        AccountError::ContextNotAllowed => symbol_short!("context"),
+        AccountError::ContextMismatch => symbol_short!("mismatch"),
+        AccountError::CoSignerRequired => symbol_short!("cosigner"),
+        AccountError::SpendLimitExceeded => symbol_short!("spend"),
+        AccountError::InvalidClientData => symbol_short!("client"),
+        AccountError::UserNotPresent => symbol_short!("presence"),
+        AccountError::SignerExhausted => symbol_short!("exhausted"),
+        _ => symbol_short!("other"),
+    };
+    events::AuthRejected {
+        reason,
+        signer: signer.clone(),
+    }
+    .publish(env);
+    error
+}
+
+/// Books a successful authorization: counts it against the rate limit,
+/// consumes the nonce it was signed with and notes when it happened
+fn record_auth(env: &Env, nonce: u64, signers: Vec<SignerKey>) -> Result<(), AccountError> {
+    check_rate_limit(env)?;
+
+    let storage = env.storage().instance();
+    storage.set(&DataKey::Nonce, &(nonce + 1));
+    storage.set(&DataKey::LastAuth, &env.ledger().timestamp());
+    extend_instance_ttl(env);
+    events::AuthSucceeded {
+        signers: Vec::from_iter(env, signers.iter().map(|signer| fingerprint(env, &signer))),
+    }
+    .publish(env);
+    Ok(())
+}
+
+/// The first four bytes of the sha256 of a signer's key: the ed25519 or
+/// uncompressed secp256r1 public key, or a delegate's address as XDR
+fn fingerprint(env: &Env, signer: &SignerKey) -> BytesN<4> {
+    let key: Bytes = match signer {
+        SignerKey::Ed25519(public_key) => public_key.clone().into(),
+        SignerKey::Secp256r1(public_key) => public_key.clone().into(),
+        SignerKey::Delegate(delegate) => delegate.to_xdr(env),
+    };
+    let digest = env.crypto().sha256(&key).to_array();
+    BytesN::from_array(env, &[digest[0], digest[1], digest[2], digest[3]])
+}
+
+/// Recoveries that may be pending at once unless configured otherwise
+const DEFAULT_MAX_RECOVERY_REQUESTS: u32 = 4;
+
+fn pending_recoveries(env: &Env) -> Vec<RecoveryRequest> {
+    env.storage()
+        .instance()
+        .get(&DataKey::PendingRecoveries)
+        .unwrap_or_else(|| Vec::new(env))
+}
+
+/// Invocations a single authorization may cover unless configured otherwise
+const DEFAULT_MAX_CONTEXTS: u32 = 16;
+
+/// Levels of delegation allowed below an account unless configured otherwise
+const DEFAULT_MAX_DELEGATION_DEPTH: u32 = 3;
+
+fn max_delegation_depth(env: &Env) -> u32 {
+    env.storage()
+        .instance()
+        .get(&DataKey::MaxDelegationDepth)
+        .unwrap_or(DEFAULT_MAX_DELEGATION_DEPTH)
+}
+
+/// How many delegations down this account is checking auth, read from the
+/// `__check_auth` of the account above it among the invocations being
+/// authorized, 0 when it isn't acting as a delegate
+///
+/// An account passes its delegates the depth they are at. A delegator that
+/// doesn't is taken to be the top of the chain.
+fn delegation_depth(env: &Env, auth_context: &Vec<Context>) -> u32 {
+    let check_auth = Symbol::new(env, "__check_auth");
+    auth_context
+        .iter()
+        .filter_map(|context| match context {
+            Context::Contract(context) if context.fn_name == check_auth => Some(
+                context
+                    .args
+                    .get(1)
+                    .and_then(|depth| u32::try_from_val(env, &depth).ok())
+                    .unwrap_or(1),
+            ),
+            _ => None,
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// Checks no delegate signature names this account, and that delegating
+/// from `depth` stays within the configured depth
+fn check_delegation(
+    env: &Env,
+    signatures: &Vec<AccountSignature>,
+    depth: u32,
+) -> Result<(), AccountError> {
+    for signature in signatures.iter() {
+        let AccountSignature::Delegate(delegate) = signature else {
+            continue;
+        };
+        if delegate == env.current_contract_address() {
+            return Err(AccountError::SelfDelegation);
+        }
+        if depth >= max_delegation_depth(env) {
+            return Err(AccountError::DelegationTooDeep);
+        }
+    }
+    Ok(())
+}
+
+fn max_contexts(env: &Env) -> u32 {
+    env.storage()
+        .instance()
+        .get(&DataKey::MaxContexts)
+        .unwrap_or(DEFAULT_MAX_CONTEXTS)
+}
+
+/// Prefixed to the message of any authorization that includes a call to a
+/// SEP-45 entrypoint in `WEB_AUTH_FNS`, so web-auth assertions and ordinary
+/// authorizations are never signatures over the same bytes
+pub const WEB_AUTH_TAG: &[u8] = b"SEP-45 web auth";
+
+/// Prefixed to application data signed for `verify_message`, so a signed
+/// message can never pass for the payload of an authorization
+pub const MESSAGE_TAG: &[u8] = b"SEP-45 signed message";
+
+/// The SEP-45 verification entrypoints of the web auth contract
+const WEB_AUTH_FNS: [&str; 5] = [
+    "web_auth_verify",
+    "web_auth_verify_many",
+    "web_auth_verify_full",
+    "web_auth_verify_reporting",
+    "web_auth_verify_struct",
+];
+
+/// Whether any invocation being authorized is a SEP-45 web auth challenge
+fn is_web_auth(env: &Env, auth_context: &Vec<Context>) -> bool {
+    let names = WEB_AUTH_FNS.map(|name| Symbol::new(env, name));
+    auth_context.iter().any(|context| {
+        let Context::Contract(context) = context else {
+            return false;
+        };
+        names.contains(&context.fn_name)
+    })
+}
+
+/// The message signers sign for `payload`: sha256(separator || payload) when
+/// a domain separator is configured, otherwise the payload itself. For web
+/// auth that message is in turn hashed as sha256(WEB_AUTH_TAG || message).
+fn signed_message(env: &Env, payload: BytesN<32>, web_auth: bool) -> BytesN<32> {
+    let message = match env
+        .storage()
+        .instance()
+        .get::<_, BytesN<32>>(&DataKey::DomainSeparator)
+    {
+        Some(separator) => {
+            let mut message = Bytes::from(separator);
+            message.append(&payload.into());
+            env.crypto().sha256(&message).to_bytes()
+        }
+        None => payload,
+    };
+    if !web_auth {
+        return message;
+    }
+    let mut tagged = Bytes::from_slice(env, WEB_AUTH_TAG);
+    tagged.append(&message.into());
+    env.crypto().sha256(&tagged).to_bytes()
+}
+
+/// Extends `message` to commit to the whole of `auth_context`, as
+/// sha256(message || sha256(auth_context as XDR))
+fn bind_contexts(env: &Env, message: BytesN<32>, auth_context: &Vec<Context>) -> BytesN<32> {
+    let mut bound = Bytes::from(message);
+    bound.append(&env.crypto().sha256(&auth_context.to_xdr(env)).into());
+    env.crypto().sha256(&bound).to_bytes()
+}
+
+/// Extends `message` to commit to the account's `nonce`, as
+/// sha256(message || nonce as 8 big-endian bytes)
+fn bind_nonce(env: &Env, message: BytesN<32>, nonce: u64) -> BytesN<32> {
+    let mut bound = Bytes::from(message);
+    bound.append(&Bytes::from_array(env, &nonce.to_be_bytes()));
+    env.crypto().sha256(&bound).to_bytes()
+}
+
+/// Whether `signer` was rotated out and its grace period has ended, after
+/// which it is treated as removed
+fn past_grace(env: &Env, signer: &SignerKey) -> bool {
+    let SignerKey::Ed25519(public_key) = signer else {
+        return false;
+    };
+    env.storage()
+        .instance()
+        .get::<_, u32>(&DataKey::SignerGrace(public_key.clone()))
+        .is_some_and(|deadline| env.ledger().sequence() > deadline)
+}
+
+/// Authorizations `signer` has left, or `None` when it is not count-limited
+fn signer_uses(env: &Env, signer: &SignerKey) -> Option<u32> {
+    let SignerKey::Ed25519(public_key) = signer else {
+        return None;
+    };
+    env.storage()
+        .instance()
+        .get(&DataKey::SignerUses(public_key.clone()))
+}
+
+/// Checks a count-limited signer has an authorization left
+fn check_uses(env: &Env, signer: &SignerKey) -> Result<(), AccountError> {
+    if signer_uses(env, signer) == Some(0) {
+        return Err(AccountError::SignerExhausted);
+    }
+    Ok(())
+}
+
+/// Uses up one of a count-limited signer's authorizations
+fn consume_use(env: &Env, signer: &SignerKey) {
+    if let (SignerKey::Ed25519(public_key), Some(uses)) = (signer, signer_uses(env, signer)) {
+        env.storage()
+            .instance()
+            .set(&DataKey::SignerUses(public_key.clone()), &(uses - 1));
+    }
+}
+
+/// Checks a signer is unexpired and its session not revoked
+fn check_active(env: &Env, record: &SignerRecord) -> Result<(), AccountError> {
+    if let Some(expiry) = record.expiry {
+        if env.ledger().sequence() > expiry {
+            return Err(AccountError::SignerExpired);
+        }
+    }
+
+    if let Some(epoch) = record.session {
+        if epoch != session_epoch(env) {
+            return Err(AccountError::SessionRevoked);
+        }
+    }
+
+    Ok(())
+}
+
+fn session_epoch(env: &Env) -> u32 {
+    env.storage()
+        .instance()
+        .get(&DataKey::SessionEpoch)
+        .unwrap_or(0)
+}
+
+/// Checks `caller` is the admin or the account itself, and has authorized
+fn require_admin_or_self(env: &Env, caller: &Address) -> Result<(), AccountError> {
+    let admin = current_admin(env);
+    if admin.as_ref() != Some(caller) && *caller != env.current_contract_address() {
+        return Err(AccountError::NotAuthorized);
+    }
+    caller.require_auth();
+    extend_instance_ttl(env);
+    Ok(())
+}
+
+/// Notes a signer change, failing with `ChangeTooSoon` while the cooldown
+/// since the last one is still running
+fn record_change(env: &Env) -> Result<(), AccountError> {
+    let storage = env.storage().instance();
+    let sequence = env.ledger().sequence();
+    if let (Some(cooldown), Some(last_change)) = (
+        storage.get::<_, u32>(&DataKey::ChangeCooldown),
+        storage.get::<_, u32>(&DataKey::LastChange),
+    ) {
+        if sequence < last_change.saturating_add(cooldown) {
+            return Err(AccountError::ChangeTooSoon);
+        }
+    }
+    storage.set(&DataKey::LastChange, &sequence);
+    Ok(())
+}
+
+fn require_admin_or_guardian(env: &Env, caller: &Address) -> Result<(), AccountError> {
+    let storage = env.storage().instance();
+    let admin = current_admin(env);
+    if admin.as_ref() != Some(caller) && !storage.has(&DataKey::Guardian(caller.clone())) {
+        return Err(AccountError::NotAuthorized);
+    }
+    caller.require_auth();
+    extend_instance_ttl(env);
+    Ok(())
+}
+
+/// Rejects signatures whose approximate total size exceeds the configured
+/// cap, if any
+fn check_signature_bytes(
+    env: &Env,
+    signatures: &Vec<AccountSignature>,
+) -> Result<(), AccountError> {
+    let Some(max_sig_bytes) = env
+        .storage()
+        .instance()
+        .get::<_, u32>(&DataKey::MaxSigBytes)
+    else {
+        return Ok(());
+    };
+    let total = signatures.iter().fold(0u32, |total, signature| {
+        total.saturating_add(signature.approx_len())
+    });
+    if total > max_sig_bytes {
+        return Err(AccountError::SignaturesTooLarge);
+    }
+    Ok(())
+}
+
+/// Orders `signatures` by signer key, dropping exact repeats so they cost
+/// nothing to verify. The same signer presented with differing signatures
+/// is still a `DuplicateSigner`.
+fn canonicalize(
+    env: &Env,
+    signatures: &Vec<AccountSignature>,
+) -> Result<Vec<AccountSignature>, AccountError> {
+    let mut canonical: Map<SignerKey, AccountSignature> = Map::new(env);
+    for signature in signatures.iter() {
+        let signer = signature.signer_key();
+        match canonical.get(signer.clone()) {
+            Some(existing) if existing == signature => {}
+            Some(_) => return Err(rejected(env, &signer, AccountError::DuplicateSigner)),
+            None => canonical.set(signer, signature),
+        }
+    }
+    Ok(canonical.values())
+}
+
+fn max_signatures(env: &Env) -> u32 {
+    env.storage()
+        .instance()
+        .get(&DataKey::MaxSignatures)
+        .unwrap_or_else(|| signer_list(env).len())
+}
+
+impl Account {
+    /// Checks `sig` is a valid ed25519 signature over `payload` bound to the
+    /// nonce `sig` carries, the exact predicate `__check_auth` applies to each
+    /// ed25519 signature once the domain separator, if any, has been applied
+    ///
+    /// The host traps rather than returning on an invalid signature, so this
+    /// never returns `false`: a simulation calling it sees the same failed
+    /// invocation `__check_auth` would produce.
+    pub fn verify_one(env: &Env, payload: &BytesN<32>, sig: &Signature) -> bool {
+        let message = bind_nonce(env, payload.clone(), sig.nonce);
+        env.crypto()
+            .ed25519_verify(&sig.public_key, message.as_ref(), &sig.signature);
+        true
+    }
+}
+
+#[contractimpl]
+impl Account {
+    /// Registers every key in `signers` with the same initial `weight`. The
+    /// `threshold` must be at least one and reachable by them together.
+    ///
+    /// With `AdminMode::SelfAuth` the admin functions require the account's
+    /// own authorization rather than a separate admin's.
+    pub fn __constructor(
+        env: Env,
+        admin_mode: AdminMode,
+        signers: Vec<BytesN<32>>,
+        weight: u32,
+        threshold: u32,
+    ) -> Result<(), AccountError> {
+        if env.storage().instance().has(&DataKey::Initialized) {
+            return Err(AccountError::AlreadyInitialized);
+        }
+        if signers.is_empty() {
+            return Err(AccountError::NoSigners);
+        }
+
+        env.storage().instance().set(&DataKey::Initialized, &());
+        env.storage()
+            .instance()
+            .set(&DataKey::Version, &STORAGE_VERSION);
+        let admin = match admin_mode {
+            AdminMode::External(admin) => {
+                env.storage().instance().set(&DataKey::Admin, &admin);
+                admin
+            }
+            AdminMode::SelfAuth => {
+                env.storage().instance().set(&DataKey::SelfAuth, &());
+                env.current_contract_address()
+            }
+        };
+        events::AdminSet { admin }.publish(&env);
+        for signer in signers.iter() {
+            register_signer(&env, &SignerKey::Ed25519(signer), weight, None)?;
+        }
+        // A zero threshold would let anyone authorize, and one above the
+        // signers' combined weight would lock the account from the start
+        if threshold == 0 {
+            return Err(AccountError::ThresholdUnsatisfiable);
+        }
+        check_satisfiable(&env, threshold)?;
+        env.storage()
+            .instance()
+            .set(&DataKey::Threshold, &threshold);
+
+        Ok(())
+    }
+
+    pub fn admin(env: Env) -> Address {
+        stored_admin(&env)
+    }
+
+    /// Who the admin functions currently answer to, failing with `NoAdmin`
+    /// once the admin has been renounced
+    pub fn admin_mode(env: Env) -> AdminMode {
+        if env.storage().instance().has(&DataKey::SelfAuth) {
+            return AdminMode::SelfAuth;
+        }
+        AdminMode::External(stored_admin(&env))
+    }
+
+    /// Removes the admin for good, leaving the signer set and configuration
+    /// as they are forever and every admin function failing with `NoAdmin`
+    ///
+    /// This cannot be undone: with no admin the account can no longer be
+    /// upgraded or reconfigured, though its signers keep authorizing as
+    /// before.
+    pub fn renounce_admin(env: Env) -> Result<(), AccountError> {
+        require_admin(&env);
+        record_change(&env)?;
+
+        env.storage().instance().remove(&DataKey::Admin);
+        env.storage().instance().remove(&DataKey::SelfAuth);
+        env.storage().instance().remove(&DataKey::PendingAdmin);
+        Ok(())
+    }
+
+    /// Tears the account down, removing its signers and configuration so
+    /// the emptied instance can archive and its rent be reclaimed
+    ///
+    /// The account is inert afterwards: every authorization fails with
+    /// `AccountClosed` and, with no admin, nothing can be reconfigured.
+    /// Revocation markers, guardians and frozen tokens can't be enumerated
+    /// and are left in place.
+    pub fn close(env: Env) {
+        let admin = require_admin(&env);
+        events::Closed { admin }.publish(&env);
+
+        let storage = env.storage().instance();
+        for signer in signer_list(&env).iter() {
+            forget_signer(&env, &signer);
+            if let Some(data_key) = signer.data_key() {
+                storage.remove(&data_key);
+            }
+        }
+        for key in [
+            DataKey::Admin,
+            DataKey::Threshold,
+            DataKey::SignerList,
+            DataKey::PendingAdmin,
+            DataKey::Nonce,
+            DataKey::MaxSignatures,
+            DataKey::Version,
+            DataKey::Recovery,
+            DataKey::PendingRecovery,
+            DataKey::PendingRecoveries,
+            DataKey::MaxRecoveryRequests,
+            DataKey::RecoveryGroup,
+            DataKey::Frozen,
+            DataKey::ExactSignatures,
+            DataKey::MaxContexts,
+            DataKey::MaxDelegationDepth,
+            DataKey::UniformVerification,
+            DataKey::SessionEpoch,
+            DataKey::DomainSeparator,
+            DataKey::MasterSigner,
+            DataKey::RateLimit,
+            DataKey::RateWindow,
+            DataKey::MaxSigners,
+            DataKey::MaxSigBytes,
+            DataKey::LastAuth,
+            DataKey::Signers,
+            DataKey::ChangeCooldown,
+            DataKey::LastChange,
+            DataKey::SelfAuth,
+            DataKey::FullContext,
+        ] {
+            storage.remove(&key);
+        }
+    }
+
+    /// Proposes a new admin, who must call `accept_admin` to take over
+    pub fn set_admin(env: Env, new_admin: Address) -> Result<(), AccountError> {
+        require_admin(&env);
+        record_change(&env)?;
+
+        env.storage()
+            .instance()
+            .set(&DataKey::PendingAdmin, &new_admin);
+        events::AdminProposed { admin: new_admin }.publish(&env);
+        Ok(())
+    }
+
+    /// Promotes the pending admin proposed by `set_admin`, moving a `SelfAuth`
+    /// account to `External` mode
+    pub fn accept_admin(env: Env) -> Result<(), AccountError> {
+        let Some(pending_admin) = env
+            .storage()
+            .instance()
+            .get::<_, Address>(&DataKey::PendingAdmin)
+        else {
+            return Err(AccountError::NoPendingAdmin);
+        };
+        pending_admin.require_auth();
+        extend_instance_ttl(&env);
+
+        env.storage().instance().remove(&DataKey::PendingAdmin);
+        env.storage().instance().remove(&DataKey::SelfAuth);
+        env.storage()
+            .instance()
+            .set(&DataKey::Admin, &pending_admin);
+        events::AdminSet {
+            admin: pending_admin,
+        }
+        .publish(&env);
+        Ok(())
+    }
+
+    /// Designates `recovery` as able to replace the signer set after
+    /// `delay_ledgers` have passed without a veto
+    ///
+    /// The delay must be between a day and 30 days of ledgers. Anything
+    /// shorter leaves no time to veto, and anything longer is as good as no
+    /// recovery.
+    pub fn set_recovery(
+        env: Env,
+        recovery: Address,
+        delay_ledgers: u32,
+    ) -> Result<(), AccountError> {
+        require_admin(&env);
+        record_change(&env)?;
+        if !(MIN_RECOVERY_DELAY..=MAX_RECOVERY_DELAY).contains(&delay_ledgers) {
+            return Err(AccountError::InvalidRecoveryDelay);
+        }
+
+        env.storage().instance().set(
+            &DataKey::Recovery,
+            &Recovery {
+                recovery,
+                delay_ledgers,
+            },
+        );
+        Ok(())
+    }
+
+    /// Lets any one of `members` start a recovery, in addition to the
+    /// recovery address; they wait out the same delay and may be vetoed
+    /// the same way
+    pub fn set_recovery_group(env: Env, members: Vec<Address>) -> Result<(), AccountError> {
+        require_admin(&env);
+        record_change(&env)?;
+
+        env.storage()
+            .instance()
+            .set(&DataKey::RecoveryGroup, &members);
+        Ok(())
+    }
+
+    pub fn recovery_group(env: Env) -> Vec<Address> {
+        env.storage()
+            .instance()
+            .get(&DataKey::RecoveryGroup)
+            .unwrap_or_else(|| Vec::new(&env))
+    }
+
+    /// Starts replacing every signer with `new_signer`. `initiator` is the
+    /// recovery address or a member of the recovery group
+    ///
+    /// Recoveries already pending stay so. Once `max_recovery_requests` are,
+    /// this fails with `TooManyRecoveryRequests` until one is executed or
+    /// they are cancelled.
+    pub fn initiate_recovery(
+        env: Env,
+        initiator: Address,
+        new_signer: BytesN<32>,
+    ) -> Result<(), AccountError> {
+        let Some(config) = env
+            .storage()
+            .instance()
+            .get::<_, Recovery>(&DataKey::Recovery)
+        else {
+            return Err(AccountError::NoRecovery);
+        };
+        if initiator != config.recovery && !Self::recovery_group(env.clone()).contains(&initiator) {
+            return Err(AccountError::NotAuthorized);
+        }
+        initiator.require_auth();
+        extend_instance_ttl(&env);
+
+        let mut requests = pending_recoveries(&env);
+        if requests.len() >= Self::max_recovery_requests(env.clone()) {
+            return Err(AccountError::TooManyRecoveryRequests);
+        }
+        let request = RecoveryRequest {
+            new_signer,
+            effective_ledger: env.ledger().sequence().saturating_add(config.delay_ledgers),
+        };
+        requests.push_back(request.clone());
+        env.storage()
+            .instance()
+            .set(&DataKey::PendingRecoveries, &requests);
+        events::RecoveryInitiated {
+            new_signer: request.new_signer,
+            effective_ledger: request.effective_ledger,
+        }
+        .publish(&env);
+        Ok(())
+    }
+
+    /// Applies the oldest pending recovery once its challenge period has
+    /// elapsed, dropping the rest along with the signers they would replace
+    /// and any master signer, which would otherwise outlive the lost keys
+    pub fn execute_recovery(env: Env) -> Result<(), AccountError> {
+        let storage = env.storage().instance();
+        let Some(request) = pending_recoveries(&env).first() else {
+            return Err(AccountError::NoPendingRecovery);
+        };
+        if env.ledger().sequence() < request.effective_ledger {
+            return Err(AccountError::RecoveryNotReady);
+        }
+
+        storage.remove(&DataKey::PendingRecoveries);
+        for signer in signer_list(&env).iter() {
+            forget_signer(&env, &signer);
+        }
+        storage.set(&DataKey::SignerList, &Vec::<SignerKey>::new(&env));
+        storage.remove(&DataKey::MasterSigner);
+        register_signer(&env, &SignerKey::Ed25519(request.new_signer), 1, None)?;
+        storage.set(&DataKey::Threshold, &1u32);
+        extend_instance_ttl(&env);
+        Ok(())
+    }
+
+    /// Vetoes every pending recovery; any one current signer may authorize
+    /// this
+    pub fn cancel_recovery(env: Env) -> Result<(), AccountError> {
+        env.current_contract_address().require_auth();
+
+        let storage = env.storage().instance();
+        if !storage.has(&DataKey::PendingRecoveries) {
+            return Err(AccountError::NoPendingRecovery);
+        }
+        storage.remove(&DataKey::PendingRecoveries);
+        events::RecoveryCancelled {}.publish(&env);
+        Ok(())
+    }
+
+    /// The recovery `execute_recovery` would apply next
+    pub fn pending_recovery(env: Env) -> Option<RecoveryRequest> {
+        pending_recoveries(&env).first()
+    }
+
+    /// Every pending recovery, oldest first
+    pub fn pending_recoveries(env: Env) -> Vec<RecoveryRequest> {
+        pending_recoveries(&env)
+    }
+
+    /// Caps how many recoveries may be pending at once, so the recovery
+    /// address or group can't pile them up. Lowering it leaves those already
+    /// pending in place.
+    pub fn set_max_recovery_requests(env: Env, max_requests: u32) {
+        require_admin(&env);
+
+        env.storage()
+            .instance()
+            .set(&DataKey::MaxRecoveryRequests, &max_requests);
+    }
+
+    pub fn max_recovery_requests(env: Env) -> u32 {
+        env.storage()
+            .instance()
+            .get(&DataKey::MaxRecoveryRequests)
+            .unwrap_or(DEFAULT_MAX_RECOVERY_REQUESTS)
+    }
+
+    /// Stops the account authorizing anything until the admin unfreezes it.
+    /// `caller` is the admin or a guardian. A frozen account still authorizes
+    /// its own `unfreeze`, for when it is its own admin.
+    pub fn freeze(env: Env, caller: Address) -> Result<(), AccountError> {
+        require_admin_or_guardian(&env, &caller)?;
+
+        env.storage().instance().set(&DataKey::Frozen, &true);
+        events::Frozen {}.publish(&env);
+        Ok(())
+    }
+
+    /// Stops the account authorizing transfers of `token`, leaving everything
+    /// else working. `caller` is the admin or a guardian
+    pub fn freeze_token(env: Env, caller: Address, token: Address) -> Result<(), AccountError> {
+        require_admin_or_guardian(&env, &caller)?;
+
+        env.storage()
+            .instance()
+            .set(&DataKey::FrozenToken(token.clone()), &true);
+        events::TokenFrozen { token }.publish(&env);
+        Ok(())
+    }
+
+    /// `caller` is the admin or a guardian
+    pub fn unfreeze_token(env: Env, caller: Address, token: Address) -> Result<(), AccountError> {
+        require_admin_or_guardian(&env, &caller)?;
+
+        env.storage()
+            .instance()
+            .remove(&DataKey::FrozenToken(token.clone()));
+        events::TokenUnfrozen { token }.publish(&env);
+        Ok(())
+    }
+
+    pub fn is_token_frozen(env: Env, token: Address) -> bool {
+        env.storage().instance().has(&DataKey::FrozenToken(token))
+    }
+
+    pub fn unfreeze(env: Env) {
+        require_admin(&env);
+
+        env.storage().instance().remove(&DataKey::Frozen);
+        events::Unfrozen {}.publish(&env);
+    }
+
+    pub fn is_frozen(env: Env) -> bool {
+        env.storage().instance().has(&DataKey::Frozen)
+    }
+
+    /// Lets `guardian` freeze the account, or transfers of a single token,
+    /// e.g. a monitoring service. A guardian can't unfreeze the account,
+    /// sign for it or change its configuration.
+    pub fn add_guardian(env: Env, guardian: Address) {
+        require_admin(&env);
+
+        env.storage()
+            .instance()
+            .set(&DataKey::Guardian(guardian), &true);
+    }
+
+    pub fn remove_guardian(env: Env, guardian: Address) {
+        require_admin(&env);
+
+        env.storage()
+            .instance()
+            .remove(&DataKey::Guardian(guardian));
+    }
+
+    pub fn is_guardian(env: Env, address: Address) -> bool {
+        env.storage().instance().has(&DataKey::Guardian(address))
+    }
+
+    /// Updates the total signer weight required to authorize, which must be
+    /// at least one, no more than `MAX_TOTAL_WEIGHT` and reachable by the
+    /// registered signers together
+    pub fn set_threshold(env: Env, threshold: u32) -> Result<(), AccountError> {
+        require_admin(&env);
+        record_change(&env)?;
+
+        if threshold == 0 {
+            return Err(AccountError::ThresholdUnsatisfiable);
+        }
+        if threshold > MAX_TOTAL_WEIGHT {
+            return Err(AccountError::WeightOverflow);
+        }
+        check_satisfiable(&env, threshold)?;
+        env.storage()
+            .instance()
+            .set(&DataKey::Threshold, &threshold);
+        events::ThresholdSet { threshold }.publish(&env);
+        Ok(())
+    }
+
+    /// Registers an ed25519 public key as a signer, or updates its weight
+    ///
+    /// `caller` must be the admin or the account itself, in which case the
+    /// change must meet the account's own signature threshold. When `expiry`
+    /// is set the signer stops working after that ledger sequence. A `label`
+    /// naming the device holding the key replaces any existing one. When
+    /// `uses` is set the signer may only count towards that many
+    /// authorizations, otherwise it is unlimited. The signers' combined
+    /// weight may not exceed `MAX_TOTAL_WEIGHT`.
+    pub fn add_signer(
+        env: Env,
+        caller: Address,
+        signer: BytesN<32>,
+        weight: u32,
+        expiry: Option<u32>,
+        label: Option<String>,
+        uses: Option<u32>,
+    ) -> Result<(), AccountError> {
+        require_admin_or_self(&env, &caller)?;
+        record_change(&env)?;
+
+        if let Some(label) = label {
+            env.storage()
+                .instance()
+                .set(&DataKey::SignerLabel(signer.clone()), &label);
+        }
+        register_signer(&env, &SignerKey::Ed25519(signer.clone()), weight, expiry)?;
+        match uses {
+            Some(uses) => env
+                .storage()
+                .instance()
+                .set(&DataKey::SignerUses(signer), &uses),
+            None => env
+                .storage()
+                .instance()
+                .remove(&DataKey::SignerUses(signer)),
+        }
+        Ok(())
+    }
+
+    /// Registers `signer` with a weight of one unless it is already a
+    /// signer, in which case it is left as it is. Safe to retry.
+    pub fn ensure_signer(env: Env, signer: BytesN<32>) -> Result<(), AccountError> {
+        require_admin(&env);
+
+        let signer = SignerKey::Ed25519(signer);
+        if signer_record(&env, &signer).is_some() {
+            return Ok(());
+        }
+        record_change(&env)?;
+        register_signer(&env, &signer, 1, None)
+    }
+
+    /// Authorizations a count-limited signer has left, or `None` when it is
+    /// unlimited
+    pub fn signer_uses(env: Env, signer: BytesN<32>) -> Option<u32> {
+        signer_uses(&env, &SignerKey::Ed25519(signer))
+    }
+
+    /// How much `signer` has left of each of its limits, for wallets to show
+    /// a session key's status
+    pub fn signer_status(env: Env, signer: BytesN<32>) -> Result<SignerStatus, AccountError> {
+        let key = SignerKey::Ed25519(signer.clone());
+        let Some(record) = signer_record(&env, &key) else {
+            return Err(AccountError::UnknownSigner);
+        };
+        let remaining_spend = env
+            .storage()
+            .instance()
+            .get::<_, SpendLimit>(&DataKey::SpendLimit(signer.clone()))
+            .map(|policy| {
+                spend_window(&env, &signer, &policy)
+                    .map_or(0, |(_, spent)| policy.limit.saturating_sub(spent).max(0))
+            });
+        Ok(SignerStatus {
+            remaining_uses: signer_uses(&env, &key),
+            remaining_spend,
+            expiry: record.expiry,
+        })
+    }
+
+    /// Names the device holding `signer`. `caller` must be the admin or the
+    /// account itself.
+    pub fn set_signer_label(
+        env: Env,
+        caller: Address,
+        signer: BytesN<32>,
+        label: String,
+    ) -> Result<(), AccountError> {
+        require_admin_or_self(&env, &caller)?;
+
+        if signer_record(&env, &SignerKey::Ed25519(signer.clone())).is_none() {
+            return Err(AccountError::UnknownSigner);
+        }
+        env.storage()
+            .instance()
+            .set(&DataKey::SignerLabel(signer), &label);
+        Ok(())
+    }
+
+    pub fn signer_label(env: Env, signer: BytesN<32>) -> Option<String> {
+        env.storage().instance().get(&DataKey::SignerLabel(signer))
+    }
+
+    /// A short identifier for showing `signer` to users, the first four bytes
+    /// of the key's sha256. Clients can compute the same from the key alone,
+    /// whether or not it is registered.
+    pub fn signer_fingerprint(env: Env, signer: BytesN<32>) -> BytesN<4> {
+        fingerprint(&env, &SignerKey::Ed25519(signer))
+    }
+
+    /// Removes an ed25519 signer, refusing to remove the last remaining one.
+    /// `caller` must be the admin or the account itself.
+    pub fn remove_signer(
+        env: Env,
+        caller: Address,
+        signer: BytesN<32>,
+    ) -> Result<(), AccountError> {
+        require_admin_or_self(&env, &caller)?;
+        record_change(&env)?;
+
+        unregister_signer(&env, &SignerKey::Ed25519(signer))
+    }
+
+    /// Makes every change to who may authorize for the account wait
+    /// `cooldown_ledgers` after the last one succeeded: to the signers and
+    /// their limits, the threshold, the master signer, the admin and
+    /// recovery. Executing a recovery is exempt, so an admin being recovered
+    /// from can't hold it off with changes of its own. This gives the other
+    /// signers time to react to an unexpected change, e.g. by freezing the
+    /// account. Changing the cooldown counts as a change itself, so it
+    /// can't be lifted to skip one. Zero turns the cooldown off.
+    pub fn set_change_cooldown(env: Env, cooldown_ledgers: u32) -> Result<(), AccountError> {
+        require_admin(&env);
+        record_change(&env)?;
+
+        env.storage()
+            .instance()
+            .set(&DataKey::ChangeCooldown, &cooldown_ledgers);
+        Ok(())
+    }
+
+    pub fn change_cooldown(env: Env) -> u32 {
+        env.storage()
+            .instance()
+            .get(&DataKey::ChangeCooldown)
+            .unwrap_or(0)
+    }
+
+    /// Swaps the whole signer set for `new_signers`, each with a weight of
+    /// one, and sets `new_threshold` in the same step
+    ///
+    /// Every current signer is removed along with its label, limits and
+    /// scope, whatever its kind, as is any master signer. Nothing changes unless the new set is
+    /// non-empty, free of duplicates and revoked keys, and can meet
+    /// `new_threshold`.
+    pub fn replace_signers(
+        env: Env,
+        new_signers: Vec<BytesN<32>>,
+        new_threshold: u32,
+    ) -> Result<(), AccountError> {
+        require_admin(&env);
+        record_change(&env)?;
+
+        if new_signers.is_empty() {
+            return Err(AccountError::NoSigners);
+        }
+        if new_signers.len() > max_signers(&env) {
+            return Err(AccountError::TooManySigners);
+        }
+        if new_threshold == 0 || new_threshold > new_signers.len() {
+            return Err(AccountError::ThresholdUnsatisfiable);
+        }
+        let storage = env.storage().instance();
+        let mut added = Vec::new(&env);
+        for signer in new_signers.iter() {
+            check_public_key(&signer)?;
+            if storage.has(&DataKey::Revoked(signer.clone())) {
+                return Err(AccountError::SignerRevoked);
+            }
+            let signer = SignerKey::Ed25519(signer);
+            if added.contains(&signer) {
+                return Err(AccountError::DuplicateSigner);
+            }
+            added.push_back(signer);
+        }
+
+        let removed = signer_list(&env);
+        for signer in removed.iter() {
+            erase_signer(&env, &signer);
+        }
+        for signer in added.iter() {
+            let record = SignerRecord {
+                weight: 1,
+                expiry: None,
+                scope: None,
+                session: None,
+            };
+            set_signer_record(&env, &signer, &record);
+        }
+        storage.set(&DataKey::SignerList, &added);
+        storage.set(&DataKey::Threshold, &new_threshold);
+        storage.remove(&DataKey::MasterSigner);
+
+        events::SignersReplaced {
+            removed,
+            added,
+            threshold: new_threshold,
+        }
+        .publish(&env);
+        Ok(())
+    }
+
+    /// Replaces `old` with `new` at the same weight, leaving `old` working
+    /// for `grace_ledgers` more ledgers so signatures already gathered with
+    /// it still count. `caller` must be the admin or the account itself.
+    ///
+    /// Once the grace period ends `old` is treated as removed, though it
+    /// stays listed until `remove_signer` clears it.
+    pub fn rotate_signer(
+        env: Env,
+        caller: Address,
+        old: BytesN<32>,
+        new: BytesN<32>,
+        grace_ledgers: u32,
+    ) -> Result<(), AccountError> {
+        require_admin_or_self(&env, &caller)?;
+        record_change(&env)?;
+
+        let old_key = SignerKey::Ed25519(old.clone());
+        let Some(record) = signer_record(&env, &old_key).filter(|_| !past_grace(&env, &old_key))
+        else {
+            return Err(AccountError::UnknownSigner);
+        };
+        let new_key = SignerKey::Ed25519(new);
+        if signer_record(&env, &new_key).is_some() {
+            return Err(AccountError::DuplicateSigner);
+        }
+        register_signer(&env, &new_key, record.weight, None)?;
+        env.storage().instance().set(
+            &DataKey::SignerGrace(old),
+            &env.ledger().sequence().saturating_add(grace_ledgers),
+        );
+        Ok(())
+    }
+
+    /// Last ledger a rotated-out signer keeps working
+    pub fn signer_grace(env: Env, signer: BytesN<32>) -> Option<u32> {
+        env.storage().instance().get(&DataKey::SignerGrace(signer))
+    }
+
+    /// Moves `old`'s weight, expiry, scope, label, remaining uses, limits and
+    /// co-signer to `new` in one step, leaving nothing stored under `old`.
+    /// Signers co-signing with `old`, and the master signer, follow it too.
+    /// `caller` must be the admin or the account itself.
+    pub fn swap_signer(
+        env: Env,
+        caller: Address,
+        old: BytesN<32>,
+        new: BytesN<32>,
+    ) -> Result<(), AccountError> {
+        require_admin_or_self(&env, &caller)?;
+        record_change(&env)?;
+
+        let old_key = SignerKey::Ed25519(old.clone());
+        let new_key = SignerKey::Ed25519(new.clone());
+        let Some(record) = signer_record(&env, &old_key).filter(|_| !past_grace(&env, &old_key))
+        else {
+            return Err(AccountError::UnknownSigner);
+        };
+        check_public_key(&new)?;
+        let storage = env.storage().instance();
+        if storage.has(&DataKey::Revoked(new.clone())) {
+            return Err(AccountError::SignerRevoked);
+        }
+        if signer_record(&env, &new_key).is_some() {
+            return Err(AccountError::DuplicateSigner);
+        }
+
+        let per_signer: [fn(BytesN<32>) -> DataKey; 9] = [
+            DataKey::SpendLimit,
+            DataKey::SpendHistory,
+            DataKey::SignerLabel,
+            DataKey::SignerGrace,
+            DataKey::SignerUses,
+            DataKey::SignerFnScope,
+            DataKey::SignerRequiredFns,
+            DataKey::CoSigner,
+            DataKey::SignerTxCap,
+        ];
+        for data_key in per_signer {
+            if let Some(value) = storage.get::<_, Val>(&data_key(old.clone())) {
+                storage.set(&data_key(new.clone()), &value);
+            }
+        }
+        erase_signer(&env, &old_key);
+        set_signer_record(&env, &new_key, &record);
+
+        let mut signers = signer_list(&env);
+        if let Some(index) = signers.first_index_of(&old_key) {
+            signers.set(index, new_key.clone());
+        }
+        storage.set(&DataKey::SignerList, &signers);
+        for signer in signers.iter() {
+            if co_signer(&env, &signer) == Some(old_key.clone()) {
+                if let SignerKey::Ed25519(public_key) = signer {
+                    storage.set(&DataKey::CoSigner(public_key), &new);
+                }
+            }
+        }
+        if storage.get::<_, BytesN<32>>(&DataKey::MasterSigner) == Some(old) {
+            storage.set(&DataKey::MasterSigner, &new);
+        }
+
+        events::SignerSwapped {
+            old: old_key,
+            new: new_key,
+        }
+        .publish(&env);
+        Ok(())
+    }
+
+    /// Removes `signer` if registered and bars it from ever being added again,
+    /// e.g. once its key is known to be compromised
+    pub fn revoke_signer(env: Env, signer: BytesN<32>) -> Result<(), AccountError> {
+        require_admin(&env);
+        record_change(&env)?;
+
+        let key = SignerKey::Ed25519(signer.clone());
+        if signer_record(&env, &key).is_some() {
+            unregister_signer(&env, &key)?;
+        }
+        env.storage().instance().set(&DataKey::Revoked(signer), &());
+        Ok(())
+    }
+
+    /// Lifts a revocation. Only the account itself may do this, so a
+    /// compromised admin can't restore a key it revoked.
+    pub fn unrevoke_signer(env: Env, signer: BytesN<32>) {
+        env.current_contract_address().require_auth();
+
+        env.storage().instance().remove(&DataKey::Revoked(signer));
+    }
+
+    pub fn is_revoked(env: Env, signer: BytesN<32>) -> bool {
+        env.storage().instance().has(&DataKey::Revoked(signer))
+    }
+
+    /// Registers `delegate` as a signer, or updates its weight. Its own
+    /// authorization of the signed message, presented as an
+    /// `AccountSignature::Delegate`, counts as a signature.
+    ///
+    /// The delegate authorizes this account's `__check_auth` with the
+    /// message and its depth in the delegation chain, 1 for a delegate of
+    /// the account being authorized, as arguments.
+    pub fn add_delegate_signer(
+        env: Env,
+        caller: Address,
+        delegate: Address,
+        weight: u32,
+    ) -> Result<(), AccountError> {
+        require_admin_or_self(&env, &caller)?;
+        record_change(&env)?;
+
+        if delegate == env.current_contract_address() {
+            return Err(AccountError::SelfDelegation);
+        }
+        register_signer(&env, &SignerKey::Delegate(delegate), weight, None)
+    }
+
+    /// Removes a delegate signer, refusing to remove the last remaining signer
+    pub fn remove_delegate_signer(
+        env: Env,
+        caller: Address,
+        delegate: Address,
+    ) -> Result<(), AccountError> {
+        require_admin_or_self(&env, &caller)?;
+        record_change(&env)?;
+
+        unregister_signer(&env, &SignerKey::Delegate(delegate))
+    }
+
+    /// Registers a secp256r1 (passkey) public key as a signer, or updates its weight
+    ///
+    /// When `expiry` is set the signer stops working after that ledger sequence.
+    pub fn add_secp256r1_signer(
+        env: Env,
+        public_key: BytesN<65>,
+        weight: u32,
+        expiry: Option<u32>,
+    ) -> Result<(), AccountError> {
+        require_admin(&env);
+        record_change(&env)?;
+
+        register_signer(&env, &SignerKey::Secp256r1(public_key), weight, expiry)
+    }
+
+    /// Removes a secp256r1 signer, refusing to remove the last remaining one
+    pub fn remove_secp256r1_signer(env: Env, public_key: BytesN<65>) -> Result<(), AccountError> {
+        require_admin(&env);
+        record_change(&env)?;
+
+        unregister_signer(&env, &SignerKey::Secp256r1(public_key))
+    }
+
+    pub fn signer_weight(env: Env, signer: BytesN<32>) -> Option<u32> {
+        signer_record(&env, &SignerKey::Ed25519(signer)).map(|record| record.weight)
+    }
+
+    pub fn is_signer(env: Env, signer: BytesN<32>) -> bool {
+        signer_record(&env, &SignerKey::Ed25519(signer)).is_some()
+    }
+
+    /// Number of registered signers across all key types
+    pub fn signer_count(env: Env) -> u32 {
+        signer_list(&env).len()
+    }
+
+    /// Ties a signer to the current session epoch, so it stops working the
+    /// next time `revoke_sessions` is called
+    pub fn set_session_signer(env: Env, signer: SignerKey) -> Result<(), AccountError> {
+        require_admin(&env);
+        record_change(&env)?;
+
+        let Some(record) = signer_record(&env, &signer) else {
+            return Err(AccountError::UnknownSigner);
+        };
+        let record = SignerRecord {
+            session: Some(session_epoch(&env)),
+            ..record
+        };
+        set_signer_record(&env, &signer, &record);
+        Ok(())
+    }
+
+    /// Invalidates every session signer at once. `caller` must be the admin
+    /// or the account itself.
+    pub fn revoke_sessions(env: Env, caller: Address) -> Result<(), AccountError> {
+        require_admin_or_self(&env, &caller)?;
+
+        let epoch = session_epoch(&env) + 1;
+        env.storage().instance().set(&DataKey::SessionEpoch, &epoch);
+        events::SessionsRevoked { epoch }.publish(&env);
+        Ok(())
+    }
+
+    pub fn session_epoch(env: Env) -> u32 {
+        session_epoch(&env)
+    }
+
+    /// Registers a new ed25519 signer of weight 1 that may only authorize
+    /// invocations of `contract`, e.g. a session key for a single dapp
+    pub fn add_scoped_signer(
+        env: Env,
+        signer: BytesN<32>,
+        contract: Address,
+    ) -> Result<(), AccountError> {
+        require_admin(&env);
+        record_change(&env)?;
+
+        let signer = SignerKey::Ed25519(signer);
+        if signer_record(&env, &signer).is_some() {
+            return Err(AccountError::DuplicateSigner);
+        }
+        register_signer(&env, &signer, 1, None)?;
+
+        let record = SignerRecord {
+            scope: Some(vec![&env, contract]),
+            ..signer_record(&env, &signer).unwrap()
+        };
+        set_signer_record(&env, &signer, &record);
+        Ok(())
+    }
+
+    /// Restricts a signer to only authorize invocations of `contracts`
+    pub fn set_signer_scope(
+        env: Env,
+        signer: SignerKey,
+        contracts: Vec<Address>,
+    ) -> Result<(), AccountError> {
+        require_admin(&env);
+        record_change(&env)?;
+
+        let Some(record) = signer_record(&env, &signer) else {
+            return Err(AccountError::UnknownSigner);
+        };
+        let record = SignerRecord {
+            scope: Some(contracts),
+            ..record
+        };
+        set_signer_record(&env, &signer, &record);
+        Ok(())
+    }
+
+    /// Lifts any scope restriction from a signer
+    pub fn clear_signer_scope(env: Env, signer: SignerKey) -> Result<(), AccountError> {
+        require_admin(&env);
+        record_change(&env)?;
+
+        if let Some(record) = signer_record(&env, &signer) {
+            let record = SignerRecord {
+                scope: None,
+                ..record
+            };
+            set_signer_record(&env, &signer, &record);
+        }
+        Ok(())
+    }
+
+    /// Restricts an ed25519 signer to only authorize calls to `functions`,
+    /// each a contract and the name of the function on it. This applies on
+    /// top of any contract-level scope.
+    pub fn set_signer_fn_scope(
+        env: Env,
+        signer: BytesN<32>,
+        functions: Vec<(Address, Symbol)>,
+    ) -> Result<(), AccountError> {
+        require_admin(&env);
+        record_change(&env)?;
+
+        if signer_record(&env, &SignerKey::Ed25519(signer.clone())).is_none() {
+            return Err(AccountError::UnknownSigner);
+        }
+        env.storage()
+            .instance()
+            .set(&DataKey::SignerFnScope(signer), &functions);
+        Ok(())
+    }
+
+    /// Lifts any function-level scope restriction from a signer
+    pub fn clear_signer_fn_scope(env: Env, signer: BytesN<32>) -> Result<(), AccountError> {
+        require_admin(&env);
+        record_change(&env)?;
+
+        env.storage()
+            .instance()
+            .remove(&DataKey::SignerFnScope(signer));
+        Ok(())
+    }
+
+    pub fn signer_fn_scope(env: Env, signer: BytesN<32>) -> Option<Vec<(Address, Symbol)>> {
+        signer_fn_scope(&env, &SignerKey::Ed25519(signer))
+    }
+
+    /// Only counts `signer` when `co_signer` also signs, as a second factor
+    /// that doesn't depend on the threshold. Both must be registered ed25519
+    /// signers, and removing `co_signer` leaves `signer` unusable until this
+    /// is cleared.
+    pub fn set_co_signer(
+        env: Env,
+        signer: BytesN<32>,
+        co_signer: BytesN<32>,
+    ) -> Result<(), AccountError> {
+        require_admin(&env);
+        record_change(&env)?;
+
+        if signer == co_signer {
+            return Err(AccountError::DuplicateSigner);
+        }
+        for key in [&signer, &co_signer] {
+            if signer_record(&env, &SignerKey::Ed25519(key.clone())).is_none() {
+                return Err(AccountError::UnknownSigner);
+            }
+        }
+        env.storage()
+            .instance()
+            .set(&DataKey::CoSigner(signer), &co_signer);
+        Ok(())
+    }
+
+    pub fn clear_co_signer(env: Env, signer: BytesN<32>) -> Result<(), AccountError> {
+        require_admin(&env);
+        record_change(&env)?;
+
+        env.storage().instance().remove(&DataKey::CoSigner(signer));
+        Ok(())
+    }
+
+    pub fn co_signer(env: Env, signer: BytesN<32>) -> Option<BytesN<32>> {
+        env.storage().instance().get(&DataKey::CoSigner(signer))
+    }
+
+    /// Only lets an ed25519 signer authorize when the calls being authorized
+    /// include each of `functions`, each a contract and the name of the
+    /// function on it. The inverse of a function scope, for pinning what a
+    /// signer's authorization must cover rather than what it may.
+    pub fn set_signer_required_fns(
+        env: Env,
+        signer: BytesN<32>,
+        functions: Vec<(Address, Symbol)>,
+    ) -> Result<(), AccountError> {
+        require_admin(&env);
+        record_change(&env)?;
+
+        if signer_record(&env, &SignerKey::Ed25519(signer.clone())).is_none() {
+            return Err(AccountError::UnknownSigner);
+        }
+        env.storage()
+            .instance()
+            .set(&DataKey::SignerRequiredFns(signer), &functions);
+        Ok(())
+    }
+
+    /// Lifts any required functions from a signer
+    pub fn clear_signer_required_fns(env: Env, signer: BytesN<32>) -> Result<(), AccountError> {
+        require_admin(&env);
+        record_change(&env)?;
+
+        env.storage()
+            .instance()
+            .remove(&DataKey::SignerRequiredFns(signer));
+        Ok(())
+    }
+
+    pub fn signer_required_fns(env: Env, signer: BytesN<32>) -> Option<Vec<(Address, Symbol)>> {
+        signer_required_fns(&env, &SignerKey::Ed25519(signer))
+    }
+
+    /// Caps what an ed25519 signer may transfer in one authorization, adding
+    /// up the amounts of every token `transfer` it covers. Its weight still
+    /// counts as usual, and the cap is lifted when a signer with a higher
+    /// cap, or none, also signs.
+    pub fn set_signer_tx_cap(env: Env, signer: BytesN<32>, cap: i128) -> Result<(), AccountError> {
+        require_admin(&env);
+        record_change(&env)?;
+
+        if signer_record(&env, &SignerKey::Ed25519(signer.clone())).is_none() {
+            return Err(AccountError::UnknownSigner);
+        }
+        env.storage()
+            .instance()
+            .set(&DataKey::SignerTxCap(signer), &cap);
+        Ok(())
+    }
+
+    /// Lifts a signer's cap on what it may transfer in one authorization
+    pub fn clear_signer_tx_cap(env: Env, signer: BytesN<32>) -> Result<(), AccountError> {
+        require_admin(&env);
+        record_change(&env)?;
+
+        env.storage()
+            .instance()
+            .remove(&DataKey::SignerTxCap(signer));
+        Ok(())
+    }
+
+    pub fn signer_tx_cap(env: Env, signer: BytesN<32>) -> Option<i128> {
+        signer_tx_cap(&env, &SignerKey::Ed25519(signer))
+    }
+
+    /// Limits a signer to transferring at most `limit` of `token` within any
+    /// `window_ledgers` ledgers, and nothing else
+    pub fn set_spend_limit(
+        env: Env,
+        signer: BytesN<32>,
+        token: Address,
+        limit: i128,
+        window_ledgers: u32,
+    ) -> Result<(), AccountError> {
+        require_admin(&env);
+        record_change(&env)?;
+
+        if signer_record(&env, &SignerKey::Ed25519(signer.clone())).is_none() {
+            return Err(AccountError::UnknownSigner);
+        }
+        let storage = env.storage().instance();
+
+        storage.set(
+            &DataKey::SpendLimit(signer.clone()),
+            &SpendLimit {
+                token,
+                limit,
+                window_ledgers,
+            },
+        );
+        storage.remove(&DataKey::SpendHistory(signer));
+        Ok(())
+    }
+
+    /// Restores a limited signer to full power
+    pub fn clear_spend_limit(env: Env, signer: BytesN<32>) -> Result<(), AccountError> {
+        require_admin(&env);
+        record_change(&env)?;
+
+        let storage = env.storage().instance();
+        storage.remove(&DataKey::SpendLimit(signer.clone()));
+        storage.remove(&DataKey::SpendHistory(signer));
+        Ok(())
+    }
+
+    pub fn spend_limit(env: Env, signer: BytesN<32>) -> Option<SpendLimit> {
+        env.storage().instance().get(&DataKey::SpendLimit(signer))
+    }
+
+    pub fn signer_scope(env: Env, signer: SignerKey) -> Option<Vec<Address>> {
+        signer_record(&env, &signer).and_then(|record| record.scope)
+    }
+
+    /// Reports, for each of `contexts`, whether `signer`'s scope would let it
+    /// authorize that invocation, to help debug rejected authorizations
+    ///
+    /// Nothing is permitted for an unregistered signer. Spend limits and the
+    /// signature itself are not considered.
+    pub fn explain_context(env: Env, signer: SignerKey, contexts: Vec<Context>) -> Vec<bool> {
+        let record = signer_record(&env, &signer);
+        let functions = signer_fn_scope(&env, &signer);
+        let mut permitted = Vec::new(&env);
+        for context in contexts.iter() {
+            let in_scope = match &record {
+                None => false,
+                Some(SignerRecord { scope: None, .. }) => true,
+                Some(SignerRecord {
+                    scope: Some(contracts),
+                    ..
+                }) => scope_permits(contracts, &context),
+            };
+            permitted.push_back(
+                in_scope
+                    && functions
+                        .as_ref()
+                        .is_none_or(|functions| fn_scope_permits(functions, &context)),
+            );
+        }
+        permitted
+    }
+
+    pub fn list_signers(env: Env) -> Vec<SignerKey> {
+        signer_list(&env)
+    }
+
+    /// Up to `limit` signers in registration order, starting from the one at
+    /// index `start`, for accounts with too many signers to list at once
+    ///
+    /// `limit` is clamped to `MAX_SIGNERS_PAGE`. A `start` past the last
+    /// signer returns an empty page; `signer_count` gives the total.
+    pub fn list_signers_paged(env: Env, start: u32, limit: u32) -> Vec<SignerKey> {
+        let signers = signer_list(&env);
+        let start = start.min(signers.len());
+        let end = start
+            .saturating_add(limit.min(MAX_SIGNERS_PAGE))
+            .min(signers.len());
+        signers.slice(start..end)
+    }
+
+    pub fn list_signers_of_kind(env: Env, kind: SignerKind) -> Vec<SignerKey> {
+        let mut signers = Vec::new(&env);
+        for signer in signer_list(&env).iter() {
+            if signer.kind() == kind {
+                signers.push_back(signer);
+            }
+        }
+        signers
+    }
+
+    /// Number of registered signers of each kind, keyed by the kind's
+    /// symbol, with kinds the account has none of counted as zero
+    pub fn signer_kind_counts(env: Env) -> Map<Symbol, u32> {
+        let mut counts = Map::new(&env);
+        for kind in [
+            SignerKind::Ed25519,
+            SignerKind::Secp256r1,
+            SignerKind::Delegate,
+        ] {
+            counts.set(kind.symbol(), 0);
+        }
+        for signer in signer_list(&env).iter() {
+            let kind = signer.kind().symbol();
+            counts.set(kind.clone(), counts.get_unchecked(kind) + 1);
+        }
+        counts
+    }
+
+    /// The nonce every signature in the next authorization must carry
+    pub fn nonce(env: Env) -> u64 {
+        env.storage().instance().get(&DataKey::Nonce).unwrap_or(0)
+    }
+
+    /// Caps the number of signatures accepted in a single authorization
+    pub fn set_max_signatures(env: Env, max_signatures: u32) {
+        require_admin(&env);
+
+        env.storage()
+            .instance()
+            .set(&DataKey::MaxSignatures, &max_signatures);
+    }
+
+    /// Caps the approximate total size, in bytes, of the signatures accepted
+    /// in a single authorization, bounding verification cost alongside
+    /// `set_max_signatures`. Unset, any size is accepted.
+    pub fn set_max_sig_bytes(env: Env, max_sig_bytes: u32) {
+        require_admin(&env);
+
+        env.storage()
+            .instance()
+            .set(&DataKey::MaxSigBytes, &max_sig_bytes);
+    }
+
+    pub fn max_sig_bytes(env: Env) -> Option<u32> {
+        env.storage().instance().get(&DataKey::MaxSigBytes)
+    }
+
+    /// Caps the number of signers the account may hold. Signers already
+    /// registered beyond a lowered cap are kept.
+    pub fn set_max_signers(env: Env, max_signers: u32) {
+        require_admin(&env);
+
+        env.storage()
+            .instance()
+            .set(&DataKey::MaxSigners, &max_signers);
+    }
+
+    pub fn max_signers(env: Env) -> u32 {
+        max_signers(&env)
+    }
+
+    /// Caps the number of invocations a single authorization may cover,
+    /// bounding the work spent inspecting them
+    pub fn set_max_contexts(env: Env, max_contexts: u32) {
+        require_admin(&env);
+
+        env.storage()
+            .instance()
+            .set(&DataKey::MaxContexts, &max_contexts);
+    }
+
+    pub fn max_contexts(env: Env) -> u32 {
+        max_contexts(&env)
+    }
+
+    /// Caps how many levels of delegate signers may sit below this account,
+    /// so a deep or cyclic chain of delegates fails with `DelegationTooDeep`
+    /// before using up the budget. 0 turns delegate signatures away.
+    pub fn set_max_delegation_depth(env: Env, max_depth: u32) {
+        require_admin(&env);
+
+        env.storage()
+            .instance()
+            .set(&DataKey::MaxDelegationDepth, &max_depth);
+    }
+
+    pub fn max_delegation_depth(env: Env) -> u32 {
+        max_delegation_depth(&env)
+    }
+
+    /// Dry-runs `__check_auth` over `signatures`, returning the registered
+    /// signers that would count towards the threshold
+    ///
+    /// Signatures that would be rejected are left out rather than failing.
+    /// Checks that depend on the invocations being authorized, like scopes
+    /// and spend limits, are not applied, and signatures are checked as for
+    /// an ordinary rather than a web auth authorization. As in `__check_auth`, an invalid
+    /// signature traps in the host. Delegates are left out too, as checking
+    /// them would require their authorization. Signatures committing to
+    /// contexts under `full_context` can't be checked here.
+    pub fn check_signatures(
+        env: Env,
+        payload: BytesN<32>,
+        signatures: Vec<AccountSignature>,
+    ) -> Vec<SignerKey> {
+        let mut satisfied = Vec::new(&env);
+        if env.storage().instance().has(&DataKey::Frozen) {
+            return satisfied;
+        }
+
+        let payload = signed_message(&env, payload, false);
+        let nonce: u64 = env.storage().instance().get(&DataKey::Nonce).unwrap_or(0);
+        for signature in signatures.iter() {
+            let signer = signature.signer_key();
+            if satisfied.contains(&signer)
+                || signer.kind() == SignerKind::Delegate
+                || signature.check_algorithm().is_err()
+                || !signature.well_formed()
+                || !signature.nonce_matches(nonce)
+                || past_grace(&env, &signer)
+                || check_uses(&env, &signer).is_err()
+                || signer_record(&env, &signer)
+                    .is_none_or(|record| check_active(&env, &record).is_err())
+                || signature.verify_at(&env, &payload, nonce, 0).is_err()
+            {
+                continue;
+            }
+            satisfied.push_back(signer);
+        }
+        satisfied
+    }
+
+    /// Checks `signatures` over application data `message`, e.g. for sign in
+    /// or accepting terms, meet the account's threshold
+    ///
+    /// Signers sign sha256(MESSAGE_TAG || message), with any domain separator
+    /// applied as for an authorization. Nothing is recorded and the nonce is
+    /// neither checked nor consumed, so the same signatures verify for as long
+    /// as their signers stay registered. Scoped, spend-limited and
+    /// required-function signers are refused with `ContextNotAllowed`, since their limits describe
+    /// invocations, and so are delegates, which could only sign through the
+    /// host's authorization. As in `__check_auth`, an invalid signature traps.
+    pub fn verify_message(
+        env: Env,
+        message: Bytes,
+        signatures: Vec<AccountSignature>,
+    ) -> Result<(), AccountError> {
+        if !env.storage().instance().has(&DataKey::SignerList) {
+            return Err(AccountError::AccountClosed);
+        }
+        if env.storage().instance().has(&DataKey::Frozen) {
+            return Err(AccountError::AccountFrozen);
+        }
+        if signatures.is_empty() {
+            return Err(AccountError::NoSignatures);
+        }
+        if signatures.len() > max_signatures(&env) {
+            return Err(AccountError::TooManySignatures);
+        }
+        for signature in signatures.iter() {
+            signature.check_algorithm()?;
+            if !signature.well_formed() {
+                return Err(AccountError::MalformedSignature);
+            }
+        }
+
+        let mut tagged = Bytes::from_slice(&env, MESSAGE_TAG);
+        tagged.append(&message);
+        let digest = signed_message(&env, env.crypto().sha256(&tagged).to_bytes(), false);
+
+        let mut seen: Vec<SignerKey> = Vec::new(&env);
+        let mut total_weight: u32 = 0;
+        for signature in signatures.iter() {
+            let signer = signature.signer_key();
+            if seen.contains(&signer) {
+                return Err(AccountError::DuplicateSigner);
+            }
+            if signer.kind() == SignerKind::Delegate {
+                return Err(AccountError::ContextNotAllowed);
+            }
+            let Some(record) = signer_record(&env, &signer).filter(|_| !past_grace(&env, &signer))
+            else {
+                return Err(AccountError::UnknownSigner);
+            };
+            check_active(&env, &record)?;
+            check_uses(&env, &signer)?;
+            if context_restricted(&env, &signer, &record) {
+                return Err(AccountError::ContextNotAllowed);
+            }
+
+            signature.verify(&env, &digest, 0)?;
+            seen.push_back(signer);
+            total_weight = total_weight
+                .checked_add(record.weight)
+                .ok_or(AccountError::WeightOverflow)?;
+        }
+
+        for signer in seen.iter() {
+            if co_signer(&env, &signer).is_some_and(|co_signer| !seen.contains(&co_signer)) {
+                return Err(AccountError::CoSignerRequired);
+            }
+        }
+
+        let threshold: u32 = env.storage().instance().get(&DataKey::Threshold).unwrap();
+        if total_weight < threshold {
+            return Err(AccountError::ThresholdNotMet);
+        }
+        Ok(())
+    }
+
+    /// Checks `signatures` authorize `payload` exactly as `__check_auth`
+    /// would for an authorization covering no invocations, consuming the
+    /// nonce on success, for relayers and contracts that compute the payload
+    /// themselves
+    ///
+    /// As there, keys sign `payload` bound to the current nonce, so the
+    /// signatures only ever authorize it once.
+    ///
+    /// The account can't tell what `payload` stands for, so callers must
+    /// make sure it's bound to the one operation they mean to authorize,
+    /// including the network, this account and a value that can't repeat.
+    /// Signers whose scopes or limits depend on the invocations authorized
+    /// are refused with `ContextNotAllowed`. As the call needs no
+    /// authorization itself, anyone holding the signatures can submit them
+    /// first and consume the nonce.
+    pub fn authorize_hash(
+        env: Env,
+        payload: BytesN<32>,
+        signatures: Vec<AccountSignature>,
+    ) -> Result<(), AccountError> {
+        check_auth(env.clone(), payload, signatures, None)
+    }
+
+    /// Sets the separator signers prefix to the payload before hashing and
+    /// signing it, or clears it so signers sign the payload itself
+    pub fn set_domain_separator(env: Env, separator: Option<BytesN<32>>) {
+        require_admin(&env);
+
+        match separator {
+            Some(separator) => env
+                .storage()
+                .instance()
+                .set(&DataKey::DomainSeparator, &separator),
+            None => env.storage().instance().remove(&DataKey::DomainSeparator),
+        }
+    }
+
+    pub fn domain_separator(env: Env) -> Option<BytesN<32>> {
+        env.storage().instance().get(&DataKey::DomainSeparator)
+    }
+
+    /// Caps how many authorizations may succeed within each `window_ledgers`
+    /// ledgers, restarting the count for the new limit
+    pub fn set_rate_limit(env: Env, max_ops: u32, window_ledgers: u32) {
+        require_admin(&env);
+
+        let storage = env.storage().instance();
+        storage.set(
+            &DataKey::RateLimit,
+            &RateLimit {
+                max_ops,
+                window_ledgers,
+            },
+        );
+        storage.remove(&DataKey::RateWindow);
+    }
+
+    pub fn clear_rate_limit(env: Env) {
+        require_admin(&env);
+
+        let storage = env.storage().instance();
+        storage.remove(&DataKey::RateLimit);
+        storage.remove(&DataKey::RateWindow);
+    }
+
+    pub fn rate_limit(env: Env) -> Option<RateLimit> {
+        env.storage().instance().get(&DataKey::RateLimit)
+    }
+
+    /// The ledger timestamp of the last successful authorization
+    pub fn last_auth(env: Env) -> Option<u64> {
+        env.storage().instance().get(&DataKey::LastAuth)
+    }
+
+    /// Designates a key whose signature alone authorizes anything regardless
+    /// of the threshold, or clears it
+    ///
+    /// The master key need not be a registered signer, as it is meant for
+    /// recovery and administrative overrides rather than everyday use.
+    pub fn set_master_signer(env: Env, master: Option<BytesN<32>>) -> Result<(), AccountError> {
+        require_admin(&env);
+        record_change(&env)?;
+
+        match master {
+            Some(master) => env
+                .storage()
+                .instance()
+                .set(&DataKey::MasterSigner, &master),
+            None => env.storage().instance().remove(&DataKey::MasterSigner),
+        };
+        Ok(())
+    }
+
+    pub fn master_signer(env: Env) -> Option<BytesN<32>> {
+        env.storage().instance().get(&DataKey::MasterSigner)
+    }
+
+    /// Toggles verifying every signature before checking its key is
+    /// registered, so failures don't reveal which keys are signers
+    ///
+    /// Unknown keys then count for nothing rather than being rejected, which
+    /// costs a signature verification that would otherwise be skipped.
+    pub fn set_uniform_verification(env: Env, uniform: bool) {
+        require_admin(&env);
+
+        env.storage()
+            .instance()
+            .set(&DataKey::UniformVerification, &uniform);
+    }
+
+    pub fn uniform_verification(env: Env) -> bool {
+        env.storage()
+            .instance()
+            .get(&DataKey::UniformVerification)
+            .unwrap_or(false)
+    }
+
+    /// Toggles requiring signatures to commit to every context authorized,
+    /// including those of nested sub-invocations
+    ///
+    /// While set, signers sign sha256(message || sha256(contexts)) rather
+    /// than the message alone, where the contexts are the `auth_context`
+    /// vector `__check_auth` receives in XDR, so a signature over only some
+    /// of the calls fails verification. Authorizations over no contexts,
+    /// including `authorize_hash`, are refused with `ContextMismatch`.
+    ///
+    /// The host derives both the payload and `auth_context` from the same
+    /// invocation tree, so the contract can't detect a tree that differs
+    /// from the one the signer was shown. What this adds is that the signer
+    /// must have hashed the flattened list of calls themselves. The list
+    /// carries no nesting, so two trees flattening to the same calls in the
+    /// same order are indistinguishable. As with any bad signature, one over
+    /// the wrong contexts traps in the host rather than returning an error.
+    pub fn set_full_context(env: Env, enabled: bool) {
+        require_admin(&env);
+
+        env.storage()
+            .instance()
+            .set(&DataKey::FullContext, &enabled);
+    }
+
+    pub fn full_context(env: Env) -> bool {
+        env.storage()
+            .instance()
+            .get(&DataKey::FullContext)
+            .unwrap_or(false)
+    }
+
+    /// Toggles rejecting authorizations carrying more signatures than the threshold
+    pub fn set_exact_signatures(env: Env, exact: bool) {
+        require_admin(&env);
+
+        env.storage()
+            .instance()
+            .set(&DataKey::ExactSignatures, &exact);
+    }
+
+    pub fn exact_signatures(env: Env) -> bool {
+        env.storage()
+            .instance()
+            .get(&DataKey::ExactSignatures)
+            .unwrap_or(false)
+    }
+
+    /// The configured signature cap, defaulting to the number of registered signers
+    pub fn max_signatures(env: Env) -> u32 {
+        max_signatures(&env)
+    }
+
+    /// The code version of the running WASM
+    pub fn version(_env: Env) -> u32 {
+        VERSION
+    }
+
+    pub fn storage_version(env: Env) -> u32 {
+        storage_version(&env)
+    }
+
+    pub fn threshold(env: Env) -> u32 {
+        env.storage().instance().get(&DataKey::Threshold).unwrap()
+    }
+
+    /// Summarizes the account's signing policy in one read, for wallets to
+    /// display
+    pub fn policy(env: Env) -> AccountPolicy {
+        let storage = env.storage().instance();
+        AccountPolicy {
+            threshold: Self::threshold(env.clone()),
+            total_weight: total_weight(&env),
+            signer_count: signer_list(&env).len(),
+            frozen: storage.has(&DataKey::Frozen),
+            has_master_signer: storage.has(&DataKey::MasterSigner),
+            has_recovery: storage.has(&DataKey::Recovery),
+        }
+    }
+}
+
+/// The checks behind `__check_auth`, for a payload signed over
+/// `auth_context`, or over nothing the account can inspect when `None`
+fn check_auth(
+    env: Env,
+    signature_payload: BytesN<32>,
+    signatures: Vec<AccountSignature>,
+    auth_context: Option<Vec<Context>>,
+) -> Result<(), AccountError> {
+    let bare = auth_context.is_none();
+    let auth_context = auth_context.unwrap_or_else(|| Vec::new(&env));
+
+    // Every live account has a signer list, which only `close` removes
+    if !env.storage().instance().has(&DataKey::SignerList) {
+        return Err(AccountError::AccountClosed);
+    }
+
+    // An account that is its own admin authorizes its own `unfreeze`, which
+    // it could otherwise never do once frozen
+    if env.storage().instance().has(&DataKey::Frozen)
+        && !only_calls_self(&env, &auth_context, "unfreeze")
+    {
+        return Err(AccountError::AccountFrozen);
+    }
+
+    if signatures.is_empty() {
+        return Err(AccountError::NoSignatures);
+    }
+
+    if signatures.len() > max_signatures(&env) {
+        return Err(AccountError::TooManySignatures);
+    }
+    check_signature_bytes(&env, &signatures)?;
+
+    for signature in signatures.iter() {
+        signature.check_algorithm()?;
+    }
+
+    if !signatures.iter().all(|signature| signature.well_formed()) {
+        return Err(AccountError::MalformedSignature);
+    }
+    let signatures = canonicalize(&env, &signatures)?;
+    let depth = delegation_depth(&env, &auth_context);
+    check_delegation(&env, &signatures, depth)?;
+
+    if auth_context.len() > max_contexts(&env) {
+        return Err(AccountError::TooManyContexts);
+    }
+    check_frozen_tokens(&env, &auth_context)?;
+
+    let full_context = Account::full_context(env.clone());
+    if full_context && auth_context.is_empty() {
+        return Err(AccountError::ContextMismatch);
+    }
+
+    let nonce: u64 = env.storage().instance().get(&DataKey::Nonce).unwrap_or(0);
+
+    // Computed once, however many signatures are verified against it
+    let mut message = signed_message(&env, signature_payload, is_web_auth(&env, &auth_context));
+    if full_context {
+        message = bind_contexts(&env, message, &auth_context);
+    }
+
+    // The master key signing alone skips threshold counting entirely
+    if let Some(master) = Account::master_signer(env.clone()) {
+        let signature = signatures.get_unchecked(0);
+        if signatures.len() == 1 && signature.signer_key() == SignerKey::Ed25519(master) {
+            if !signature.nonce_matches(nonce) {
+                return Err(AccountError::BadNonce);
+            }
+            signature.verify_at(&env, &message, nonce, depth)?;
+            return record_auth(&env, nonce, vec![&env, signature.signer_key()]);
+        }
+    }
+
+    let threshold: u32 = env.storage().instance().get(&DataKey::Threshold).unwrap();
+    if Account::exact_signatures(env.clone()) && signatures.len() > threshold {
+        return Err(AccountError::TooManySignatures);
+    }
+
+    let uniform = Account::uniform_verification(env.clone());
+
+    // Every signer presented, in a deterministic key order, mapped to
+    // whether it counts towards the threshold
+    let mut satisfied: Map<SignerKey, bool> = Map::new(&env);
+    // Allowance each spend-limited signer would have left once charged
+    let mut allowances: Map<SignerKey, i128> = Map::new(&env);
+    let mut total_weight: u32 = 0;
+    for signature in signatures.iter() {
+        let signer = signature.signer_key();
+        let reject = |error| rejected(&env, &signer, error);
+
+        // Verifying before consulting storage makes the work done, and the
+        // way a bad signature fails, the same for registered and unknown keys
+        if uniform {
+            if !signature.nonce_matches(nonce) {
+                return Err(reject(AccountError::BadNonce));
+            }
+            signature
+                .verify_at(&env, &message, nonce, depth)
+                .map_err(reject)?;
+        }
+
+        let Some(record) = signer_record(&env, &signer).filter(|_| !past_grace(&env, &signer))
+        else {
+            if uniform {
+                satisfied.set(signer, false);
+                continue;
+            }
+            return Err(reject(AccountError::UnknownSigner));
+        };
+        check_active(&env, &record).map_err(reject)?;
+        check_uses(&env, &signer).map_err(reject)?;
+
+        if bare && context_restricted(&env, &signer, &record) {
+            return Err(reject(AccountError::ContextNotAllowed));
+        }
+        check_scope(&record, &auth_context).map_err(reject)?;
+        check_fn_scope(&env, &signer, &auth_context).map_err(reject)?;
+        check_required_fns(&env, &signer, &auth_context).map_err(reject)?;
+        if let Some(left) = check_spend_limit(&env, &signer, &auth_context).map_err(reject)? {
+            allowances.set(signer.clone(), left);
+        }
+
+        if !uniform {
+            if !signature.nonce_matches(nonce) {
+                return Err(reject(AccountError::BadNonce));
+            }
+            signature
+                .verify_at(&env, &message, nonce, depth)
+                .map_err(reject)?;
+        }
+
+        extend_signer_ttl(&env, &signer);
+        satisfied.set(signer, true);
+        total_weight = total_weight
+            .checked_add(record.weight)
+            .ok_or(AccountError::WeightOverflow)?;
+    }
+
+    for (signer, counted) in satisfied.iter() {
+        let Some(co_signer) = co_signer(&env, &signer).filter(|_| counted) else {
+            continue;
+        };
+        if satisfied.get(co_signer) != Some(true) {
+            return Err(rejected(&env, &signer, AccountError::CoSignerRequired));
+        }
+    }
+    check_tx_caps(&env, &satisfied, &auth_context)?;
+
+    // A veto still needs a registered signer, or anyone could block recovery
+    let vetoes = total_weight > 0 && only_cancels_recovery(&env, &auth_context);
+    if total_weight < threshold && !vetoes {
+        return Err(AccountError::ThresholdNotMet);
+    }
+
+    if let Some(signer) = charged_signer(&satisfied, |signer| allowances.get(signer.clone())) {
+        record_spend(&env, &signer, &auth_context);
+    }
+    if let Some(signer) = charged_signer(&satisfied, |signer| {
+        signer_uses(&env, signer).map(i128::from)
+    }) {
+        consume_use(&env, &signer);
+    }
+    let counted = Vec::from_iter(
+        &env,
+        satisfied
+            .iter()
+            .filter(|(_, counted)| *counted)
+            .map(|(signer, _)| signer),
+    );
+    record_auth(&env, nonce, counted)
+}
+
+#[contractimpl]
+impl CustomAccountInterface for Account {
+    type Error = AccountError;
+    type Signature = Vec<AccountSignature>;
+
+    /// Verifies the combined weight of the signers over `signature_payload`
+    /// meets the threshold
+    ///
+    /// Each signature must carry the account's current nonce, which is bumped
+    /// on every successful authorization. This is an explicit sequencing guard
+    /// for relayers on top of the host's own replay protection. Keys sign the
+    /// message bound to the nonce, sha256(message || nonce as 8 big-endian
+    /// bytes), so a signature seen once can't be resubmitted with the next
+    /// nonce. Delegates authorize the message itself.
+    ///
+    /// Signature verification is performed by the host, which traps on an
+    /// invalid signature. The failure surfaces as a host crypto error rather
+    /// than an `AccountError`, and cannot be intercepted by the contract.
+    ///
+    /// A rejection can't be recorded for reading back later: the host
+    /// discards every write a failed authorization made, and fails the whole
+    /// transaction with it. To find out why one was rejected, read the
+    /// `AccountError` code from the failed transaction's result, translated by
+    /// `error_message`, and the `AuthRejected` event in its diagnostics.
+    ///
+    /// Signatures are put in signer key order and exact repeats dropped
+    /// before any is verified, so the outcome, and the verifications done,
+    /// don't depend on the order they are submitted in. CPU cost is roughly
+    /// linear in `signatures.len()`.
+    ///
+    /// Every counted signer must be within its own spend limit and have a use
+    /// left, but each authorization is charged to one signer only, for spend
+    /// limits and uses separately. An unlimited signer is charged first,
+    /// which leaves every counter as it was. Otherwise the signer with the
+    /// most left after the charge pays, and on a tie the one whose key sorts
+    /// first.
+    fn __check_auth(
+        env: Env,
+        signature_payload: Hash<32>,
+        signatures: Self::Signature,
+        auth_context: Vec<Context>,
+    ) -> Result<(), AccountError> {
+        check_auth(
+            env,
+            signature_payload.to_bytes(),
+            signatures,
+            Some(auth_context),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test;
+#[cfg(test)]
+mod test_fuzz;
+#[cfg(test)]
+mod test_spec;
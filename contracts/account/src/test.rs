@@ -0,0 +1,5326 @@
+#![cfg(test)]
+extern crate std;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use ed25519_dalek::{Signer, SigningKey, VerifyingKey};
+use p256::ecdsa::signature::hazmat::PrehashSigner;
+use rand::{rngs::OsRng, RngCore};
+use sha2::{Digest, Sha256};
+use soroban_sdk::{
+    auth::{Context, ContractContext},
+    symbol_short,
+    testutils::{
+        storage::{Instance as _, Persistent as _},
+        Address as _, AuthorizedFunction, AuthorizedInvocation, BytesN as _, Events as _, Ledger,
+        MockAuth, MockAuthInvoke,
+    },
+    vec,
+    xdr::{self, ContractCostType, Limited, Limits, ReadXdr, ScMetaEntry, ScMetaV0, WriteXdr},
+    Address, Bytes, BytesN, Env, Event, Executable, IntoVal, InvokeError, Map, String, Symbol,
+    TryFromVal, Val, Vec,
+};
+use std::format;
+
+use crate::{
+    decode_transfer, error_message,
+    events::{
+        AdminProposed, AdminSet, AuthRejected, AuthSucceeded, Closed, SessionsRevoked, SignerAdded,
+        SignerRemoved, SignerSwapped, SignersReplaced, ThresholdSet, TokenFrozen, Upgraded,
+    },
+    testutils::{self, web_auth_message},
+    Account, AccountClient, AccountError, AccountPolicy, AccountSignature, AdminMode, DataKey,
+    RateLimit, RecoveryRequest, Secp256r1Signature, Signature, SignerKey, SignerKind, SignerRecord,
+    SignerStatus, SpendRecord, ALGORITHM_ED25519, ALGORITHM_SECP256R1, INSTANCE_BUMP_AMOUNT,
+    MAX_RECOVERY_DELAY, MAX_SIGNERS_PAGE, MAX_TOTAL_WEIGHT, MESSAGE_TAG, MIN_RECOVERY_DELAY,
+    SIGNER_BUMP_AMOUNT, VERSION, WEAK_ED25519_KEYS,
+};
+
+/// Produced by `stellar contract build`
+const ACCOUNT_WASM: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/../target/wasm32v1-none/release/account.wasm"
+);
+
+struct Setup {
+    env: Env,
+    admin: Address,
+    client: AccountClient<'static>,
+    signing_key: SigningKey,
+}
+
+fn generate_signer(env: &Env) -> (SigningKey, BytesN<32>) {
+    testutils::random_signer(env)
+}
+
+fn sign(env: &Env, signing_key: &SigningKey, payload: &BytesN<32>) -> AccountSignature {
+    sign_with_nonce(env, signing_key, payload, 0)
+}
+
+fn sign_with_nonce(
+    env: &Env,
+    signing_key: &SigningKey,
+    payload: &BytesN<32>,
+    nonce: u64,
+) -> AccountSignature {
+    AccountSignature::Ed25519(testutils::sign_payload(env, signing_key, payload, nonce))
+}
+
+fn sign_message(env: &Env, signing_key: &SigningKey, digest: &BytesN<32>) -> AccountSignature {
+    AccountSignature::Ed25519(testutils::sign_message(env, signing_key, digest))
+}
+
+fn generate_passkey(env: &Env) -> (p256::ecdsa::SigningKey, BytesN<65>) {
+    let signing_key = p256::ecdsa::SigningKey::random(&mut OsRng);
+    let point = signing_key.verifying_key().to_encoded_point(false);
+    let public_key = BytesN::from_array(env, point.as_bytes().try_into().unwrap());
+    (signing_key, public_key)
+}
+
+fn sign_passkey(
+    env: &Env,
+    signing_key: &p256::ecdsa::SigningKey,
+    challenge: &BytesN<32>,
+) -> AccountSignature {
+    sign_assertion(env, signing_key, challenge, 0x49, "webauthn.get")
+}
+
+/// Signs a passkey assertion with the given `authenticatorData` flags and
+/// `clientDataJSON` type
+fn sign_assertion(
+    env: &Env,
+    signing_key: &p256::ecdsa::SigningKey,
+    challenge: &BytesN<32>,
+    flags: u8,
+    client_data_type: &str,
+) -> AccountSignature {
+    let mut authenticator_data = [0x49u8; 37];
+    authenticator_data[32] = flags;
+    let challenge = testutils::nonce_bound_message(env, challenge, 0);
+    let client_data_json = format!(
+        r#"{{"type":"{client_data_type}","challenge":"{}","origin":"https://localhost"}}"#,
+        URL_SAFE_NO_PAD.encode(challenge.to_array())
+    );
+
+    let mut message = authenticator_data.to_vec();
+    message.extend_from_slice(&Sha256::digest(client_data_json.as_bytes()));
+    let digest = Sha256::digest(&message);
+
+    let signature: p256::ecdsa::Signature = signing_key.sign_prehash(&digest).unwrap();
+    let signature = signature.normalize_s().unwrap_or(signature);
+    let point = signing_key.verifying_key().to_encoded_point(false);
+
+    AccountSignature::Secp256r1(Secp256r1Signature {
+        public_key: BytesN::from_array(env, point.as_bytes().try_into().unwrap()),
+        authenticator_data: Bytes::from_slice(env, &authenticator_data),
+        client_data_json: Bytes::from_slice(env, client_data_json.as_bytes()),
+        signature: BytesN::from_array(env, &signature.to_bytes().into()),
+        nonce: 0,
+    })
+}
+
+fn setup(threshold: u32) -> Setup {
+    setup_weighted(1, threshold)
+}
+
+fn setup_weighted(weight: u32, threshold: u32) -> Setup {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let (signing_key, signer) = generate_signer(&env);
+    let contract_id = env.register(
+        Account,
+        (
+            AdminMode::External(admin.clone()),
+            vec![&env, signer],
+            weight,
+            weight.min(threshold),
+        ),
+    );
+    let client = AccountClient::new(&env, &contract_id);
+    // Tests raise the threshold past the lone signer's weight before adding
+    // the signers that meet it, which the constructor would refuse
+    env.as_contract(&contract_id, || {
+        env.storage()
+            .instance()
+            .set(&DataKey::Threshold, &threshold);
+    });
+
+    Setup {
+        env,
+        admin,
+        client,
+        signing_key,
+    }
+}
+
+fn check_auth(
+    env: &Env,
+    client: &AccountClient,
+    payload: &BytesN<32>,
+    signatures: Vec<AccountSignature>,
+) -> Result<(), AccountError> {
+    check_auth_with_context(env, client, payload, signatures, vec![env])
+}
+
+fn check_auth_with_context(
+    env: &Env,
+    client: &AccountClient,
+    payload: &BytesN<32>,
+    signatures: Vec<AccountSignature>,
+    auth_context: Vec<Context>,
+) -> Result<(), AccountError> {
+    env.try_invoke_contract_check_auth::<AccountError>(
+        &client.address,
+        payload,
+        signatures.into_val(env),
+        &auth_context,
+    )
+    .map_err(|e| e.unwrap())
+}
+
+fn contract_context(env: &Env, contract: &Address, fn_name: &str) -> Context {
+    Context::Contract(ContractContext {
+        contract: contract.clone(),
+        fn_name: Symbol::new(env, fn_name),
+        args: vec![env],
+    })
+}
+
+fn add_signer(setup: &Setup) -> SigningKey {
+    add_weighted_signer(setup, 1)
+}
+
+fn add_weighted_signer(setup: &Setup, weight: u32) -> SigningKey {
+    let (signing_key, signer) = generate_signer(&setup.env);
+    setup.env.mock_all_auths();
+    setup
+        .client
+        .add_signer(&setup.admin, &signer, &weight, &None, &None, &None);
+    signing_key
+}
+
+#[test]
+fn test_single_signer() {
+    let setup = setup(1);
+    let env = &setup.env;
+    let payload = BytesN::random(env);
+
+    let signatures = vec![env, sign(env, &setup.signing_key, &payload)];
+    assert_eq!(check_auth(env, &setup.client, &payload, signatures), Ok(()));
+}
+
+#[test]
+fn test_threshold_met() {
+    let setup = setup(2);
+    let env = &setup.env;
+    let second = add_signer(&setup);
+    add_signer(&setup);
+    let payload = BytesN::random(env);
+
+    let signatures = vec![
+        env,
+        sign(env, &setup.signing_key, &payload),
+        sign(env, &second, &payload),
+    ];
+    assert_eq!(check_auth(env, &setup.client, &payload, signatures), Ok(()));
+}
+
+#[test]
+fn test_threshold_not_met() {
+    let setup = setup(2);
+    let env = &setup.env;
+    add_signer(&setup);
+    let payload = BytesN::random(env);
+
+    let signatures = vec![env, sign(env, &setup.signing_key, &payload)];
+    assert_eq!(
+        check_auth(env, &setup.client, &payload, signatures),
+        Err(AccountError::ThresholdNotMet)
+    );
+}
+
+#[test]
+fn test_unknown_signer() {
+    let setup = setup(1);
+    let env = &setup.env;
+    let (unknown, _) = generate_signer(env);
+    let payload = BytesN::random(env);
+
+    let signatures = vec![env, sign(env, &unknown, &payload)];
+    assert_eq!(
+        check_auth(env, &setup.client, &payload, signatures),
+        Err(AccountError::UnknownSigner)
+    );
+}
+
+#[test]
+fn test_unknown_signer_event() {
+    let setup = setup(1);
+    let env = &setup.env;
+    let (unknown, public_key) = generate_signer(env);
+    let payload = BytesN::random(env);
+
+    let signatures = vec![env, sign(env, &unknown, &payload)];
+    assert_eq!(
+        check_auth(env, &setup.client, &payload, signatures),
+        Err(AccountError::UnknownSigner)
+    );
+    // Rejecting the authorization rolls the event back, leaving it recorded
+    // only as part of the failed call
+    assert!(env.events().all().is_empty());
+    let rejected = AuthRejected {
+        reason: symbol_short!("unknown"),
+        signer: SignerKey::Ed25519(public_key),
+    };
+    let events = env.to_snapshot().events.0;
+    let event = events.last().unwrap();
+    assert!(event.failed_call);
+    let xdr::ContractEventBody::V0(body) = &event.event.body;
+    let topics: std::vec::Vec<xdr::ScVal> = rejected
+        .topics(env)
+        .iter()
+        .map(|topic| xdr::ScVal::try_from_val(env, &topic).unwrap())
+        .collect();
+    assert_eq!(body.topics.to_vec(), topics);
+    assert_eq!(
+        body.data,
+        xdr::ScVal::try_from_val(env, &rejected.data(env)).unwrap()
+    );
+}
+
+#[test]
+fn test_auth_success_event() {
+    let setup = setup(2);
+    let env = &setup.env;
+    let second = add_signer(&setup);
+    let payload = BytesN::random(env);
+    let public_key = |key: &SigningKey| BytesN::from_array(env, &key.verifying_key().to_bytes());
+    let event = |signing_keys: &[&SigningKey]| {
+        // Signers count in key order
+        let mut keys: std::vec::Vec<_> = signing_keys.iter().map(|key| public_key(key)).collect();
+        keys.sort();
+        let succeeded = AuthSucceeded {
+            signers: Vec::from_iter(
+                env,
+                keys.into_iter()
+                    .map(|key| setup.client.signer_fingerprint(&key)),
+            ),
+        };
+        vec![
+            env,
+            (
+                setup.client.address.clone(),
+                succeeded.topics(env),
+                succeeded.data(env),
+            ),
+        ]
+    };
+
+    let signatures = vec![
+        env,
+        sign_with_nonce(env, &second, &payload, setup.client.nonce()),
+        sign_with_nonce(env, &setup.signing_key, &payload, setup.client.nonce()),
+    ];
+    assert_eq!(check_auth(env, &setup.client, &payload, signatures), Ok(()));
+    assert_eq!(env.events().all(), event(&[&setup.signing_key, &second]));
+
+    env.mock_all_auths();
+    setup.client.set_threshold(&1);
+    let signatures = vec![
+        env,
+        sign_with_nonce(env, &second, &payload, setup.client.nonce()),
+    ];
+    assert_eq!(check_auth(env, &setup.client, &payload, signatures), Ok(()));
+    assert_eq!(env.events().all(), event(&[&second]));
+
+    // The master key signing alone is named too
+    setup
+        .client
+        .set_master_signer(&Some(public_key(&setup.signing_key)));
+    let signatures = vec![
+        env,
+        sign_with_nonce(env, &setup.signing_key, &payload, setup.client.nonce()),
+    ];
+    assert_eq!(check_auth(env, &setup.client, &payload, signatures), Ok(()));
+    assert_eq!(env.events().all(), event(&[&setup.signing_key]));
+}
+
+#[test]
+fn test_set_threshold() {
+    let setup = setup(1);
+    setup.env.mock_all_auths();
+    add_signer(&setup);
+
+    setup.client.set_threshold(&2);
+    let set = ThresholdSet { threshold: 2 };
+    assert_eq!(
+        setup.env.events().all(),
+        vec![
+            &setup.env,
+            (
+                setup.client.address.clone(),
+                set.topics(&setup.env),
+                set.data(&setup.env)
+            )
+        ]
+    );
+    assert_eq!(setup.client.threshold(), 2);
+
+    setup.client.set_threshold(&1);
+    assert_eq!(setup.client.threshold(), 1);
+}
+
+#[test]
+fn test_zero_threshold() {
+    let setup = setup(1);
+    setup.env.mock_all_auths();
+
+    assert_eq!(
+        setup.client.try_set_threshold(&0),
+        Err(Ok(AccountError::ThresholdUnsatisfiable))
+    );
+    assert_eq!(setup.client.threshold(), 1);
+}
+
+#[test]
+fn test_unsatisfiable_threshold() {
+    let setup = setup(1);
+    let env = &setup.env;
+    env.mock_all_auths();
+    let second = add_weighted_signer(&setup, 2);
+
+    assert_eq!(
+        setup.client.try_set_threshold(&4),
+        Err(Ok(AccountError::ThresholdUnsatisfiable))
+    );
+    setup.client.set_threshold(&3);
+
+    // Either signer leaving would put the threshold out of reach
+    let owner = BytesN::from_array(env, &setup.signing_key.verifying_key().to_bytes());
+    let second = BytesN::from_array(env, &second.verifying_key().to_bytes());
+    for signer in [&owner, &second] {
+        assert_eq!(
+            setup.client.try_remove_signer(&setup.admin, signer),
+            Err(Ok(AccountError::ThresholdUnsatisfiable))
+        );
+        assert!(setup.client.is_signer(signer));
+    }
+
+    setup.client.set_threshold(&2);
+    setup.client.remove_signer(&setup.admin, &owner);
+    assert!(!setup.client.is_signer(&owner));
+}
+
+#[test]
+fn test_max_sig_bytes() {
+    let setup = setup(2);
+    let env = &setup.env;
+    let second = add_signer(&setup);
+    env.mock_all_auths();
+    assert_eq!(setup.client.max_sig_bytes(), None);
+
+    // Each ed25519 signature counts as 108 bytes
+    setup.client.set_max_sig_bytes(&216);
+    let payload = BytesN::random(env);
+    let signatures = vec![
+        env,
+        sign(env, &setup.signing_key, &payload),
+        sign(env, &second, &payload),
+    ];
+    assert_eq!(check_auth(env, &setup.client, &payload, signatures), Ok(()));
+
+    setup.client.set_max_sig_bytes(&215);
+    let signatures = vec![
+        env,
+        sign_with_nonce(env, &setup.signing_key, &payload, setup.client.nonce()),
+        sign_with_nonce(env, &second, &payload, setup.client.nonce()),
+    ];
+    assert_eq!(
+        check_auth(env, &setup.client, &payload, signatures),
+        Err(AccountError::SignaturesTooLarge)
+    );
+}
+
+#[test]
+fn test_signature_algorithm() {
+    let setup = setup(1);
+    let env = &setup.env;
+    let payload = BytesN::random(env);
+    let with_algorithm = |algorithm| {
+        let AccountSignature::Ed25519(signature) =
+            sign_with_nonce(env, &setup.signing_key, &payload, setup.client.nonce())
+        else {
+            unreachable!()
+        };
+        vec![
+            env,
+            AccountSignature::Ed25519(Signature {
+                algorithm,
+                ..signature
+            }),
+        ]
+    };
+
+    assert_eq!(
+        check_auth(
+            env,
+            &setup.client,
+            &payload,
+            with_algorithm(ALGORITHM_SECP256R1)
+        ),
+        Err(AccountError::MalformedSignature)
+    );
+    assert_eq!(
+        check_auth(env, &setup.client, &payload, with_algorithm(7)),
+        Err(AccountError::UnsupportedAlgorithm)
+    );
+    assert_eq!(
+        check_auth(
+            env,
+            &setup.client,
+            &payload,
+            with_algorithm(ALGORITHM_ED25519)
+        ),
+        Ok(())
+    );
+}
+
+#[test]
+fn test_verify_one() {
+    let setup = setup(1);
+    let env = &setup.env;
+    let payload = BytesN::random(env);
+    let other_payload = BytesN::random(env);
+    let verify_one = |signature: &AccountSignature| {
+        let AccountSignature::Ed25519(signature) = signature else {
+            panic!("not an ed25519 signature");
+        };
+        std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            Account::verify_one(env, &payload, signature)
+        }))
+        .is_ok_and(|valid| valid)
+    };
+
+    let valid = sign(env, &setup.signing_key, &payload);
+    assert!(verify_one(&valid));
+    assert_eq!(
+        check_auth(env, &setup.client, &payload, vec![env, valid]),
+        Ok(())
+    );
+
+    // Both trap on a signature over a different payload
+    let invalid = sign_with_nonce(env, &setup.signing_key, &other_payload, 1);
+    assert!(!verify_one(&invalid));
+    assert_eq!(
+        env.try_invoke_contract_check_auth::<AccountError>(
+            &setup.client.address,
+            &payload,
+            vec![env, invalid].into_val(env),
+            &vec![env],
+        ),
+        Err(Err(InvokeError::Abort))
+    );
+}
+
+/// The digest signers sign to approve application data `message`
+fn message_digest(env: &Env, message: &[u8]) -> BytesN<32> {
+    let mut tagged = MESSAGE_TAG.to_vec();
+    tagged.extend_from_slice(message);
+    BytesN::from_array(env, &Sha256::digest(&tagged).into())
+}
+
+#[test]
+fn test_authorize_hash() {
+    let setup = setup(2);
+    let env = &setup.env;
+    let second = add_signer(&setup);
+    let payload = BytesN::random(env);
+
+    // The same outcomes as `__check_auth`, on a twin account with the same
+    // signers
+    let public_key = |key: &SigningKey| BytesN::from_array(env, &key.verifying_key().to_bytes());
+    let twin = AccountClient::new(
+        env,
+        &env.register(
+            Account,
+            (
+                AdminMode::External(setup.admin.clone()),
+                vec![env, public_key(&setup.signing_key), public_key(&second)],
+                1u32,
+                2u32,
+            ),
+        ),
+    );
+    for (signatures, expected) in [
+        (
+            vec![env, sign(env, &setup.signing_key, &payload)],
+            Err(AccountError::ThresholdNotMet),
+        ),
+        (
+            vec![
+                env,
+                sign(env, &setup.signing_key, &payload),
+                sign_with_nonce(env, &setup.signing_key, &payload, 1),
+            ],
+            Err(AccountError::DuplicateSigner),
+        ),
+        (
+            vec![
+                env,
+                sign(env, &setup.signing_key, &payload),
+                sign_with_nonce(env, &second, &payload, 1),
+            ],
+            Err(AccountError::BadNonce),
+        ),
+        (
+            vec![
+                env,
+                sign(env, &setup.signing_key, &payload),
+                sign(env, &second, &payload),
+            ],
+            Ok(()),
+        ),
+    ] {
+        assert_eq!(
+            setup
+                .client
+                .try_authorize_hash(&payload, &signatures)
+                .map_err(|e| e.unwrap()),
+            expected.map(Ok)
+        );
+        assert_eq!(check_auth(env, &twin, &payload, signatures), expected);
+    }
+    assert_eq!(setup.client.nonce(), 1);
+
+    // Replaying the signatures fails once the nonce has moved on
+    let signatures = vec![
+        env,
+        sign(env, &setup.signing_key, &payload),
+        sign(env, &second, &payload),
+    ];
+    assert_eq!(
+        setup.client.try_authorize_hash(&payload, &signatures),
+        Err(Ok(AccountError::BadNonce))
+    );
+    // and resending them with the new nonce fails verification, as they
+    // weren't signed over it
+    let retagged = Vec::from_iter(
+        env,
+        signatures.iter().map(|signature| {
+            let AccountSignature::Ed25519(signature) = signature else {
+                unreachable!()
+            };
+            AccountSignature::Ed25519(Signature {
+                nonce: 1,
+                ..signature
+            })
+        }),
+    );
+    assert!(matches!(
+        setup.client.try_authorize_hash(&payload, &retagged),
+        Err(Err(_))
+    ));
+    assert_eq!(setup.client.nonce(), 1);
+
+    // A scoped signer's limits can't apply to a bare hash
+    env.mock_all_auths();
+    setup.client.set_signer_scope(
+        &SignerKey::Ed25519(BytesN::from_array(env, &second.verifying_key().to_bytes())),
+        &vec![env, Address::generate(env)],
+    );
+    let signatures = vec![
+        env,
+        sign_with_nonce(env, &setup.signing_key, &payload, 1),
+        sign_with_nonce(env, &second, &payload, 1),
+    ];
+    assert_eq!(
+        setup.client.try_authorize_hash(&payload, &signatures),
+        Err(Ok(AccountError::ContextNotAllowed))
+    );
+
+    setup.client.freeze(&setup.admin);
+    assert_eq!(
+        setup.client.try_authorize_hash(&payload, &signatures),
+        Err(Ok(AccountError::AccountFrozen))
+    );
+}
+
+#[test]
+fn test_full_context() {
+    let setup = setup(1);
+    let env = &setup.env;
+    assert!(!setup.client.full_context());
+    env.mock_all_auths();
+    setup.client.set_full_context(&true);
+    assert_eq!(env.auths()[0].0, setup.admin);
+    assert!(setup.client.full_context());
+
+    let router = Address::generate(env);
+    let token = Address::generate(env);
+    let single = vec![env, contract_context(env, &token, "transfer")];
+    // A swap whose nested token transfer needs the account's authorization
+    // too, flattened by the host into one context per call
+    let nested = vec![
+        env,
+        contract_context(env, &router, "swap"),
+        contract_context(env, &token, "transfer"),
+    ];
+
+    let mut nonce = 0;
+    for auth_context in [single, nested.clone()] {
+        let payload = BytesN::random(env);
+        let message = testutils::context_bound_message(env, &payload, &auth_context);
+        let signatures = vec![
+            env,
+            sign_with_nonce(env, &setup.signing_key, &message, nonce),
+        ];
+        assert_eq!(
+            check_auth_with_context(env, &setup.client, &payload, signatures, auth_context),
+            Ok(())
+        );
+        nonce += 1;
+    }
+
+    // Signatures over the payload alone, or committing to only the
+    // top-level call, fail verification
+    let payload = BytesN::random(env);
+    let top_level = vec![env, nested.get(0).unwrap()];
+    for message in [
+        payload.clone(),
+        testutils::context_bound_message(env, &payload, &top_level),
+    ] {
+        let signatures = vec![
+            env,
+            sign_with_nonce(env, &setup.signing_key, &message, nonce),
+        ];
+        assert!(matches!(
+            env.try_invoke_contract_check_auth::<AccountError>(
+                &setup.client.address,
+                &payload,
+                signatures.into_val(env),
+                &nested,
+            ),
+            Err(Err(_))
+        ));
+    }
+
+    // Nothing to commit to
+    let signatures = vec![
+        env,
+        sign_with_nonce(env, &setup.signing_key, &payload, nonce),
+    ];
+    assert_eq!(
+        check_auth(env, &setup.client, &payload, signatures.clone()),
+        Err(AccountError::ContextMismatch)
+    );
+    assert_eq!(
+        setup.client.try_authorize_hash(&payload, &signatures),
+        Err(Ok(AccountError::ContextMismatch))
+    );
+
+    setup.client.set_full_context(&false);
+    assert_eq!(check_auth(env, &setup.client, &payload, signatures), Ok(()));
+}
+
+#[test]
+fn test_verify_message() {
+    let setup = setup(2);
+    let env = &setup.env;
+    let second = add_signer(&setup);
+    let message = b"I accept the terms of service";
+    let digest = message_digest(env, message);
+    let message = Bytes::from_slice(env, message);
+
+    let signatures = vec![
+        env,
+        sign_message(env, &setup.signing_key, &digest),
+        sign_message(env, &second, &digest),
+    ];
+    assert_eq!(
+        setup.client.try_verify_message(&message, &signatures),
+        Ok(Ok(()))
+    );
+    // Verifying consumes nothing, so the signatures keep verifying
+    assert_eq!(setup.client.nonce(), 0);
+    assert_eq!(
+        setup.client.try_verify_message(&message, &signatures),
+        Ok(Ok(()))
+    );
+
+    assert_eq!(
+        setup
+            .client
+            .try_verify_message(&message, &vec![env, sign_message(env, &second, &digest)]),
+        Err(Ok(AccountError::ThresholdNotMet))
+    );
+}
+
+#[test]
+fn test_verify_message_tampered() {
+    let setup = setup(1);
+    let env = &setup.env;
+    let digest = message_digest(env, b"pay 10 XLM");
+    let signatures = vec![env, sign_message(env, &setup.signing_key, &digest)];
+
+    assert_eq!(
+        setup
+            .client
+            .try_verify_message(&Bytes::from_slice(env, b"pay 10 XLM"), &signatures),
+        Ok(Ok(()))
+    );
+    assert_eq!(
+        setup
+            .client
+            .try_verify_message(&Bytes::from_slice(env, b"pay 99 XLM"), &signatures),
+        Err(Err(InvokeError::Abort))
+    );
+
+    // Scoped signers are limited to invocations and can't sign messages
+    env.mock_all_auths();
+    let signer = SignerKey::Ed25519(setup.signing_key.verifying_key().to_bytes().into_val(env));
+    setup
+        .client
+        .set_signer_scope(&signer, &vec![env, Address::generate(env)]);
+    assert_eq!(
+        setup
+            .client
+            .try_verify_message(&Bytes::from_slice(env, b"pay 10 XLM"), &signatures),
+        Err(Ok(AccountError::ContextNotAllowed))
+    );
+}
+
+#[test]
+fn test_invalid_signature() {
+    let setup = setup(1);
+    let env = &setup.env;
+    let payload = BytesN::random(env);
+    let other_payload = BytesN::random(env);
+
+    // A bad signature traps in the host rather than surfacing an AccountError
+    let signatures = vec![env, sign(env, &setup.signing_key, &other_payload)];
+    assert_eq!(
+        env.try_invoke_contract_check_auth::<AccountError>(
+            &setup.client.address,
+            &payload,
+            signatures.into_val(env),
+            &vec![env],
+        ),
+        Err(Err(InvokeError::Abort))
+    );
+}
+
+#[test]
+fn test_secp256r1_signer() {
+    let setup = setup(2);
+    let env = &setup.env;
+    env.mock_all_auths();
+    let (passkey, public_key) = generate_passkey(env);
+    setup.client.add_secp256r1_signer(&public_key, &1, &None);
+    let payload = BytesN::random(env);
+
+    let signatures = vec![
+        env,
+        sign(env, &setup.signing_key, &payload),
+        sign_passkey(env, &passkey, &payload),
+    ];
+    assert_eq!(check_auth(env, &setup.client, &payload, signatures), Ok(()));
+}
+
+#[test]
+fn test_signer_kinds() {
+    let setup = setup(1);
+    let env = &setup.env;
+    env.mock_all_auths();
+    let (passkey, public_key) = generate_passkey(env);
+    setup.client.add_secp256r1_signer(&public_key, &1, &None);
+    let signer = BytesN::from_array(env, &setup.signing_key.verifying_key().to_bytes());
+
+    assert_eq!(
+        setup.client.list_signers_of_kind(&SignerKind::Ed25519),
+        vec![env, SignerKey::Ed25519(signer)]
+    );
+    assert_eq!(
+        setup.client.list_signers_of_kind(&SignerKind::Secp256r1),
+        vec![env, SignerKey::Secp256r1(public_key)]
+    );
+
+    // Either kind can authorize on its own
+    let payload = BytesN::random(env);
+    let signatures = vec![env, sign_passkey(env, &passkey, &payload)];
+    assert_eq!(check_auth(env, &setup.client, &payload, signatures), Ok(()));
+    let signatures = vec![
+        env,
+        sign_with_nonce(env, &setup.signing_key, &payload, setup.client.nonce()),
+    ];
+    assert_eq!(check_auth(env, &setup.client, &payload, signatures), Ok(()));
+}
+
+#[test]
+fn test_signer_kind_counts() {
+    let setup = setup(1);
+    let env = &setup.env;
+    env.mock_all_auths();
+    let counts = |ed25519: u32, secp256r1: u32, delegate: u32| {
+        Map::from_array(
+            env,
+            [
+                (Symbol::new(env, "ed25519"), ed25519),
+                (Symbol::new(env, "secp256r1"), secp256r1),
+                (Symbol::new(env, "delegate"), delegate),
+            ],
+        )
+    };
+    assert_eq!(setup.client.signer_kind_counts(), counts(1, 0, 0));
+
+    let second = add_signer(&setup);
+    let (_, passkey) = generate_passkey(env);
+    setup.client.add_secp256r1_signer(&passkey, &1, &None);
+    setup
+        .client
+        .add_delegate_signer(&setup.admin, &Address::generate(env), &1);
+    assert_eq!(setup.client.signer_kind_counts(), counts(2, 1, 1));
+
+    let second = BytesN::from_array(env, &second.verifying_key().to_bytes());
+    setup.client.remove_signer(&setup.admin, &second);
+    assert_eq!(setup.client.signer_kind_counts(), counts(1, 1, 1));
+}
+
+#[test]
+fn test_malformed_secp256r1_signature() {
+    let setup = setup(1);
+    let env = &setup.env;
+    env.mock_all_auths();
+    let (passkey, public_key) = generate_passkey(env);
+    setup.client.add_secp256r1_signer(&public_key, &1, &None);
+    let payload = BytesN::random(env);
+
+    let malform = |malform: &dyn Fn(&mut Secp256r1Signature)| {
+        let AccountSignature::Secp256r1(mut signature) = sign_passkey(env, &passkey, &payload)
+        else {
+            unreachable!()
+        };
+        malform(&mut signature);
+        vec![env, AccountSignature::Secp256r1(signature)]
+    };
+    let compressed_key = malform(&|signature| {
+        signature.public_key = BytesN::from_array(env, &[0x02; 65]);
+    });
+    let short_authenticator_data = malform(&|signature| {
+        signature.authenticator_data = Bytes::from_slice(env, &[0x49; 36]);
+    });
+    let empty_client_data = malform(&|signature| {
+        signature.client_data_json = Bytes::new(env);
+    });
+
+    for signatures in [compressed_key, short_authenticator_data, empty_client_data] {
+        assert_eq!(
+            check_auth(env, &setup.client, &payload, signatures.clone()),
+            Err(AccountError::MalformedSignature)
+        );
+        assert!(setup
+            .client
+            .check_signatures(&payload, &signatures)
+            .is_empty());
+    }
+}
+
+#[test]
+fn test_secp256r1_wrong_challenge() {
+    let setup = setup(1);
+    let env = &setup.env;
+    env.mock_all_auths();
+    let (passkey, public_key) = generate_passkey(env);
+    setup.client.add_secp256r1_signer(&public_key, &1, &None);
+    let payload = BytesN::random(env);
+
+    let signatures = vec![env, sign_passkey(env, &passkey, &BytesN::random(env))];
+    assert_eq!(
+        check_auth(env, &setup.client, &payload, signatures),
+        Err(AccountError::InvalidClientData)
+    );
+}
+
+#[test]
+fn test_secp256r1_wrong_type() {
+    let setup = setup(1);
+    let env = &setup.env;
+    env.mock_all_auths();
+    let (passkey, public_key) = generate_passkey(env);
+    setup.client.add_secp256r1_signer(&public_key, &1, &None);
+    let payload = BytesN::random(env);
+
+    // Client data from registering a credential is no assertion
+    let signatures = vec![
+        env,
+        sign_assertion(env, &passkey, &payload, 0x49, "webauthn.create"),
+    ];
+    assert_eq!(
+        check_auth(env, &setup.client, &payload, signatures),
+        Err(AccountError::InvalidClientData)
+    );
+}
+
+#[test]
+fn test_secp256r1_user_not_present() {
+    let setup = setup(1);
+    let env = &setup.env;
+    env.mock_all_auths();
+    let (passkey, public_key) = generate_passkey(env);
+    setup.client.add_secp256r1_signer(&public_key, &1, &None);
+    let payload = BytesN::random(env);
+
+    let signatures = vec![
+        env,
+        sign_assertion(env, &passkey, &payload, 0x48, "webauthn.get"),
+    ];
+    assert_eq!(
+        check_auth(env, &setup.client, &payload, signatures),
+        Err(AccountError::UserNotPresent)
+    );
+
+    // Presence is the only flag required
+    let signatures = vec![
+        env,
+        sign_assertion(env, &passkey, &payload, 0x01, "webauthn.get"),
+    ];
+    assert_eq!(check_auth(env, &setup.client, &payload, signatures), Ok(()));
+}
+
+#[test]
+fn test_add_signer() {
+    let setup = setup(1);
+    let env = &setup.env;
+    let signing_key = add_signer(&setup);
+    let payload = BytesN::random(env);
+
+    let signatures = vec![env, sign(env, &signing_key, &payload)];
+    assert_eq!(check_auth(env, &setup.client, &payload, signatures), Ok(()));
+}
+
+/// An invocation of `contract`'s `fn_name` with `args`, as authorized
+fn invocation(
+    env: &Env,
+    contract: &Address,
+    fn_name: &str,
+    args: Vec<Val>,
+) -> xdr::SorobanAuthorizedInvocation {
+    let args: std::vec::Vec<xdr::ScVal> = args
+        .iter()
+        .map(|arg| xdr::ScVal::try_from_val(env, &arg).unwrap())
+        .collect();
+    xdr::SorobanAuthorizedInvocation {
+        function: xdr::SorobanAuthorizedFunction::ContractFn(xdr::InvokeContractArgs {
+            contract_address: xdr::ScAddress::from(contract),
+            function_name: xdr::ScSymbol(fn_name.try_into().unwrap()),
+            args: args.try_into().unwrap(),
+        }),
+        sub_invocations: xdr::VecM::default(),
+    }
+}
+
+/// Ledger after which the authorizations built by these tests expire
+fn signature_expiration_ledger(env: &Env) -> u32 {
+    env.ledger().sequence() + 100
+}
+
+/// The payload an account's `__check_auth` receives for `invocation`
+fn auth_payload(
+    env: &Env,
+    auth_nonce: i64,
+    invocation: &xdr::SorobanAuthorizedInvocation,
+) -> BytesN<32> {
+    let preimage =
+        xdr::HashIdPreimage::SorobanAuthorization(xdr::HashIdPreimageSorobanAuthorization {
+            network_id: env.ledger().network_id().to_array().into(),
+            nonce: auth_nonce,
+            signature_expiration_ledger: signature_expiration_ledger(env),
+            invocation: invocation.clone(),
+        });
+    let preimage = Bytes::from_slice(env, &preimage.to_xdr(xdr::Limits::none()).unwrap());
+    env.crypto().sha256(&preimage).into()
+}
+
+fn auth_entry(
+    env: &Env,
+    address: &Address,
+    auth_nonce: i64,
+    invocation: xdr::SorobanAuthorizedInvocation,
+    signatures: Vec<AccountSignature>,
+) -> xdr::SorobanAuthorizationEntry {
+    let signatures: Val = signatures.into_val(env);
+    xdr::SorobanAuthorizationEntry {
+        credentials: xdr::SorobanCredentials::Address(xdr::SorobanAddressCredentials {
+            address: xdr::ScAddress::from(address),
+            nonce: auth_nonce,
+            signature_expiration_ledger: signature_expiration_ledger(env),
+            signature: xdr::ScVal::try_from_val(env, &signatures).unwrap(),
+        }),
+        root_invocation: invocation,
+    }
+}
+
+/// Signs an invocation of the account's own `fn_name` on its behalf, ready
+/// for its `__check_auth`
+fn authorize_self(
+    setup: &Setup,
+    signing_key: &SigningKey,
+    fn_name: &str,
+    args: Vec<Val>,
+) -> xdr::SorobanAuthorizationEntry {
+    let env = &setup.env;
+    let account = &setup.client.address;
+    let invocation = invocation(env, account, fn_name, args);
+
+    // The host's replay protection nonce is separate from the account's own
+    let auth_nonce = OsRng.next_u64() as i64;
+    let payload = auth_payload(env, auth_nonce, &invocation);
+    let signature = sign_with_nonce(env, signing_key, &payload, setup.client.nonce());
+    auth_entry(env, account, auth_nonce, invocation, vec![env, signature])
+}
+
+#[test]
+fn test_self_managed_signers() {
+    let setup = setup(1);
+    let env = &setup.env;
+    let account = setup.client.address.clone();
+    let (signing_key, signer) = generate_signer(env);
+
+    // The account authorizes adding a signer with its existing key, so the
+    // change passes through its own `__check_auth`
+    let args: Vec<Val> = (
+        account.clone(),
+        signer.clone(),
+        1u32,
+        None::<u32>,
+        None::<String>,
+        None::<u32>,
+    )
+        .into_val(env);
+    env.set_auths(&[authorize_self(
+        &setup,
+        &setup.signing_key,
+        "add_signer",
+        args,
+    )]);
+    setup
+        .client
+        .add_signer(&account, &signer, &1, &None, &None, &None);
+    assert!(setup.client.is_signer(&signer));
+
+    // A signature from a key the account doesn't hold is refused
+    let (stranger, _) = generate_signer(env);
+    let args: Vec<Val> = (account.clone(), signer.clone()).into_val(env);
+    env.set_auths(&[authorize_self(
+        &setup,
+        &stranger,
+        "remove_signer",
+        args.clone(),
+    )]);
+    assert_eq!(
+        setup.client.try_remove_signer(&account, &signer),
+        Err(Err(InvokeError::Abort))
+    );
+
+    env.set_auths(&[authorize_self(&setup, &signing_key, "remove_signer", args)]);
+    setup.client.remove_signer(&account, &signer);
+    assert!(!setup.client.is_signer(&signer));
+}
+
+#[test]
+fn test_self_managed_signers_meet_threshold() {
+    let setup = setup(2);
+    let env = &setup.env;
+    let account = setup.client.address.clone();
+    add_signer(&setup);
+    let (_, signer) = generate_signer(env);
+
+    // One of two required signatures can't change the signer set
+    let args: Vec<Val> = (
+        account.clone(),
+        signer.clone(),
+        1u32,
+        None::<u32>,
+        None::<String>,
+        None::<u32>,
+    )
+        .into_val(env);
+    env.set_auths(&[authorize_self(
+        &setup,
+        &setup.signing_key,
+        "add_signer",
+        args,
+    )]);
+    assert_eq!(
+        setup
+            .client
+            .try_add_signer(&account, &signer, &1, &None, &None, &None),
+        Err(Err(InvokeError::Abort))
+    );
+    assert!(!setup.client.is_signer(&signer));
+}
+
+#[test]
+fn test_add_signer_rejects_other_callers() {
+    let setup = setup(1);
+    let env = &setup.env;
+    env.mock_all_auths();
+    let (_, signer) = generate_signer(env);
+
+    assert_eq!(
+        setup
+            .client
+            .try_add_signer(&Address::generate(env), &signer, &1, &None, &None, &None),
+        Err(Ok(AccountError::NotAuthorized))
+    );
+}
+
+#[test]
+fn test_ensure_signer() {
+    let setup = setup(1);
+    let env = &setup.env;
+    env.mock_all_auths();
+    let (_, signer) = generate_signer(env);
+
+    setup.client.ensure_signer(&signer);
+    assert_eq!(setup.client.signer_count(), 2);
+    assert_eq!(setup.client.signer_weight(&signer), Some(1));
+
+    // Re-running leaves the registration as it was, including its weight
+    setup
+        .client
+        .add_signer(&setup.admin, &signer, &3, &None, &None, &None);
+    assert_eq!(setup.client.try_ensure_signer(&signer), Ok(Ok(())));
+    assert!(env.events().all().is_empty());
+    assert_eq!(setup.client.signer_count(), 2);
+    assert_eq!(setup.client.signer_weight(&signer), Some(3));
+}
+
+#[test]
+fn test_replace_signers() {
+    let setup = setup(2);
+    let env = &setup.env;
+    env.mock_all_auths();
+    let second = add_signer(&setup);
+    let old = setup.client.list_signers();
+    let keys = [
+        generate_signer(env),
+        generate_signer(env),
+        generate_signer(env),
+    ];
+    let new_signers = Vec::from_iter(env, keys.iter().map(|(_, signer)| signer.clone()));
+    let (master_key, master) = generate_signer(env);
+    setup.client.set_master_signer(&Some(master));
+
+    setup.client.replace_signers(&new_signers, &2);
+    let replaced = SignersReplaced {
+        removed: old,
+        added: Vec::from_iter(env, new_signers.iter().map(SignerKey::Ed25519)),
+        threshold: 2,
+    };
+    assert_eq!(
+        env.events().all(),
+        vec![
+            env,
+            (
+                setup.client.address.clone(),
+                replaced.topics(env),
+                replaced.data(env)
+            )
+        ]
+    );
+    assert_eq!(setup.client.signer_count(), 3);
+    assert_eq!(setup.client.threshold(), 2);
+    assert_eq!(setup.client.master_signer(), None);
+    assert!(!setup
+        .client
+        .is_signer(&second.verifying_key().to_bytes().into_val(env)));
+
+    let payload = BytesN::random(env);
+    let signatures = vec![
+        env,
+        sign(env, &setup.signing_key, &payload),
+        sign(env, &second, &payload),
+    ];
+    assert_eq!(
+        check_auth(env, &setup.client, &payload, signatures),
+        Err(AccountError::UnknownSigner)
+    );
+    let signatures = vec![env, sign(env, &master_key, &payload)];
+    assert_eq!(
+        check_auth(env, &setup.client, &payload, signatures),
+        Err(AccountError::UnknownSigner)
+    );
+    let signatures = vec![
+        env,
+        sign(env, &keys[0].0, &payload),
+        sign(env, &keys[2].0, &payload),
+    ];
+    assert_eq!(check_auth(env, &setup.client, &payload, signatures), Ok(()));
+}
+
+#[test]
+fn test_replace_signers_validation() {
+    let setup = setup(1);
+    let env = &setup.env;
+    env.mock_all_auths();
+    let (_, first) = generate_signer(env);
+    let (_, revoked) = generate_signer(env);
+    setup.client.revoke_signer(&revoked);
+
+    for (new_signers, threshold, error) in [
+        (Vec::new(env), 1, AccountError::NoSigners),
+        (
+            vec![env, first.clone()],
+            0,
+            AccountError::ThresholdUnsatisfiable,
+        ),
+        (
+            vec![env, first.clone()],
+            2,
+            AccountError::ThresholdUnsatisfiable,
+        ),
+        (
+            vec![env, first.clone(), first.clone()],
+            1,
+            AccountError::DuplicateSigner,
+        ),
+        (
+            vec![env, first.clone(), revoked.clone()],
+            1,
+            AccountError::SignerRevoked,
+        ),
+        (
+            vec![env, first.clone(), BytesN::from_array(env, &[0; 32])],
+            1,
+            AccountError::InvalidPublicKey,
+        ),
+    ] {
+        assert_eq!(
+            setup.client.try_replace_signers(&new_signers, &threshold),
+            Err(Ok(error))
+        );
+    }
+    // The existing set is untouched
+    assert_eq!(setup.client.signer_count(), 1);
+    assert!(setup
+        .client
+        .is_signer(&setup.signing_key.verifying_key().to_bytes().into_val(env)));
+}
+
+#[test]
+#[should_panic(expected = "Error(Auth, InvalidAction)")]
+fn test_replace_signers_requires_admin() {
+    let setup = setup(1);
+    let env = &setup.env;
+    let (_, signer) = generate_signer(env);
+
+    setup.client.replace_signers(&vec![env, signer], &1);
+}
+
+#[test]
+fn test_count_limited_signer() {
+    let setup = setup(1);
+    let env = &setup.env;
+    env.mock_all_auths();
+    let (session_key, session) = generate_signer(env);
+    setup
+        .client
+        .add_signer(&setup.admin, &session, &1, &None, &None, &Some(2));
+    assert_eq!(setup.client.signer_uses(&session), Some(2));
+
+    let payload = BytesN::random(env);
+    for remaining in [1, 0] {
+        let signatures = vec![
+            env,
+            sign_with_nonce(env, &session_key, &payload, setup.client.nonce()),
+        ];
+        assert_eq!(check_auth(env, &setup.client, &payload, signatures), Ok(()));
+        assert_eq!(setup.client.signer_uses(&session), Some(remaining));
+    }
+
+    let signatures = vec![
+        env,
+        sign_with_nonce(env, &session_key, &payload, setup.client.nonce()),
+    ];
+    assert_eq!(
+        check_auth(env, &setup.client, &payload, signatures),
+        Err(AccountError::SignerExhausted)
+    );
+    assert_eq!(
+        setup.client.check_signatures(
+            &payload,
+            &vec![
+                env,
+                sign_with_nonce(env, &session_key, &payload, setup.client.nonce())
+            ]
+        ),
+        vec![env]
+    );
+
+    // Unlimited signers are unaffected, and re-adding lifts the limit
+    let owner = BytesN::from_array(env, &setup.signing_key.verifying_key().to_bytes());
+    assert_eq!(setup.client.signer_uses(&owner), None);
+    setup
+        .client
+        .add_signer(&setup.admin, &session, &1, &None, &None, &None);
+    let signatures = vec![
+        env,
+        sign_with_nonce(env, &session_key, &payload, setup.client.nonce()),
+    ];
+    assert_eq!(check_auth(env, &setup.client, &payload, signatures), Ok(()));
+}
+
+#[test]
+fn test_rotate_signer() {
+    let setup = setup(1);
+    let env = &setup.env;
+    let old = BytesN::from_array(env, &setup.signing_key.verifying_key().to_bytes());
+    let (new_key, new) = generate_signer(env);
+    env.mock_all_auths();
+
+    setup.client.rotate_signer(&setup.admin, &old, &new, &10);
+    let deadline = env.ledger().sequence() + 10;
+    assert_eq!(setup.client.signer_grace(&old), Some(deadline));
+    assert!(setup.client.is_signer(&new));
+
+    // Both keys work until the grace period ends
+    let payload = BytesN::random(env);
+    let signatures = vec![env, sign(env, &setup.signing_key, &payload)];
+    assert_eq!(check_auth(env, &setup.client, &payload, signatures), Ok(()));
+    env.ledger().set_sequence_number(deadline);
+    let signatures = vec![
+        env,
+        sign_with_nonce(env, &setup.signing_key, &payload, setup.client.nonce()),
+    ];
+    assert_eq!(check_auth(env, &setup.client, &payload, signatures), Ok(()));
+
+    env.ledger().set_sequence_number(deadline + 1);
+    let signatures = vec![
+        env,
+        sign_with_nonce(env, &setup.signing_key, &payload, setup.client.nonce()),
+    ];
+    assert_eq!(
+        check_auth(env, &setup.client, &payload, signatures),
+        Err(AccountError::UnknownSigner)
+    );
+    let signatures = vec![
+        env,
+        sign_with_nonce(env, &new_key, &payload, setup.client.nonce()),
+    ];
+    assert_eq!(check_auth(env, &setup.client, &payload, signatures), Ok(()));
+
+    // The old key no longer counts towards the reachable weight
+    assert_eq!(
+        setup.client.try_set_threshold(&2),
+        Err(Ok(AccountError::ThresholdUnsatisfiable))
+    );
+    assert_eq!(
+        setup
+            .client
+            .try_rotate_signer(&setup.admin, &old, &new, &10),
+        Err(Ok(AccountError::UnknownSigner))
+    );
+}
+
+#[test]
+fn test_swap_signer() {
+    let setup = setup(1);
+    let env = &setup.env;
+    let first = BytesN::from_array(env, &setup.signing_key.verifying_key().to_bytes());
+    let (_, old) = generate_signer(env);
+    let (new_key, new) = generate_signer(env);
+    let token = Address::generate(env);
+    let scope = vec![env, token.clone()];
+    let label = String::from_str(env, "old phone");
+    env.mock_all_auths();
+    setup.client.add_signer(
+        &setup.admin,
+        &old,
+        &2,
+        &Some(1_000),
+        &Some(label.clone()),
+        &Some(5),
+    );
+    setup
+        .client
+        .set_signer_scope(&SignerKey::Ed25519(old.clone()), &scope);
+    setup.client.set_spend_limit(&old, &token, &100, &10);
+    setup.client.set_co_signer(&first, &old);
+    let record = |signer: &BytesN<32>| {
+        env.as_contract(&setup.client.address, || {
+            env.storage()
+                .persistent()
+                .get::<_, SignerRecord>(&DataKey::PersistentSigner(SignerKey::Ed25519(
+                    signer.clone(),
+                )))
+        })
+    };
+    let old_record = record(&old).unwrap();
+
+    setup.client.swap_signer(&setup.admin, &old, &new);
+    let swapped = SignerSwapped {
+        old: SignerKey::Ed25519(old.clone()),
+        new: SignerKey::Ed25519(new.clone()),
+    };
+    assert_eq!(
+        env.events().all(),
+        vec![
+            env,
+            (
+                setup.client.address.clone(),
+                swapped.topics(env),
+                swapped.data(env)
+            )
+        ]
+    );
+
+    // Everything about the signer carries over to the new key
+    assert_eq!(record(&new), Some(old_record));
+    assert_eq!(setup.client.signer_weight(&new), Some(2));
+    assert_eq!(
+        setup.client.signer_scope(&SignerKey::Ed25519(new.clone())),
+        Some(scope)
+    );
+    assert_eq!(setup.client.signer_label(&new), Some(label));
+    assert_eq!(setup.client.signer_uses(&new), Some(5));
+    assert_eq!(setup.client.spend_limit(&new).unwrap().limit, 100);
+    assert_eq!(setup.client.co_signer(&first), Some(new.clone()));
+    assert_eq!(
+        setup.client.list_signers(),
+        vec![
+            env,
+            SignerKey::Ed25519(first),
+            SignerKey::Ed25519(new.clone())
+        ]
+    );
+
+    // And nothing is left under the old one
+    assert_eq!(record(&old), None);
+    assert!(!setup.client.is_signer(&old));
+    assert_eq!(setup.client.signer_label(&old), None);
+    assert_eq!(setup.client.signer_uses(&old), None);
+    assert_eq!(setup.client.spend_limit(&old), None);
+
+    let payload = BytesN::random(env);
+    let nonce = setup.client.nonce();
+    let signatures = vec![env, sign_with_nonce(env, &new_key, &payload, nonce)];
+    assert_eq!(
+        check_auth_with_context(
+            env,
+            &setup.client,
+            &payload,
+            signatures,
+            vec![env, transfer_context(env, &token, 10)],
+        ),
+        Ok(())
+    );
+
+    assert_eq!(
+        setup.client.try_swap_signer(&setup.admin, &old, &new),
+        Err(Ok(AccountError::UnknownSigner))
+    );
+    assert_eq!(
+        setup.client.try_swap_signer(&setup.admin, &new, &new),
+        Err(Ok(AccountError::DuplicateSigner))
+    );
+}
+
+#[test]
+fn test_revoke_signer() {
+    let setup = setup(1);
+    let env = &setup.env;
+    let account = setup.client.address.clone();
+    let revoked = add_signer(&setup);
+    let revoked = BytesN::from_array(env, &revoked.verifying_key().to_bytes());
+
+    setup.client.revoke_signer(&revoked);
+    assert!(!setup.client.is_signer(&revoked));
+    assert!(setup.client.is_revoked(&revoked));
+    assert_eq!(
+        setup
+            .client
+            .try_add_signer(&setup.admin, &revoked, &1, &None, &None, &None),
+        Err(Ok(AccountError::SignerRevoked))
+    );
+
+    // Keys that were never revoked are unaffected
+    let (_, signer) = generate_signer(env);
+    setup
+        .client
+        .add_signer(&setup.admin, &signer, &1, &None, &None, &None);
+    assert!(setup.client.is_signer(&signer));
+
+    // The admin alone can't lift a revocation
+    env.mock_auths(&[MockAuth {
+        address: &setup.admin,
+        invoke: &MockAuthInvoke {
+            contract: &account,
+            fn_name: "unrevoke_signer",
+            args: (revoked.clone(),).into_val(env),
+            sub_invokes: &[],
+        },
+    }]);
+    assert!(setup.client.try_unrevoke_signer(&revoked).is_err());
+
+    let args: Vec<Val> = (revoked.clone(),).into_val(env);
+    env.set_auths(&[authorize_self(
+        &setup,
+        &setup.signing_key,
+        "unrevoke_signer",
+        args,
+    )]);
+    setup.client.unrevoke_signer(&revoked);
+    assert!(!setup.client.is_revoked(&revoked));
+
+    env.mock_all_auths();
+    setup
+        .client
+        .add_signer(&setup.admin, &revoked, &1, &None, &None, &None);
+    assert!(setup.client.is_signer(&revoked));
+}
+
+/// Authorizes `parent`'s `fn_name` through its delegate `child`, whose
+/// `__check_auth` in turn verifies a signature from `child_key`
+fn authorize_delegated(
+    env: &Env,
+    parent: &Address,
+    child: &AccountClient,
+    child_key: &SigningKey,
+    fn_name: &str,
+    args: Vec<Val>,
+) -> std::vec::Vec<xdr::SorobanAuthorizationEntry> {
+    authorize_chain(env, parent, &[child], child_key, fn_name, args)
+}
+
+/// Authorizes `parent`'s `fn_name` through a chain of delegates, each the
+/// delegate of the one before, the last verifying a signature from `key`
+fn authorize_chain(
+    env: &Env,
+    parent: &Address,
+    delegates: &[&AccountClient],
+    key: &SigningKey,
+    fn_name: &str,
+    args: Vec<Val>,
+) -> std::vec::Vec<xdr::SorobanAuthorizationEntry> {
+    let parent_invocation = invocation(env, parent, fn_name, args);
+    let parent_nonce = OsRng.next_u64() as i64;
+    let mut payload = auth_payload(env, parent_nonce, &parent_invocation);
+    let mut entries = std::vec![auth_entry(
+        env,
+        parent,
+        parent_nonce,
+        parent_invocation,
+        vec![
+            env,
+            AccountSignature::Delegate(delegates[0].address.clone())
+        ],
+    )];
+
+    // Each delegate authorizes the check of the payload above it
+    let mut checked = parent.clone();
+    for (depth, delegate) in delegates.iter().enumerate() {
+        let message: Val = Bytes::from(payload).into_val(env);
+        let depth = (depth + 1) as u32;
+        let invocation = invocation(
+            env,
+            &checked,
+            "__check_auth",
+            vec![env, message, depth.into_val(env)],
+        );
+        let nonce = OsRng.next_u64() as i64;
+        payload = auth_payload(env, nonce, &invocation);
+        let signature = match delegates.get(depth as usize) {
+            Some(next) => AccountSignature::Delegate(next.address.clone()),
+            None => sign_with_nonce(env, key, &payload, delegate.nonce()),
+        };
+        entries.push(auth_entry(
+            env,
+            &delegate.address,
+            nonce,
+            invocation,
+            vec![env, signature],
+        ));
+        checked = delegate.address.clone();
+    }
+    entries
+}
+
+#[test]
+fn test_delegate_signer() {
+    let setup = setup(1);
+    let env = &setup.env;
+    let parent = setup.client.address.clone();
+    let (child_key, child_signer) = generate_signer(env);
+    let child = AccountClient::new(
+        env,
+        &env.register(
+            Account,
+            (
+                AdminMode::External(Address::generate(env)),
+                vec![env, child_signer],
+                1u32,
+                1u32,
+            ),
+        ),
+    );
+
+    env.mock_all_auths();
+    setup
+        .client
+        .add_delegate_signer(&setup.admin, &child.address, &1);
+    assert_eq!(
+        setup.client.list_signers_of_kind(&SignerKind::Delegate),
+        vec![env, SignerKey::Delegate(child.address.clone())]
+    );
+
+    // The child's own signer authorizes a change to the parent
+    let (_, signer) = generate_signer(env);
+    let args: Vec<Val> = (
+        parent.clone(),
+        signer.clone(),
+        1u32,
+        None::<u32>,
+        None::<String>,
+        None::<u32>,
+    )
+        .into_val(env);
+    env.set_auths(&authorize_delegated(
+        env,
+        &parent,
+        &child,
+        &child_key,
+        "add_signer",
+        args.clone(),
+    ));
+    setup
+        .client
+        .add_signer(&parent, &signer, &1, &None, &None, &None);
+    assert!(setup.client.is_signer(&signer));
+    assert_eq!(child.nonce(), 1);
+
+    // A key the child doesn't hold can't sign for it
+    let (stranger, _) = generate_signer(env);
+    env.set_auths(&authorize_delegated(
+        env,
+        &parent,
+        &child,
+        &stranger,
+        "add_signer",
+        args,
+    ));
+    assert_eq!(
+        setup
+            .client
+            .try_add_signer(&parent, &signer, &2, &None, &None, &None),
+        Err(Err(InvokeError::Abort))
+    );
+}
+
+/// An account with a single signer of its own
+fn delegate_account(env: &Env) -> (AccountClient<'_>, SigningKey) {
+    let (signing_key, signer) = generate_signer(env);
+    let account = AccountClient::new(
+        env,
+        &env.register(
+            Account,
+            (
+                AdminMode::External(Address::generate(env)),
+                vec![env, signer],
+                1u32,
+                1u32,
+            ),
+        ),
+    );
+    (account, signing_key)
+}
+
+#[test]
+fn test_delegation_chain() {
+    let setup = setup(1);
+    let env = &setup.env;
+    let parent = setup.client.address.clone();
+    let (middle, _) = delegate_account(env);
+    let (leaf, leaf_key) = delegate_account(env);
+    env.mock_all_auths();
+    setup
+        .client
+        .add_delegate_signer(&setup.admin, &middle.address, &1);
+    middle.add_delegate_signer(&middle.admin(), &leaf.address, &1);
+    assert_eq!(setup.client.max_delegation_depth(), 3);
+
+    // Two levels down the leaf's own key authorizes a change to the parent
+    let (_, signer) = generate_signer(env);
+    let args = |weight: u32| -> Vec<Val> {
+        (
+            parent.clone(),
+            signer.clone(),
+            weight,
+            None::<u32>,
+            None::<String>,
+            None::<u32>,
+        )
+            .into_val(env)
+    };
+    env.set_auths(&authorize_chain(
+        env,
+        &parent,
+        &[&middle, &leaf],
+        &leaf_key,
+        "add_signer",
+        args(1),
+    ));
+    setup
+        .client
+        .add_signer(&parent, &signer, &1, &None, &None, &None);
+    assert!(setup.client.is_signer(&signer));
+    assert_eq!((middle.nonce(), leaf.nonce()), (1, 1));
+
+    // The middle account refuses to delegate past its own limit
+    env.mock_all_auths();
+    middle.set_max_delegation_depth(&1);
+    env.set_auths(&authorize_chain(
+        env,
+        &parent,
+        &[&middle, &leaf],
+        &leaf_key,
+        "add_signer",
+        args(2),
+    ));
+    assert!(setup
+        .client
+        .try_add_signer(&parent, &signer, &2, &None, &None, &None)
+        .is_err());
+    assert_eq!(setup.client.signer_weight(&signer), Some(1));
+}
+
+#[test]
+fn test_delegation_too_deep() {
+    let setup = setup(1);
+    let env = &setup.env;
+    let (delegate, _) = delegate_account(env);
+    env.mock_all_auths();
+    setup
+        .client
+        .add_delegate_signer(&setup.admin, &delegate.address, &1);
+
+    // Checking auth for the account above, three levels down already
+    let payload = BytesN::random(env);
+    let above = |depth: u32| {
+        vec![
+            env,
+            Context::Contract(ContractContext {
+                contract: Address::generate(env),
+                fn_name: Symbol::new(env, "__check_auth"),
+                args: vec![env, payload.to_val(), depth.into_val(env)],
+            }),
+        ]
+    };
+    let signatures = vec![env, AccountSignature::Delegate(delegate.address.clone())];
+    assert_eq!(
+        check_auth_with_context(env, &setup.client, &payload, signatures.clone(), above(3)),
+        Err(AccountError::DelegationTooDeep)
+    );
+    setup.client.set_max_delegation_depth(&0);
+    assert_eq!(
+        check_auth_with_context(env, &setup.client, &payload, signatures, above(0)),
+        Err(AccountError::DelegationTooDeep)
+    );
+
+    // A delegate signature naming the account itself is refused outright
+    let signatures = vec![
+        env,
+        AccountSignature::Delegate(setup.client.address.clone()),
+    ];
+    assert_eq!(
+        check_auth(env, &setup.client, &payload, signatures),
+        Err(AccountError::SelfDelegation)
+    );
+}
+
+#[test]
+fn test_self_delegation() {
+    let setup = setup(1);
+    let env = &setup.env;
+    env.mock_all_auths();
+
+    assert_eq!(
+        setup
+            .client
+            .try_add_delegate_signer(&setup.admin, &setup.client.address, &1),
+        Err(Ok(AccountError::SelfDelegation))
+    );
+}
+
+#[test]
+fn test_remove_signer() {
+    let setup = setup(1);
+    let env = &setup.env;
+    add_signer(&setup);
+    let signer = BytesN::from_array(env, &setup.signing_key.verifying_key().to_bytes());
+    setup.client.remove_signer(&setup.admin, &signer);
+    let payload = BytesN::random(env);
+
+    let signatures = vec![env, sign(env, &setup.signing_key, &payload)];
+    assert_eq!(
+        check_auth(env, &setup.client, &payload, signatures),
+        Err(AccountError::UnknownSigner)
+    );
+}
+
+#[test]
+fn test_change_cooldown() {
+    let setup = setup(1);
+    let env = &setup.env;
+    env.mock_all_auths();
+    let (_, first) = generate_signer(env);
+    let (_, second) = generate_signer(env);
+    let at = |sequence| env.ledger().set_sequence_number(sequence);
+    assert_eq!(setup.client.change_cooldown(), 0);
+
+    // Without a cooldown, changes may follow each other immediately
+    setup
+        .client
+        .add_signer(&setup.admin, &first, &1, &None, &None, &None);
+    setup.client.set_threshold(&2);
+
+    at(1000);
+    setup.client.set_change_cooldown(&100);
+    assert_eq!(setup.client.change_cooldown(), 100);
+    assert_eq!(
+        setup
+            .client
+            .try_add_signer(&setup.admin, &second, &1, &None, &None, &None),
+        Err(Ok(AccountError::ChangeTooSoon))
+    );
+
+    at(1100);
+    setup
+        .client
+        .add_signer(&setup.admin, &second, &1, &None, &None, &None);
+    at(1199);
+    assert_eq!(
+        setup.client.try_remove_signer(&setup.admin, &second),
+        Err(Ok(AccountError::ChangeTooSoon))
+    );
+    assert_eq!(
+        setup.client.try_set_threshold(&1),
+        Err(Ok(AccountError::ChangeTooSoon))
+    );
+    assert_eq!(
+        setup.client.try_set_change_cooldown(&0),
+        Err(Ok(AccountError::ChangeTooSoon))
+    );
+    // Nor can the whole set be swapped, or a signer rotated, in one go
+    assert_eq!(
+        setup
+            .client
+            .try_replace_signers(&vec![env, second.clone()], &1),
+        Err(Ok(AccountError::ChangeTooSoon))
+    );
+    let (_, third) = generate_signer(env);
+    assert_eq!(
+        setup
+            .client
+            .try_rotate_signer(&setup.admin, &first, &third, &0),
+        Err(Ok(AccountError::ChangeTooSoon))
+    );
+    // Nor can a signer's limits be lifted, a master signer installed or the
+    // admin handed over
+    assert_eq!(
+        setup.client.try_clear_spend_limit(&first),
+        Err(Ok(AccountError::ChangeTooSoon))
+    );
+    assert_eq!(
+        setup.client.try_set_master_signer(&Some(third.clone())),
+        Err(Ok(AccountError::ChangeTooSoon))
+    );
+    assert_eq!(
+        setup.client.try_set_admin(&Address::generate(env)),
+        Err(Ok(AccountError::ChangeTooSoon))
+    );
+    assert_eq!(
+        setup.client.try_renounce_admin(),
+        Err(Ok(AccountError::ChangeTooSoon))
+    );
+
+    at(1200);
+    setup.client.remove_signer(&setup.admin, &second);
+    assert_eq!(setup.client.signer_count(), 2);
+    at(1300);
+    setup.client.set_change_cooldown(&0);
+    setup.client.set_threshold(&1);
+
+    // A recovery waits out its own delay only, so changes made meanwhile
+    // can't put it off
+    setup.client.set_change_cooldown(&100);
+    at(1400);
+    let recovery = Address::generate(env);
+    setup.client.set_recovery(&recovery, &MIN_RECOVERY_DELAY);
+    setup.client.initiate_recovery(&recovery, &third);
+    at(1400 + MIN_RECOVERY_DELAY);
+    setup
+        .client
+        .add_signer(&setup.admin, &second, &1, &None, &None, &None);
+    setup.client.execute_recovery();
+    assert!(setup.client.is_signer(&third));
+}
+
+#[test]
+fn test_remove_unknown_signer() {
+    let setup = setup(1);
+    let env = &setup.env;
+    env.mock_all_auths();
+    let (_, unknown) = generate_signer(env);
+
+    assert_eq!(
+        setup.client.try_remove_signer(&setup.admin, &unknown),
+        Err(Ok(AccountError::UnknownSigner))
+    );
+}
+
+#[test]
+fn test_remove_last_signer() {
+    let setup = setup(1);
+    let env = &setup.env;
+    env.mock_all_auths();
+    let signer = BytesN::from_array(env, &setup.signing_key.verifying_key().to_bytes());
+
+    assert_eq!(
+        setup.client.try_remove_signer(&setup.admin, &signer),
+        Err(Ok(AccountError::LastSigner))
+    );
+}
+
+#[test]
+fn test_last_auth() {
+    let setup = setup(1);
+    let env = &setup.env;
+    assert_eq!(setup.client.last_auth(), None);
+
+    env.ledger().set_timestamp(1_700_000_000);
+    let payload = BytesN::random(env);
+    let signatures = vec![env, sign(env, &setup.signing_key, &payload)];
+    assert_eq!(check_auth(env, &setup.client, &payload, signatures), Ok(()));
+    assert_eq!(setup.client.last_auth(), Some(1_700_000_000));
+
+    // A rejected authorization leaves it untouched
+    env.ledger().set_timestamp(1_700_000_600);
+    let signatures = vec![env, sign(env, &setup.signing_key, &payload)];
+    assert_eq!(
+        check_auth(env, &setup.client, &payload, signatures),
+        Err(AccountError::BadNonce)
+    );
+    assert_eq!(setup.client.last_auth(), Some(1_700_000_000));
+}
+
+#[test]
+fn test_rate_limit() {
+    let setup = setup(1);
+    let env = &setup.env;
+    env.mock_all_auths();
+    setup.client.set_rate_limit(&2, &100);
+    assert_eq!(
+        setup.client.rate_limit(),
+        Some(RateLimit {
+            max_ops: 2,
+            window_ledgers: 100,
+        })
+    );
+
+    let authorize = |nonce| {
+        let payload = BytesN::random(env);
+        let signatures = vec![
+            env,
+            sign_with_nonce(env, &setup.signing_key, &payload, nonce),
+        ];
+        check_auth(env, &setup.client, &payload, signatures)
+    };
+    assert_eq!(authorize(0), Ok(()));
+    assert_eq!(authorize(1), Ok(()));
+    assert_eq!(authorize(2), Err(AccountError::RateLimited));
+
+    env.ledger()
+        .with_mut(|ledger| ledger.sequence_number += 100);
+    assert_eq!(authorize(2), Ok(()));
+
+    setup.client.clear_rate_limit();
+    assert_eq!(setup.client.rate_limit(), None);
+    assert_eq!(authorize(3), Ok(()));
+    assert_eq!(authorize(4), Ok(()));
+}
+
+#[test]
+fn test_master_signer_bypasses_threshold() {
+    let setup = setup(2);
+    let env = &setup.env;
+    env.mock_all_auths();
+    add_signer(&setup);
+    let (master_key, master) = generate_signer(env);
+    setup.client.set_master_signer(&Some(master.clone()));
+    assert_eq!(setup.client.master_signer(), Some(master));
+    let payload = BytesN::random(env);
+
+    let signatures = vec![env, sign(env, &master_key, &payload)];
+    assert_eq!(check_auth(env, &setup.client, &payload, signatures), Ok(()));
+    assert_eq!(setup.client.nonce(), 1);
+
+    let payload = BytesN::random(env);
+    let signatures = vec![env, sign_with_nonce(env, &setup.signing_key, &payload, 1)];
+    assert_eq!(
+        check_auth(env, &setup.client, &payload, signatures),
+        Err(AccountError::ThresholdNotMet)
+    );
+
+    setup.client.set_master_signer(&None);
+    let signatures = vec![env, sign_with_nonce(env, &master_key, &payload, 1)];
+    assert_eq!(
+        check_auth(env, &setup.client, &payload, signatures),
+        Err(AccountError::UnknownSigner)
+    );
+}
+
+#[test]
+fn test_weighted_threshold_met() {
+    let setup = setup_weighted(2, 2);
+    let env = &setup.env;
+    add_weighted_signer(&setup, 1);
+    let payload = BytesN::random(env);
+
+    let signatures = vec![env, sign(env, &setup.signing_key, &payload)];
+    assert_eq!(check_auth(env, &setup.client, &payload, signatures), Ok(()));
+}
+
+#[test]
+fn test_weighted_threshold_not_met() {
+    let setup = setup_weighted(2, 3);
+    let env = &setup.env;
+    let phone = add_weighted_signer(&setup, 1);
+    let tablet = add_weighted_signer(&setup, 1);
+    let payload = BytesN::random(env);
+
+    let signatures = vec![
+        env,
+        sign(env, &phone, &payload),
+        sign(env, &tablet, &payload),
+    ];
+    assert_eq!(
+        check_auth(env, &setup.client, &payload, signatures),
+        Err(AccountError::ThresholdNotMet)
+    );
+
+    let signatures = vec![
+        env,
+        sign(env, &setup.signing_key, &payload),
+        sign(env, &phone, &payload),
+    ];
+    assert_eq!(check_auth(env, &setup.client, &payload, signatures), Ok(()));
+}
+
+#[test]
+fn test_signer_weight() {
+    let setup = setup_weighted(2, 2);
+    let env = &setup.env;
+    let signer = BytesN::from_array(env, &setup.signing_key.verifying_key().to_bytes());
+    let (_, unknown) = generate_signer(env);
+
+    assert_eq!(setup.client.signer_weight(&signer), Some(2));
+    assert_eq!(setup.client.signer_weight(&unknown), None);
+}
+
+#[test]
+fn test_list_signers_paged() {
+    let setup = setup(1);
+    let env = &setup.env;
+    env.mock_all_auths();
+    setup.client.set_max_signers(&40);
+    for _ in 1..37 {
+        add_signer(&setup);
+    }
+    let signers = setup.client.list_signers();
+    assert_eq!(setup.client.signer_count(), 37);
+
+    for limit in [1, 5, 16] {
+        let mut pages = Vec::new(env);
+        let mut start = 0;
+        while start < setup.client.signer_count() {
+            let page = setup.client.list_signers_paged(&start, &limit);
+            assert!(!page.is_empty() && page.len() <= limit);
+            pages.append(&page);
+            start += limit;
+        }
+        assert_eq!(pages, signers);
+    }
+
+    // Pages never exceed the cap, however large the limit
+    assert_eq!(
+        setup.client.list_signers_paged(&0, &u32::MAX),
+        signers.slice(..MAX_SIGNERS_PAGE)
+    );
+    assert_eq!(
+        setup.client.list_signers_paged(&30, &16),
+        signers.slice(30..)
+    );
+    assert!(setup.client.list_signers_paged(&37, &16).is_empty());
+    assert!(setup.client.list_signers_paged(&u32::MAX, &16).is_empty());
+}
+
+#[test]
+fn test_list_signers() {
+    let setup = setup(1);
+    let env = &setup.env;
+    let first = BytesN::from_array(env, &setup.signing_key.verifying_key().to_bytes());
+    let (_, second) = generate_signer(env);
+    let (_, third) = generate_signer(env);
+    let (_, passkey) = generate_passkey(env);
+    env.mock_all_auths();
+
+    setup
+        .client
+        .add_signer(&setup.admin, &second, &1, &None, &None, &None);
+    setup
+        .client
+        .add_signer(&setup.admin, &third, &1, &None, &None, &None);
+    setup.client.add_secp256r1_signer(&passkey, &1, &None);
+    setup.client.remove_signer(&setup.admin, &second);
+    setup
+        .client
+        .add_signer(&setup.admin, &third, &2, &None, &None, &None);
+
+    assert_eq!(
+        setup.client.list_signers(),
+        vec![
+            env,
+            SignerKey::Ed25519(first.clone()),
+            SignerKey::Ed25519(third),
+            SignerKey::Secp256r1(passkey.clone()),
+        ]
+    );
+
+    setup.client.remove_signer(&setup.admin, &first);
+    setup.client.remove_secp256r1_signer(&passkey);
+    assert_eq!(setup.client.list_signers().len(), 1);
+}
+
+#[test]
+fn test_constructor_events() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let (_, signer) = generate_signer(&env);
+    let contract_id = env.register(
+        Account,
+        (
+            AdminMode::External(admin.clone()),
+            vec![&env, signer.clone()],
+            1u32,
+            1u32,
+        ),
+    );
+
+    let admin_set = AdminSet { admin };
+    let signer_added = SignerAdded {
+        signer: SignerKey::Ed25519(signer),
+    };
+    assert_eq!(
+        env.events().all(),
+        vec![
+            &env,
+            (
+                contract_id.clone(),
+                admin_set.topics(&env),
+                admin_set.data(&env)
+            ),
+            (
+                contract_id,
+                signer_added.topics(&env),
+                signer_added.data(&env)
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_signer_events() {
+    let setup = setup(1);
+    let env = &setup.env;
+    env.mock_all_auths();
+    let (_, signer) = generate_signer(env);
+
+    setup
+        .client
+        .add_signer(&setup.admin, &signer, &1, &None, &None, &None);
+    let added = SignerAdded {
+        signer: SignerKey::Ed25519(signer.clone()),
+    };
+    assert_eq!(
+        env.events().all(),
+        vec![
+            env,
+            (
+                setup.client.address.clone(),
+                added.topics(env),
+                added.data(env)
+            )
+        ]
+    );
+
+    setup.client.remove_signer(&setup.admin, &signer);
+    let removed = SignerRemoved {
+        signer: SignerKey::Ed25519(signer),
+    };
+    assert_eq!(
+        env.events().all(),
+        vec![
+            env,
+            (
+                setup.client.address.clone(),
+                removed.topics(env),
+                removed.data(env)
+            )
+        ]
+    );
+}
+
+#[test]
+fn test_admin_rotation() {
+    let setup = setup(1);
+    let env = &setup.env;
+    let new_admin = Address::generate(env);
+
+    setup
+        .client
+        .mock_auths(&[MockAuth {
+            address: &setup.admin,
+            invoke: &MockAuthInvoke {
+                contract: &setup.client.address,
+                fn_name: "set_admin",
+                args: (new_admin.clone(),).into_val(env),
+                sub_invokes: &[],
+            },
+        }])
+        .set_admin(&new_admin);
+    let proposed = AdminProposed {
+        admin: new_admin.clone(),
+    };
+    assert_eq!(
+        env.events().all(),
+        vec![
+            env,
+            (
+                setup.client.address.clone(),
+                proposed.topics(env),
+                proposed.data(env)
+            )
+        ]
+    );
+
+    setup
+        .client
+        .mock_auths(&[MockAuth {
+            address: &new_admin,
+            invoke: &MockAuthInvoke {
+                contract: &setup.client.address,
+                fn_name: "accept_admin",
+                args: ().into_val(env),
+                sub_invokes: &[],
+            },
+        }])
+        .accept_admin();
+    let accepted = AdminSet {
+        admin: new_admin.clone(),
+    };
+    assert_eq!(
+        env.events().all(),
+        vec![
+            env,
+            (
+                setup.client.address.clone(),
+                accepted.topics(env),
+                accepted.data(env)
+            )
+        ]
+    );
+
+    // The previous admin no longer controls the account
+    let (_, signer) = generate_signer(env);
+    assert!(setup
+        .client
+        .mock_auths(&[MockAuth {
+            address: &setup.admin,
+            invoke: &MockAuthInvoke {
+                contract: &setup.client.address,
+                fn_name: "add_signer",
+                args: (signer.clone(), 1u32, None::<u32>, None::<String>).into_val(env),
+                sub_invokes: &[],
+            },
+        }])
+        .try_add_signer(&setup.admin, &signer, &1, &None, &None, &None)
+        .is_err());
+}
+
+#[test]
+fn test_set_admin_unauthorized() {
+    let setup = setup(1);
+    let env = &setup.env;
+    let attacker = Address::generate(env);
+
+    assert!(setup
+        .client
+        .mock_auths(&[MockAuth {
+            address: &attacker,
+            invoke: &MockAuthInvoke {
+                contract: &setup.client.address,
+                fn_name: "set_admin",
+                args: (attacker.clone(),).into_val(env),
+                sub_invokes: &[],
+            },
+        }])
+        .try_set_admin(&attacker)
+        .is_err());
+}
+
+#[test]
+fn test_accept_admin_without_pending() {
+    let setup = setup(1);
+    setup.env.mock_all_auths();
+
+    assert_eq!(
+        setup.client.try_accept_admin(),
+        Err(Ok(AccountError::NoPendingAdmin))
+    );
+}
+
+#[test]
+fn test_add_scoped_signer() {
+    let setup = setup(1);
+    let env = &setup.env;
+    env.mock_all_auths();
+    let dapp = Address::generate(env);
+    let other = Address::generate(env);
+    let (session_key, signer) = generate_signer(env);
+    setup.client.add_scoped_signer(&signer, &dapp);
+    assert_eq!(
+        setup
+            .client
+            .signer_scope(&SignerKey::Ed25519(signer.clone())),
+        Some(vec![env, dapp.clone()])
+    );
+    let payload = BytesN::random(env);
+
+    let signatures = vec![env, sign(env, &session_key, &payload)];
+    assert_eq!(
+        check_auth_with_context(
+            env,
+            &setup.client,
+            &payload,
+            signatures.clone(),
+            vec![env, contract_context(env, &other, "swap")],
+        ),
+        Err(AccountError::ContextNotAllowed)
+    );
+    assert_eq!(
+        check_auth_with_context(
+            env,
+            &setup.client,
+            &payload,
+            signatures,
+            vec![env, contract_context(env, &dapp, "swap")],
+        ),
+        Ok(())
+    );
+
+    assert_eq!(
+        setup.client.try_add_scoped_signer(&signer, &other),
+        Err(Ok(AccountError::DuplicateSigner))
+    );
+}
+
+#[test]
+fn test_explain_context() {
+    let setup = setup(1);
+    let env = &setup.env;
+    let permitted = Address::generate(env);
+    let other = Address::generate(env);
+    let signer = SignerKey::Ed25519(BytesN::from_array(
+        env,
+        &setup.signing_key.verifying_key().to_bytes(),
+    ));
+    let contexts = vec![
+        env,
+        contract_context(env, &permitted, "transfer"),
+        contract_context(env, &other, "transfer"),
+        contract_context(env, &permitted, "approve"),
+    ];
+    assert_eq!(
+        setup.client.explain_context(&signer, &contexts),
+        vec![env, true, true, true]
+    );
+
+    env.mock_all_auths();
+    setup
+        .client
+        .set_signer_scope(&signer, &vec![env, permitted.clone()]);
+    assert_eq!(
+        setup.client.explain_context(&signer, &contexts),
+        vec![env, true, false, true]
+    );
+
+    let (_, unknown) = generate_signer(env);
+    assert_eq!(
+        setup
+            .client
+            .explain_context(&SignerKey::Ed25519(unknown), &contexts),
+        vec![env, false, false, false]
+    );
+}
+
+#[test]
+fn test_fn_scoped_signer() {
+    let setup = setup(1);
+    let env = &setup.env;
+    let token = Address::generate(env);
+    let public_key = BytesN::from_array(env, &setup.signing_key.verifying_key().to_bytes());
+    let signer = SignerKey::Ed25519(public_key.clone());
+    env.mock_all_auths();
+    let functions = vec![env, (token.clone(), Symbol::new(env, "transfer"))];
+    setup.client.set_signer_fn_scope(&public_key, &functions);
+    assert_eq!(setup.client.signer_fn_scope(&public_key), Some(functions));
+    assert_eq!(
+        setup.client.explain_context(
+            &signer,
+            &vec![
+                env,
+                contract_context(env, &token, "transfer"),
+                contract_context(env, &token, "approve"),
+            ]
+        ),
+        vec![env, true, false]
+    );
+    let payload = BytesN::random(env);
+
+    assert_eq!(
+        check_auth_with_context(
+            env,
+            &setup.client,
+            &payload,
+            vec![env, sign(env, &setup.signing_key, &payload)],
+            vec![env, contract_context(env, &token, "approve")],
+        ),
+        Err(AccountError::ContextNotAllowed)
+    );
+    assert_eq!(
+        check_auth_with_context(
+            env,
+            &setup.client,
+            &payload,
+            vec![env, sign(env, &setup.signing_key, &payload)],
+            vec![env, contract_context(env, &token, "transfer")],
+        ),
+        Ok(())
+    );
+
+    setup.client.clear_signer_fn_scope(&public_key);
+    assert_eq!(setup.client.signer_fn_scope(&public_key), None);
+    assert_eq!(
+        check_auth_with_context(
+            env,
+            &setup.client,
+            &payload,
+            vec![env, sign_with_nonce(env, &setup.signing_key, &payload, 1)],
+            vec![env, contract_context(env, &token, "approve")],
+        ),
+        Ok(())
+    );
+
+    let (_, unknown) = generate_signer(env);
+    assert_eq!(
+        setup.client.try_set_signer_fn_scope(&unknown, &vec![env]),
+        Err(Ok(AccountError::UnknownSigner))
+    );
+}
+
+#[test]
+fn test_required_fns_signer() {
+    let setup = setup(1);
+    let env = &setup.env;
+    let token = Address::generate(env);
+    let public_key = BytesN::from_array(env, &setup.signing_key.verifying_key().to_bytes());
+    env.mock_all_auths();
+    let functions = vec![env, (token.clone(), Symbol::new(env, "transfer"))];
+    setup
+        .client
+        .set_signer_required_fns(&public_key, &functions);
+    assert_eq!(
+        setup.client.signer_required_fns(&public_key),
+        Some(functions)
+    );
+    let payload = BytesN::random(env);
+
+    for auth_context in [
+        vec![env],
+        vec![env, contract_context(env, &token, "approve")],
+        vec![
+            env,
+            contract_context(env, &Address::generate(env), "transfer"),
+        ],
+    ] {
+        assert_eq!(
+            check_auth_with_context(
+                env,
+                &setup.client,
+                &payload,
+                vec![env, sign(env, &setup.signing_key, &payload)],
+                auth_context,
+            ),
+            Err(AccountError::ContextMismatch)
+        );
+    }
+    // Other calls may be authorized alongside the required one
+    assert_eq!(
+        check_auth_with_context(
+            env,
+            &setup.client,
+            &payload,
+            vec![env, sign(env, &setup.signing_key, &payload)],
+            vec![
+                env,
+                contract_context(env, &token, "approve"),
+                contract_context(env, &token, "transfer"),
+            ],
+        ),
+        Ok(())
+    );
+
+    setup.client.clear_signer_required_fns(&public_key);
+    assert_eq!(setup.client.signer_required_fns(&public_key), None);
+    assert_eq!(
+        check_auth_with_context(
+            env,
+            &setup.client,
+            &payload,
+            vec![env, sign_with_nonce(env, &setup.signing_key, &payload, 1)],
+            vec![env, contract_context(env, &token, "approve")],
+        ),
+        Ok(())
+    );
+
+    let (_, unknown) = generate_signer(env);
+    assert_eq!(
+        setup
+            .client
+            .try_set_signer_required_fns(&unknown, &vec![env]),
+        Err(Ok(AccountError::UnknownSigner))
+    );
+}
+
+#[test]
+fn test_scoped_signer() {
+    let setup = setup(1);
+    let env = &setup.env;
+    let permitted = Address::generate(env);
+    let other = Address::generate(env);
+    let signer = SignerKey::Ed25519(BytesN::from_array(
+        env,
+        &setup.signing_key.verifying_key().to_bytes(),
+    ));
+    env.mock_all_auths();
+    setup
+        .client
+        .set_signer_scope(&signer, &vec![env, permitted.clone()]);
+    let payload = BytesN::random(env);
+
+    let signatures = vec![env, sign(env, &setup.signing_key, &payload)];
+    assert_eq!(
+        check_auth_with_context(
+            env,
+            &setup.client,
+            &payload,
+            signatures.clone(),
+            vec![env, contract_context(env, &permitted, "transfer")],
+        ),
+        Ok(())
+    );
+    assert_eq!(
+        check_auth_with_context(
+            env,
+            &setup.client,
+            &payload,
+            signatures.clone(),
+            vec![
+                env,
+                contract_context(env, &permitted, "transfer"),
+                contract_context(env, &other, "transfer"),
+            ],
+        ),
+        Err(AccountError::ContextNotAllowed)
+    );
+
+    setup.client.clear_signer_scope(&signer);
+    assert_eq!(
+        check_auth_with_context(
+            env,
+            &setup.client,
+            &payload,
+            vec![env, sign_with_nonce(env, &setup.signing_key, &payload, 1)],
+            vec![env, contract_context(env, &other, "transfer")],
+        ),
+        Ok(())
+    );
+}
+
+#[test]
+fn test_signer_expiry() {
+    let setup = setup(1);
+    let env = &setup.env;
+    env.mock_all_auths();
+    let (contractor, signer) = generate_signer(env);
+    let expiry = env.ledger().sequence() + 100;
+    setup
+        .client
+        .add_signer(&setup.admin, &signer, &1, &Some(expiry), &None, &None);
+    let payload = BytesN::random(env);
+
+    let signatures = vec![env, sign(env, &contractor, &payload)];
+    env.ledger().set_sequence_number(expiry);
+    assert_eq!(
+        check_auth(env, &setup.client, &payload, signatures.clone()),
+        Ok(())
+    );
+
+    env.ledger().set_sequence_number(expiry + 1);
+    assert_eq!(
+        check_auth(env, &setup.client, &payload, signatures),
+        Err(AccountError::SignerExpired)
+    );
+}
+
+#[test]
+fn test_nonce_replay() {
+    let setup = setup(1);
+    let env = &setup.env;
+    let payload = BytesN::random(env);
+
+    let signatures = vec![env, sign(env, &setup.signing_key, &payload)];
+    let AccountSignature::Ed25519(seen) = signatures.get(0).unwrap() else {
+        unreachable!()
+    };
+    assert_eq!(
+        check_auth(env, &setup.client, &payload, signatures.clone()),
+        Ok(())
+    );
+    assert_eq!(setup.client.nonce(), 1);
+
+    assert_eq!(
+        check_auth(env, &setup.client, &payload, signatures),
+        Err(AccountError::BadNonce)
+    );
+
+    let signatures = vec![env, sign_with_nonce(env, &setup.signing_key, &payload, 5)];
+    assert_eq!(
+        check_auth(env, &setup.client, &payload, signatures),
+        Err(AccountError::BadNonce)
+    );
+
+    // The signature seen above, resent with the next nonce, doesn't verify:
+    // it was signed over the old one
+    let replayed = vec![
+        env,
+        AccountSignature::Ed25519(Signature { nonce: 1, ..seen }),
+    ];
+    let result = env.try_invoke_contract_check_auth::<AccountError>(
+        &setup.client.address,
+        &payload,
+        replayed.into_val(env),
+        &vec![env],
+    );
+    assert!(result.is_err());
+    assert_eq!(setup.client.nonce(), 1);
+
+    let signatures = vec![env, sign_with_nonce(env, &setup.signing_key, &payload, 1)];
+    assert_eq!(check_auth(env, &setup.client, &payload, signatures), Ok(()));
+}
+
+#[test]
+fn test_max_signatures() {
+    let setup = setup(1);
+    let env = &setup.env;
+    let second = add_signer(&setup);
+    let third = add_signer(&setup);
+    setup.client.set_max_signatures(&2);
+    let payload = BytesN::random(env);
+
+    let signatures = vec![
+        env,
+        sign(env, &setup.signing_key, &payload),
+        sign(env, &second, &payload),
+    ];
+    assert_eq!(check_auth(env, &setup.client, &payload, signatures), Ok(()));
+
+    let signatures = vec![
+        env,
+        sign_with_nonce(env, &setup.signing_key, &payload, 1),
+        sign_with_nonce(env, &second, &payload, 1),
+        sign_with_nonce(env, &third, &payload, 1),
+    ];
+    assert_eq!(
+        check_auth(env, &setup.client, &payload, signatures),
+        Err(AccountError::TooManySignatures)
+    );
+}
+
+#[test]
+fn test_max_signatures_defaults_to_signer_count() {
+    let setup = setup(1);
+    assert_eq!(setup.client.max_signatures(), 1);
+
+    add_signer(&setup);
+    assert_eq!(setup.client.max_signatures(), 2);
+}
+
+#[test]
+fn test_no_signatures() {
+    let setup = setup(1);
+    let env = &setup.env;
+    let payload = BytesN::random(env);
+
+    assert_eq!(
+        check_auth(env, &setup.client, &payload, vec![env]),
+        Err(AccountError::NoSignatures)
+    );
+}
+
+#[test]
+fn test_migrate() {
+    let setup = setup(1);
+    let env = &setup.env;
+    env.mock_all_auths();
+    assert_eq!(setup.client.storage_version(), 6);
+
+    // Simulate an account created before storage was versioned
+    env.as_contract(&setup.client.address, || {
+        env.storage().instance().remove(&DataKey::Version);
+        env.storage().instance().remove(&DataKey::Threshold);
+    });
+    assert_eq!(setup.client.storage_version(), 0);
+
+    setup.client.migrate();
+    assert_eq!(setup.client.storage_version(), 6);
+    assert_eq!(setup.client.threshold(), 1);
+}
+
+#[test]
+fn test_reinitialize() {
+    let setup = setup(1);
+    let env = &setup.env;
+    let (_, signer) = generate_signer(env);
+
+    let result = env.as_contract(&setup.client.address, || {
+        Account::__constructor(
+            env.clone(),
+            AdminMode::External(Address::generate(env)),
+            vec![env, signer.clone()],
+            1,
+            1,
+        )
+    });
+    assert_eq!(result, Err(AccountError::AlreadyInitialized));
+    assert_eq!(setup.client.admin(), setup.admin);
+    assert!(!setup.client.is_signer(&signer));
+}
+
+#[test]
+fn test_migrate_initialized() {
+    let setup = setup(1);
+    let env = &setup.env;
+    env.mock_all_auths();
+
+    // Accounts constructed by version 4 were never marked
+    env.as_contract(&setup.client.address, || {
+        env.storage().instance().set(&DataKey::Version, &4u32);
+        env.storage().instance().remove(&DataKey::Initialized);
+    });
+
+    setup.client.migrate();
+    assert!(env.as_contract(&setup.client.address, || {
+        env.storage().instance().has(&DataKey::Initialized)
+    }));
+}
+
+#[test]
+fn test_migrate_pending_recovery() {
+    let setup = setup(1);
+    let env = &setup.env;
+    let (_, new_signer) = generate_signer(env);
+    env.mock_all_auths();
+
+    // Version 5 held at most one recovery, under its own key
+    let request = RecoveryRequest {
+        new_signer,
+        effective_ledger: env.ledger().sequence() + 100,
+    };
+    env.as_contract(&setup.client.address, || {
+        env.storage().instance().set(&DataKey::Version, &5u32);
+        env.storage()
+            .instance()
+            .set(&DataKey::PendingRecovery, &request);
+    });
+
+    setup.client.migrate();
+    assert_eq!(setup.client.pending_recoveries(), vec![env, request]);
+    assert!(!env.as_contract(&setup.client.address, || {
+        env.storage().instance().has(&DataKey::PendingRecovery)
+    }));
+}
+
+#[test]
+fn test_migrate_signer_records() {
+    let setup = setup(1);
+    let env = &setup.env;
+    env.mock_all_auths();
+    let signer = SignerKey::Ed25519(setup.signing_key.verifying_key().to_bytes().into_val(env));
+    let permitted = Address::generate(env);
+
+    // Lay the signer out as version 1 stored it, one key per field
+    env.as_contract(&setup.client.address, || {
+        let storage = env.storage().instance();
+        storage.set(&DataKey::Version, &1u32);
+        env.storage()
+            .persistent()
+            .remove(&DataKey::PersistentSigner(signer.clone()));
+        storage.set(&signer.data_key().unwrap(), &3u32);
+        storage.set(&DataKey::SignerExpiry(signer.clone()), &500u32);
+        storage.set(
+            &DataKey::SignerScope(signer.clone()),
+            &vec![env, permitted.clone()],
+        );
+    });
+
+    setup.client.migrate();
+    assert_eq!(setup.client.storage_version(), 6);
+    assert_eq!(
+        env.as_contract(&setup.client.address, || {
+            env.storage()
+                .persistent()
+                .get::<_, SignerRecord>(&DataKey::PersistentSigner(signer.clone()))
+        }),
+        Some(SignerRecord {
+            weight: 3,
+            expiry: Some(500),
+            scope: Some(vec![env, permitted]),
+            session: None,
+        })
+    );
+    env.as_contract(&setup.client.address, || {
+        assert!(!env
+            .storage()
+            .instance()
+            .has(&DataKey::SignerExpiry(signer.clone())));
+        assert!(!env
+            .storage()
+            .instance()
+            .has(&DataKey::SignerScope(signer.clone())));
+        assert!(!env.storage().instance().has(&signer.data_key().unwrap()));
+    });
+}
+
+#[test]
+fn test_migrate_signer_map() {
+    let setup = setup(1);
+    let env = &setup.env;
+    env.mock_all_auths();
+    let second = add_weighted_signer(&setup, 2);
+    let (_, passkey) = generate_passkey(env);
+    setup.client.add_secp256r1_signer(&passkey, &1, &Some(500));
+    let payload = BytesN::random(env);
+    let signatures = vec![
+        env,
+        sign(env, &setup.signing_key, &payload),
+        sign(env, &second, &payload),
+    ];
+    let expected = setup.client.check_signatures(&payload, &signatures);
+
+    // Lay the signers out as version 2 stored them, one record per key
+    env.as_contract(&setup.client.address, || {
+        let storage = env.storage().instance();
+        let signers: Vec<SignerKey> = storage.get(&DataKey::SignerList).unwrap();
+        for signer in signers.iter() {
+            let key = DataKey::PersistentSigner(signer.clone());
+            let record: SignerRecord = env.storage().persistent().get(&key).unwrap();
+            storage.set(&signer.data_key().unwrap(), &record);
+            env.storage().persistent().remove(&key);
+        }
+        storage.set(&DataKey::Version, &2u32);
+    });
+    assert_eq!(setup.client.signer_count(), 3);
+    assert_eq!(
+        setup
+            .client
+            .signer_weight(&second.verifying_key().to_bytes().into_val(env)),
+        None
+    );
+
+    setup.client.migrate();
+    assert_eq!(setup.client.storage_version(), 6);
+    assert_eq!(
+        setup
+            .client
+            .signer_weight(&second.verifying_key().to_bytes().into_val(env)),
+        Some(2)
+    );
+    assert_eq!(
+        setup.client.check_signatures(&payload, &signatures),
+        expected
+    );
+    assert_eq!(expected.len(), 2);
+    env.as_contract(&setup.client.address, || {
+        assert!(!env
+            .storage()
+            .instance()
+            .has(&SignerKey::Secp256r1(passkey.clone()).data_key().unwrap()));
+    });
+}
+
+#[test]
+fn test_migrate_persistent_signers() {
+    let setup = setup(1);
+    let env = &setup.env;
+    env.mock_all_auths();
+    let second = add_weighted_signer(&setup, 2);
+    let second = SignerKey::Ed25519(second.verifying_key().to_bytes().into_val(env));
+
+    // Lay the signers out as version 3 stored them, in one instance map
+    env.as_contract(&setup.client.address, || {
+        let storage = env.storage().instance();
+        let signers: Vec<SignerKey> = storage.get(&DataKey::SignerList).unwrap();
+        let mut map: Map<SignerKey, SignerRecord> = Map::new(env);
+        for signer in signers.iter() {
+            let key = DataKey::PersistentSigner(signer.clone());
+            map.set(signer, env.storage().persistent().get(&key).unwrap());
+            env.storage().persistent().remove(&key);
+        }
+        storage.set(&DataKey::Signers, &map);
+        storage.set(&DataKey::Version, &3u32);
+    });
+    assert_eq!(setup.client.signer_weight(&signer_bytes(&second)), None);
+
+    setup.client.migrate();
+    assert_eq!(setup.client.storage_version(), 6);
+    assert_eq!(setup.client.signer_weight(&signer_bytes(&second)), Some(2));
+    env.as_contract(&setup.client.address, || {
+        assert!(!env.storage().instance().has(&DataKey::Signers));
+        assert_eq!(
+            env.storage()
+                .persistent()
+                .get_ttl(&DataKey::PersistentSigner(second.clone())),
+            SIGNER_BUMP_AMOUNT
+        );
+    });
+}
+
+fn signer_bytes(signer: &SignerKey) -> BytesN<32> {
+    let SignerKey::Ed25519(public_key) = signer else {
+        panic!("not an ed25519 signer");
+    };
+    public_key.clone()
+}
+
+#[test]
+fn test_persistent_signers() {
+    let setup = setup(1);
+    let env = &setup.env;
+    let signing_key = add_weighted_signer(&setup, 2);
+    let signer = SignerKey::Ed25519(signing_key.verifying_key().to_bytes().into_val(env));
+    let key = DataKey::PersistentSigner(signer.clone());
+
+    env.as_contract(&setup.client.address, || {
+        assert_eq!(
+            env.storage().persistent().get::<_, SignerRecord>(&key),
+            Some(SignerRecord {
+                weight: 2,
+                expiry: None,
+                scope: None,
+                session: None,
+            })
+        );
+        assert!(!env.storage().instance().has(&DataKey::Signers));
+    });
+
+    // Letting the signer's TTL run down, then authorizing with it, extends it
+    // without touching other signers
+    let owner = DataKey::PersistentSigner(SignerKey::Ed25519(
+        setup.signing_key.verifying_key().to_bytes().into_val(env),
+    ));
+    env.ledger()
+        .set_sequence_number(env.ledger().sequence() + 2 * 17280);
+    let ttl = |key: &DataKey| {
+        env.as_contract(&setup.client.address, || {
+            env.storage().persistent().get_ttl(key)
+        })
+    };
+    assert!(ttl(&key) < SIGNER_BUMP_AMOUNT - 17280);
+    let owner_ttl = ttl(&owner);
+
+    let payload = BytesN::random(env);
+    let signatures = vec![env, sign(env, &signing_key, &payload)];
+    assert_eq!(check_auth(env, &setup.client, &payload, signatures), Ok(()));
+    assert_eq!(ttl(&key), SIGNER_BUMP_AMOUNT);
+    assert_eq!(ttl(&owner), owner_ttl);
+
+    setup
+        .client
+        .remove_signer(&setup.admin, &signer_bytes(&signer));
+    env.as_contract(&setup.client.address, || {
+        assert!(!env.storage().persistent().has(&key));
+    });
+}
+
+#[test]
+fn test_shared_message_verify_cost() {
+    let setup = setup(1);
+    let env = &setup.env;
+    let payload: BytesN<32> = BytesN::random(env);
+    let signatures: std::vec::Vec<(BytesN<32>, BytesN<64>)> = (0..3)
+        .map(|_| {
+            let (signing_key, public_key) = generate_signer(env);
+            let signature = signing_key.sign(&payload.to_array()).to_bytes();
+            (public_key, BytesN::from_array(env, &signature))
+        })
+        .collect();
+
+    let cpu_cost = |verify: &dyn Fn()| {
+        env.as_contract(&setup.client.address, || {
+            env.cost_estimate().budget().reset_default();
+            verify();
+            env.cost_estimate().budget().cpu_instruction_cost()
+        })
+    };
+
+    // Converting the payload for every signature, as verification used to
+    let naive = cpu_cost(&|| {
+        for (public_key, signature) in &signatures {
+            let message: Bytes = payload.clone().into();
+            env.crypto().ed25519_verify(public_key, &message, signature);
+        }
+    });
+    let shared = cpu_cost(&|| {
+        let message: Bytes = payload.clone().into();
+        for (public_key, signature) in &signatures {
+            env.crypto().ed25519_verify(public_key, &message, signature);
+        }
+    });
+    // A `BytesN` converts to `Bytes` without any host work, and the SDK has
+    // no batched ed25519 verification, so the three host verifications are
+    // the whole cost either way
+    assert!(shared <= naive);
+}
+
+#[test]
+fn test_upgrade_without_admin() {
+    let setup = setup(1);
+    let env = &setup.env;
+    env.mock_all_auths();
+    env.as_contract(&setup.client.address, || {
+        env.storage().instance().remove(&DataKey::Admin);
+    });
+
+    assert_eq!(
+        setup
+            .client
+            .try_upgrade(&BytesN::from_array(env, &[0; 32]), &None),
+        Err(Ok(AccountError::NoAdmin))
+    );
+}
+
+#[test]
+fn test_upgrade_version_mismatch() {
+    let setup = setup(1);
+    let env = &setup.env;
+    env.mock_all_auths();
+
+    assert_eq!(
+        setup
+            .client
+            .try_upgrade(&BytesN::from_array(env, &[0; 32]), &Some(0)),
+        Err(Ok(AccountError::VersionMismatch))
+    );
+}
+
+#[test]
+fn test_close() {
+    let setup = setup(1);
+    let env = &setup.env;
+    env.mock_all_auths();
+    let second = add_signer(&setup);
+    let signer = SignerKey::Ed25519(second.verifying_key().to_bytes().into_val(env));
+
+    setup.client.close();
+    let closed = Closed {
+        admin: setup.admin.clone(),
+    };
+    assert_eq!(
+        env.events().all().slice(..1),
+        vec![
+            env,
+            (
+                setup.client.address.clone(),
+                closed.topics(env),
+                closed.data(env)
+            )
+        ]
+    );
+
+    env.as_contract(&setup.client.address, || {
+        let storage = env.storage().instance();
+        assert!(!storage.has(&DataKey::Admin));
+        assert!(!storage.has(&DataKey::Threshold));
+        assert!(!storage.has(&DataKey::SignerList));
+        assert!(!env
+            .storage()
+            .persistent()
+            .has(&DataKey::PersistentSigner(signer)));
+    });
+    assert_eq!(setup.client.signer_count(), 0);
+
+    // Authorizing fails cleanly rather than trapping on the missing state
+    let payload = BytesN::random(env);
+    let signatures = vec![env, sign(env, &second, &payload)];
+    assert_eq!(
+        check_auth(env, &setup.client, &payload, signatures),
+        Err(AccountError::AccountClosed)
+    );
+}
+
+#[test]
+fn test_renounce_admin() {
+    let setup = setup(1);
+    let env = &setup.env;
+    env.mock_all_auths();
+
+    setup.client.renounce_admin();
+    assert_eq!(env.auths()[0].0, setup.admin);
+
+    let no_admin = soroban_sdk::Error::from_contract_error(AccountError::NoAdmin as u32);
+    assert_eq!(setup.client.try_admin(), Err(Ok(no_admin)));
+    assert_eq!(
+        setup.client.try_set_threshold(&1),
+        Err(Ok(AccountError::NoAdmin))
+    );
+    assert_eq!(
+        setup.client.try_set_admin(&Address::generate(env)),
+        Err(Ok(AccountError::NoAdmin))
+    );
+    assert_eq!(
+        setup
+            .client
+            .try_upgrade(&BytesN::from_array(env, &[0; 32]), &None),
+        Err(Ok(AccountError::NoAdmin))
+    );
+
+    let payload = BytesN::random(env);
+    let signatures = vec![env, sign(env, &setup.signing_key, &payload)];
+    assert_eq!(check_auth(env, &setup.client, &payload, signatures), Ok(()));
+}
+
+#[test]
+fn test_admin_mode_external() {
+    let setup = setup(1);
+    let env = &setup.env;
+    assert_eq!(
+        setup.client.admin_mode(),
+        AdminMode::External(setup.admin.clone())
+    );
+
+    env.mock_all_auths();
+    let (_, signer) = generate_signer(env);
+    setup
+        .client
+        .add_signer(&setup.admin, &signer, &1, &None, &None, &None);
+    assert_eq!(env.auths()[0].0, setup.admin);
+    assert_eq!(setup.client.signer_weight(&signer), Some(1));
+
+    // The account's own authorization doesn't stand in for the admin's
+    let account = setup.client.address.clone();
+    assert!(setup
+        .client
+        .mock_auths(&[MockAuth {
+            address: &account,
+            invoke: &MockAuthInvoke {
+                contract: &account,
+                fn_name: "set_threshold",
+                args: (2u32,).into_val(env),
+                sub_invokes: &[],
+            },
+        }])
+        .try_set_threshold(&2)
+        .is_err());
+}
+
+#[test]
+fn test_admin_mode_self_auth() {
+    let env = Env::default();
+    let (_, signer) = generate_signer(&env);
+    let contract_id = env.register(
+        Account,
+        (AdminMode::SelfAuth, vec![&env, signer], 1u32, 1u32),
+    );
+    let client = AccountClient::new(&env, &contract_id);
+    assert_eq!(client.admin_mode(), AdminMode::SelfAuth);
+    assert_eq!(client.admin(), contract_id);
+
+    env.mock_all_auths();
+    let (_, second) = generate_signer(&env);
+    client.add_signer(&contract_id, &second, &1, &None, &None, &None);
+    assert_eq!(env.auths()[0].0, contract_id);
+    assert_eq!(client.signer_weight(&second), Some(1));
+
+    // Admin-only functions answer to the account's threshold too
+    client.set_threshold(&2);
+    assert_eq!(env.auths()[0].0, contract_id);
+
+    // No outside address can act as the admin
+    let outsider = Address::generate(&env);
+    let (_, third) = generate_signer(&env);
+    assert_eq!(
+        client.try_add_signer(&outsider, &third, &1, &None, &None, &None),
+        Err(Ok(AccountError::NotAuthorized))
+    );
+
+    // Accepting a proposed admin moves the account to `External`
+    let new_admin = Address::generate(&env);
+    client.set_admin(&new_admin);
+    assert_eq!(env.auths()[0].0, contract_id);
+    client.accept_admin();
+    assert_eq!(client.admin_mode(), AdminMode::External(new_admin));
+}
+
+#[test]
+fn test_self_auth_unfreeze() {
+    let env = Env::default();
+    let (signing_key, signer) = generate_signer(&env);
+    let contract_id = env.register(
+        Account,
+        (AdminMode::SelfAuth, vec![&env, signer], 1u32, 1u32),
+    );
+    let setup = Setup {
+        admin: contract_id.clone(),
+        client: AccountClient::new(&env, &contract_id),
+        env,
+        signing_key,
+    };
+    let env = &setup.env;
+    let account = setup.client.address.clone();
+
+    // Every authorization goes through the account's own `__check_auth`
+    let args: Vec<Val> = (account.clone(),).into_val(env);
+    env.set_auths(&[authorize_self(&setup, &setup.signing_key, "freeze", args)]);
+    setup.client.freeze(&account);
+    assert!(setup.client.is_frozen());
+
+    // Frozen, it authorizes nothing else, not even as its own admin
+    env.set_auths(&[authorize_self(
+        &setup,
+        &setup.signing_key,
+        "set_threshold",
+        (1u32,).into_val(env),
+    )]);
+    assert_eq!(
+        setup.client.try_set_threshold(&1),
+        Err(Err(InvokeError::Abort))
+    );
+
+    // but its own unfreeze still needs its threshold
+    let (stranger, _) = generate_signer(env);
+    env.set_auths(&[authorize_self(&setup, &stranger, "unfreeze", vec![env])]);
+    assert!(setup.client.try_unfreeze().is_err());
+    assert!(setup.client.is_frozen());
+
+    env.set_auths(&[authorize_self(
+        &setup,
+        &setup.signing_key,
+        "unfreeze",
+        vec![env],
+    )]);
+    setup.client.unfreeze();
+    assert!(!setup.client.is_frozen());
+
+    env.set_auths(&[authorize_self(
+        &setup,
+        &setup.signing_key,
+        "set_threshold",
+        (1u32,).into_val(env),
+    )]);
+    setup.client.set_threshold(&1);
+}
+
+#[test]
+fn test_check_auth_extends_ttl() {
+    let setup = setup(1);
+    let env = &setup.env;
+    let ttl = || env.as_contract(&setup.client.address, || env.storage().instance().get_ttl());
+    let initial_ttl = ttl();
+    assert!(initial_ttl < INSTANCE_BUMP_AMOUNT);
+
+    let payload = BytesN::random(env);
+    let signatures = vec![env, sign(env, &setup.signing_key, &payload)];
+    assert_eq!(check_auth(env, &setup.client, &payload, signatures), Ok(()));
+    assert_eq!(ttl(), INSTANCE_BUMP_AMOUNT);
+}
+
+#[test]
+fn test_duplicate_signer() {
+    let setup = setup(2);
+    let env = &setup.env;
+    add_signer(&setup);
+    let payload = BytesN::random(env);
+
+    // An exact repeat is dropped, so it can't make up the threshold
+    let signature = sign(env, &setup.signing_key, &payload);
+    let signatures = vec![env, signature.clone(), signature];
+    assert_eq!(
+        check_auth(env, &setup.client, &payload, signatures),
+        Err(AccountError::ThresholdNotMet)
+    );
+
+    // The same signer with differing signatures is refused
+    let signatures = vec![
+        env,
+        sign(env, &setup.signing_key, &payload),
+        sign_with_nonce(env, &setup.signing_key, &payload, 1),
+    ];
+    assert_eq!(
+        check_auth(env, &setup.client, &payload, signatures),
+        Err(AccountError::DuplicateSigner)
+    );
+}
+
+#[test]
+fn test_canonical_signature_order() {
+    let setup = setup(2);
+    let env = &setup.env;
+    let one = &setup.signing_key;
+    let two = &add_signer(&setup);
+    let three = &add_signer(&setup);
+    env.mock_all_auths();
+    setup.client.set_max_signatures(&8);
+
+    // Whether `signing_keys` signing in this order are accepted, and how many
+    // signatures that verifies
+    let verify = |signing_keys: &[&SigningKey]| {
+        let payload = BytesN::random(env);
+        let mut signatures = Vec::new(env);
+        for signing_key in signing_keys {
+            signatures.push_back(sign_with_nonce(
+                env,
+                signing_key,
+                &payload,
+                setup.client.nonce(),
+            ));
+        }
+        env.cost_estimate().budget().reset_default();
+        let result = check_auth(env, &setup.client, &payload, signatures);
+        let verifications = env
+            .cost_estimate()
+            .budget()
+            .tracker(ContractCostType::VerifyEd25519Sig)
+            .iterations;
+        (result, verifications)
+    };
+
+    let ordered = verify(&[one, two]);
+    assert_eq!(ordered, (Ok(()), 2));
+    assert_eq!(verify(&[two, one]), ordered);
+    assert_eq!(verify(&[one, two, one, two, two]), ordered);
+    assert_eq!(verify(&[three, one, two]), (Ok(()), 3));
+
+    // Padding one signer with repeats of itself still falls short
+    let alone = verify(&[three]);
+    assert_eq!(alone, (Err(AccountError::ThresholdNotMet), 1));
+    assert_eq!(verify(&[three, three, three]), alone);
+}
+
+#[test]
+fn test_constructor_registers_every_signer() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let signers = vec![
+        &env,
+        generate_signer(&env).1,
+        generate_signer(&env).1,
+        generate_signer(&env).1,
+    ];
+    let contract_id = env.register(
+        Account,
+        (AdminMode::External(admin), signers.clone(), 1u32, 2u32),
+    );
+    let client = AccountClient::new(&env, &contract_id);
+
+    for signer in signers.iter() {
+        assert_eq!(client.signer_weight(&signer), Some(1));
+    }
+    assert_eq!(client.list_signers().len(), 3);
+    assert_eq!(client.threshold(), 2);
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #13)")]
+fn test_constructor_rejects_empty_signers() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let signers: Vec<BytesN<32>> = vec![&env];
+    env.register(Account, (AdminMode::External(admin), signers, 1u32, 1u32));
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #29)")]
+fn test_constructor_rejects_zero_threshold() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let signers = vec![&env, generate_signer(&env).1];
+    env.register(Account, (AdminMode::External(admin), signers, 1u32, 0u32));
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #29)")]
+fn test_constructor_rejects_unsatisfiable_threshold() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let signers = vec![&env, generate_signer(&env).1, generate_signer(&env).1];
+    env.register(Account, (AdminMode::External(admin), signers, 2u32, 5u32));
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #35)")]
+fn test_constructor_rejects_zero_key() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let signers = vec![&env, BytesN::from_array(&env, &[0; 32])];
+    env.register(Account, (AdminMode::External(admin), signers, 1u32, 1u32));
+}
+
+#[test]
+fn test_invalid_public_key() {
+    let setup = setup(1);
+    let env = &setup.env;
+    env.mock_all_auths();
+
+    for weak in WEAK_ED25519_KEYS {
+        // Each is a point of small order
+        assert!(VerifyingKey::from_bytes(&weak).unwrap().is_weak());
+        let weak = BytesN::from_array(env, &weak);
+        assert_eq!(
+            setup
+                .client
+                .try_add_signer(&setup.admin, &weak, &1, &None, &None, &None),
+            Err(Ok(AccountError::InvalidPublicKey))
+        );
+        assert_eq!(
+            setup.client.try_ensure_signer(&weak),
+            Err(Ok(AccountError::InvalidPublicKey))
+        );
+    }
+
+    let (_, valid) = generate_signer(env);
+    setup
+        .client
+        .add_signer(&setup.admin, &valid, &1, &None, &None, &None);
+    assert!(setup.client.is_signer(&valid));
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #26)")]
+fn test_constructor_rejects_too_many_signers() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let signers = Vec::from_iter(&env, (0..21).map(|_| generate_signer(&env).1));
+    env.register(Account, (AdminMode::External(admin), signers, 1u32, 1u32));
+}
+
+#[test]
+fn test_max_signers() {
+    let setup = setup(1);
+    let env = &setup.env;
+    env.mock_all_auths();
+    assert_eq!(setup.client.max_signers(), 20);
+
+    setup.client.set_max_signers(&3);
+    add_signer(&setup);
+    add_signer(&setup);
+    assert_eq!(setup.client.signer_count(), 3);
+
+    let (_, signer) = generate_signer(env);
+    assert_eq!(
+        setup
+            .client
+            .try_add_signer(&setup.admin, &signer, &1, &None, &None, &None),
+        Err(Ok(AccountError::TooManySigners))
+    );
+    let (_, passkey) = generate_passkey(env);
+    assert_eq!(
+        setup.client.try_add_secp256r1_signer(&passkey, &1, &None),
+        Err(Ok(AccountError::TooManySigners))
+    );
+
+    // Updating a signer already registered doesn't count against the cap
+    let existing = BytesN::from_array(env, &setup.signing_key.verifying_key().to_bytes());
+    setup
+        .client
+        .add_signer(&setup.admin, &existing, &2, &None, &None, &None);
+    assert_eq!(setup.client.signer_weight(&existing), Some(2));
+
+    setup.client.set_max_signers(&4);
+    setup
+        .client
+        .add_signer(&setup.admin, &signer, &1, &None, &None, &None);
+    assert_eq!(setup.client.signer_count(), 4);
+}
+
+#[test]
+fn test_is_signer() {
+    let setup = setup(1);
+    let (_, unknown) = generate_signer(&setup.env);
+    let known = BytesN::from_array(&setup.env, &setup.signing_key.verifying_key().to_bytes());
+
+    assert!(setup.client.is_signer(&known));
+    assert!(!setup.client.is_signer(&unknown));
+}
+
+#[test]
+fn test_signer_count() {
+    let setup = setup(1);
+    assert_eq!(setup.client.signer_count(), 1);
+
+    let second = add_signer(&setup);
+    assert_eq!(setup.client.signer_count(), 2);
+
+    let second = BytesN::from_array(&setup.env, &second.verifying_key().to_bytes());
+    setup.client.remove_signer(&setup.admin, &second);
+    assert_eq!(setup.client.signer_count(), 1);
+}
+
+fn transfer_context(env: &Env, token: &Address, amount: i128) -> Context {
+    Context::Contract(ContractContext {
+        contract: token.clone(),
+        fn_name: Symbol::new(env, "transfer"),
+        args: vec![
+            env,
+            Address::generate(env).into_val(env),
+            Address::generate(env).into_val(env),
+            amount.into_val(env),
+        ],
+    })
+}
+
+#[test]
+fn test_decode_transfer() {
+    let env = Env::default();
+    let token = Address::generate(&env);
+    let from = Address::generate(&env);
+    let to = Address::generate(&env);
+
+    let transfer = |fn_name: &str, args: Vec<Val>| {
+        Context::Contract(ContractContext {
+            contract: token.clone(),
+            fn_name: Symbol::new(&env, fn_name),
+            args,
+        })
+    };
+    let args = vec![
+        &env,
+        from.into_val(&env),
+        to.into_val(&env),
+        100i128.into_val(&env),
+    ];
+    assert_eq!(
+        decode_transfer(&transfer("transfer", args.clone())),
+        Some((from.clone(), to.clone(), 100))
+    );
+
+    // Same arguments, different function
+    assert_eq!(decode_transfer(&transfer("approve", args)), None);
+    // Amount of the wrong type
+    let args = vec![
+        &env,
+        from.into_val(&env),
+        to.into_val(&env),
+        100u32.into_val(&env),
+    ];
+    assert_eq!(decode_transfer(&transfer("transfer", args)), None);
+    // Missing amount
+    let args = vec![&env, from.into_val(&env), to.into_val(&env)];
+    assert_eq!(decode_transfer(&transfer("transfer", args)), None);
+    assert_eq!(
+        decode_transfer(&contract_context(&env, &token, "transfer")),
+        None
+    );
+}
+
+fn spend(
+    setup: &Setup,
+    signing_key: &SigningKey,
+    token: &Address,
+    amount: i128,
+) -> Result<(), AccountError> {
+    let env = &setup.env;
+    let payload = BytesN::random(env);
+    let signatures = vec![
+        env,
+        sign_with_nonce(env, signing_key, &payload, setup.client.nonce()),
+    ];
+    check_auth_with_context(
+        env,
+        &setup.client,
+        &payload,
+        signatures,
+        vec![env, transfer_context(env, token, amount)],
+    )
+}
+
+#[test]
+fn test_spend_limit() {
+    let setup = setup(1);
+    let env = &setup.env;
+    let token = Address::generate(env);
+    let limited = add_signer(&setup);
+    let limited_key = BytesN::from_array(env, &limited.verifying_key().to_bytes());
+    setup
+        .client
+        .set_spend_limit(&limited_key, &token, &100, &100);
+
+    assert_eq!(spend(&setup, &limited, &token, 60), Ok(()));
+    assert_eq!(spend(&setup, &limited, &token, 40), Ok(()));
+    assert_eq!(
+        spend(&setup, &limited, &token, 1),
+        Err(AccountError::SpendLimitExceeded)
+    );
+
+    // Earlier transfers stop counting once they leave the window
+    env.ledger()
+        .set_sequence_number(env.ledger().sequence() + 100);
+    assert_eq!(spend(&setup, &limited, &token, 100), Ok(()));
+}
+
+#[test]
+fn test_spend_limit_restricts_context() {
+    let setup = setup(1);
+    let env = &setup.env;
+    let token = Address::generate(env);
+    let other_token = Address::generate(env);
+    let limited = add_signer(&setup);
+    let limited_key = BytesN::from_array(env, &limited.verifying_key().to_bytes());
+    setup
+        .client
+        .set_spend_limit(&limited_key, &token, &100, &100);
+
+    assert_eq!(
+        spend(&setup, &limited, &other_token, 1),
+        Err(AccountError::ContextNotAllowed)
+    );
+    // Full-power signers are unaffected
+    assert_eq!(spend(&setup, &setup.signing_key, &token, 1_000), Ok(()));
+}
+
+fn spend_together(
+    setup: &Setup,
+    signing_keys: &[&SigningKey],
+    token: &Address,
+    amount: i128,
+) -> Result<(), AccountError> {
+    let env = &setup.env;
+    let payload = BytesN::random(env);
+    let mut signatures = Vec::new(env);
+    for signing_key in signing_keys {
+        signatures.push_back(sign_with_nonce(
+            env,
+            signing_key,
+            &payload,
+            setup.client.nonce(),
+        ));
+    }
+    check_auth_with_context(
+        env,
+        &setup.client,
+        &payload,
+        signatures,
+        vec![env, transfer_context(env, token, amount)],
+    )
+}
+
+fn spent(setup: &Setup, signer: &BytesN<32>) -> i128 {
+    let env = &setup.env;
+    env.as_contract(&setup.client.address, || {
+        env.storage()
+            .instance()
+            .get::<_, Vec<SpendRecord>>(&DataKey::SpendHistory(signer.clone()))
+            .map_or(0, |history| {
+                history.iter().map(|record| record.amount).sum()
+            })
+    })
+}
+
+#[test]
+fn test_limited_signers_charged_once() {
+    let setup = setup(1);
+    let env = &setup.env;
+    let token = Address::generate(env);
+    let small = add_signer(&setup);
+    let small_key = BytesN::from_array(env, &small.verifying_key().to_bytes());
+    let large = add_signer(&setup);
+    let large_key = BytesN::from_array(env, &large.verifying_key().to_bytes());
+    setup.client.set_spend_limit(&small_key, &token, &100, &100);
+    setup.client.set_spend_limit(&large_key, &token, &300, &100);
+
+    // The signer with the most left pays, whatever order they sign in
+    assert_eq!(
+        spend_together(&setup, &[&small, &large], &token, 80),
+        Ok(())
+    );
+    assert_eq!(spent(&setup, &large_key), 80);
+    assert_eq!(spent(&setup, &small_key), 0);
+    assert_eq!(
+        spend_together(&setup, &[&large, &small], &token, 60),
+        Ok(())
+    );
+    assert_eq!(spent(&setup, &large_key), 140);
+    assert_eq!(spent(&setup, &small_key), 0);
+
+    // Each still has to fit its own limit
+    assert_eq!(
+        spend_together(&setup, &[&small, &large], &token, 101),
+        Err(AccountError::SpendLimitExceeded)
+    );
+
+    // An unlimited signer is charged first, leaving the limits untouched
+    let before = (spent(&setup, &small_key), spent(&setup, &large_key));
+    assert_eq!(
+        spend_together(&setup, &[&small, &setup.signing_key], &token, 40),
+        Ok(())
+    );
+    assert_eq!(
+        (spent(&setup, &small_key), spent(&setup, &large_key)),
+        before
+    );
+
+    // On a tie the signer whose key sorts first pays
+    let left = add_signer(&setup);
+    let left_key = BytesN::from_array(env, &left.verifying_key().to_bytes());
+    let right = add_signer(&setup);
+    let right_key = BytesN::from_array(env, &right.verifying_key().to_bytes());
+    setup.client.set_spend_limit(&left_key, &token, &100, &100);
+    setup.client.set_spend_limit(&right_key, &token, &100, &100);
+    assert_eq!(spend_together(&setup, &[&right, &left], &token, 10), Ok(()));
+    let (first, second) = if left_key < right_key {
+        (left_key, right_key)
+    } else {
+        (right_key, left_key)
+    };
+    assert_eq!(spent(&setup, &first), 10);
+    assert_eq!(spent(&setup, &second), 0);
+}
+
+#[test]
+fn test_count_limited_signers_charged_once() {
+    let setup = setup(1);
+    let env = &setup.env;
+    let token = Address::generate(env);
+    env.mock_all_auths();
+    let (fewer_key, fewer) = generate_signer(env);
+    let (more_key, more) = generate_signer(env);
+    setup
+        .client
+        .add_signer(&setup.admin, &fewer, &1, &None, &None, &Some(2));
+    setup
+        .client
+        .add_signer(&setup.admin, &more, &1, &None, &None, &Some(5));
+
+    assert_eq!(
+        spend_together(&setup, &[&fewer_key, &more_key], &token, 1),
+        Ok(())
+    );
+    assert_eq!(setup.client.signer_uses(&fewer), Some(2));
+    assert_eq!(setup.client.signer_uses(&more), Some(4));
+
+    assert_eq!(
+        spend_together(&setup, &[&fewer_key, &setup.signing_key], &token, 1),
+        Ok(())
+    );
+    assert_eq!(setup.client.signer_uses(&fewer), Some(2));
+}
+
+#[test]
+fn test_signer_status() {
+    let setup = setup(1);
+    let env = &setup.env;
+    let token = Address::generate(env);
+    env.mock_all_auths();
+    let owner = BytesN::from_array(env, &setup.signing_key.verifying_key().to_bytes());
+    let unlimited = SignerStatus {
+        remaining_uses: None,
+        remaining_spend: None,
+        expiry: None,
+    };
+    assert_eq!(setup.client.signer_status(&owner), unlimited);
+
+    let (session_key, session) = generate_signer(env);
+    let expiry = env.ledger().sequence() + 1_000;
+    setup
+        .client
+        .add_signer(&setup.admin, &session, &1, &Some(expiry), &None, &Some(5));
+    setup.client.set_spend_limit(&session, &token, &100, &100);
+    assert_eq!(
+        setup.client.signer_status(&session),
+        SignerStatus {
+            remaining_uses: Some(5),
+            remaining_spend: Some(100),
+            expiry: Some(expiry),
+        }
+    );
+
+    assert_eq!(spend(&setup, &session_key, &token, 30), Ok(()));
+    assert_eq!(
+        setup.client.signer_status(&session),
+        SignerStatus {
+            remaining_uses: Some(4),
+            remaining_spend: Some(70),
+            expiry: Some(expiry),
+        }
+    );
+
+    // The allowance comes back once the transfer leaves the window
+    env.ledger()
+        .set_sequence_number(env.ledger().sequence() + 100);
+    assert_eq!(
+        setup.client.signer_status(&session).remaining_spend,
+        Some(100)
+    );
+
+    let (_, stranger) = generate_signer(env);
+    assert_eq!(
+        setup.client.try_signer_status(&stranger),
+        Err(Ok(AccountError::UnknownSigner))
+    );
+}
+
+#[test]
+fn test_signer_tx_cap() {
+    let setup = setup(2);
+    let env = &setup.env;
+    let token = Address::generate(env);
+    // Heavy enough to meet the threshold alone
+    let capped = add_weighted_signer(&setup, 2);
+    let capped_key = BytesN::from_array(env, &capped.verifying_key().to_bytes());
+    setup.client.set_signer_tx_cap(&capped_key, &100);
+    assert_eq!(setup.client.signer_tx_cap(&capped_key), Some(100));
+
+    assert_eq!(spend(&setup, &capped, &token, 60), Ok(()));
+    assert_eq!(spend(&setup, &capped, &token, 100), Ok(()));
+    assert_eq!(
+        spend(&setup, &capped, &token, 101),
+        Err(AccountError::SpendLimitExceeded)
+    );
+
+    // The cap covers every transfer in the authorization together
+    let payload = BytesN::random(env);
+    let nonce = setup.client.nonce();
+    let transfers = vec![
+        env,
+        transfer_context(env, &token, 60),
+        transfer_context(env, &Address::generate(env), 60),
+    ];
+    assert_eq!(
+        check_auth_with_context(
+            env,
+            &setup.client,
+            &payload,
+            vec![env, sign_with_nonce(env, &capped, &payload, nonce)],
+            transfers.clone(),
+        ),
+        Err(AccountError::SpendLimitExceeded)
+    );
+
+    // An uncapped signer alongside lifts the cap
+    let signatures = vec![
+        env,
+        sign_with_nonce(env, &capped, &payload, nonce),
+        sign_with_nonce(env, &setup.signing_key, &payload, nonce),
+    ];
+    assert_eq!(
+        check_auth_with_context(env, &setup.client, &payload, signatures, transfers),
+        Ok(())
+    );
+
+    setup.client.clear_signer_tx_cap(&capped_key);
+    assert_eq!(setup.client.signer_tx_cap(&capped_key), None);
+    assert_eq!(spend(&setup, &capped, &token, 1_000), Ok(()));
+}
+
+#[test]
+fn test_recovery_after_delay() {
+    let setup = setup(1);
+    let env = &setup.env;
+    let recovery = Address::generate(env);
+    let old_signer = BytesN::from_array(env, &setup.signing_key.verifying_key().to_bytes());
+    let (_, new_signer) = generate_signer(env);
+    env.mock_all_auths();
+    setup.client.set_recovery(&recovery, &MIN_RECOVERY_DELAY);
+    let (_, master) = generate_signer(env);
+    setup.client.set_master_signer(&Some(master));
+
+    setup.client.initiate_recovery(&recovery, &new_signer);
+    assert_eq!(
+        setup.client.try_execute_recovery(),
+        Err(Ok(AccountError::RecoveryNotReady))
+    );
+
+    env.ledger()
+        .set_sequence_number(env.ledger().sequence() + MIN_RECOVERY_DELAY);
+    setup.client.execute_recovery();
+    assert!(!setup.client.is_signer(&old_signer));
+    assert!(setup.client.is_signer(&new_signer));
+    assert_eq!(setup.client.signer_count(), 1);
+    assert_eq!(setup.client.threshold(), 1);
+    assert_eq!(setup.client.pending_recovery(), None);
+    // The lost keys' master signer goes with them
+    assert_eq!(setup.client.master_signer(), None);
+}
+
+#[test]
+fn test_recovery_delay_bounds() {
+    let setup = setup(1);
+    let env = &setup.env;
+    let recovery = Address::generate(env);
+    env.mock_all_auths();
+
+    for delay in [0, MIN_RECOVERY_DELAY - 1, MAX_RECOVERY_DELAY + 1, u32::MAX] {
+        assert_eq!(
+            setup.client.try_set_recovery(&recovery, &delay),
+            Err(Ok(AccountError::InvalidRecoveryDelay))
+        );
+    }
+    assert!(!setup.client.policy().has_recovery);
+
+    for delay in [MIN_RECOVERY_DELAY, 7 * 17280, MAX_RECOVERY_DELAY] {
+        assert_eq!(setup.client.try_set_recovery(&recovery, &delay), Ok(Ok(())));
+    }
+    assert!(setup.client.policy().has_recovery);
+}
+
+#[test]
+fn test_recovery_group() {
+    let setup = setup(1);
+    let env = &setup.env;
+    let recovery = Address::generate(env);
+    let friends = vec![env, Address::generate(env), Address::generate(env)];
+    env.mock_all_auths();
+    setup.client.set_recovery(&recovery, &MIN_RECOVERY_DELAY);
+    setup.client.set_recovery_group(&friends);
+    assert_eq!(setup.client.recovery_group(), friends);
+
+    for friend in friends.iter() {
+        let (_, new_signer) = generate_signer(env);
+        setup.client.initiate_recovery(&friend, &new_signer);
+        assert_eq!(
+            env.auths(),
+            std::vec![(
+                friend.clone(),
+                AuthorizedInvocation {
+                    function: AuthorizedFunction::Contract((
+                        setup.client.address.clone(),
+                        Symbol::new(env, "initiate_recovery"),
+                        (friend.clone(), new_signer.clone()).into_val(env),
+                    )),
+                    sub_invocations: std::vec![],
+                }
+            )]
+        );
+        assert_eq!(
+            setup.client.pending_recoveries().last().unwrap().new_signer,
+            new_signer
+        );
+    }
+
+    let (_, new_signer) = generate_signer(env);
+    assert_eq!(
+        setup
+            .client
+            .try_initiate_recovery(&Address::generate(env), &new_signer),
+        Err(Ok(AccountError::NotAuthorized))
+    );
+}
+
+#[test]
+fn test_max_recovery_requests() {
+    let setup = setup(1);
+    let env = &setup.env;
+    let recovery = Address::generate(env);
+    env.mock_all_auths();
+    setup.client.set_recovery(&recovery, &MIN_RECOVERY_DELAY);
+    assert_eq!(setup.client.max_recovery_requests(), 4);
+    setup.client.set_max_recovery_requests(&2);
+    assert_eq!(setup.client.max_recovery_requests(), 2);
+
+    let (_, first) = generate_signer(env);
+    let (_, second) = generate_signer(env);
+    let (_, third) = generate_signer(env);
+    setup.client.initiate_recovery(&recovery, &first);
+    env.ledger()
+        .set_sequence_number(env.ledger().sequence() + 10);
+    setup.client.initiate_recovery(&recovery, &second);
+    assert_eq!(
+        setup.client.try_initiate_recovery(&recovery, &third),
+        Err(Ok(AccountError::TooManyRecoveryRequests))
+    );
+    assert_eq!(setup.client.pending_recoveries().len(), 2);
+    assert_eq!(setup.client.pending_recovery().unwrap().new_signer, first);
+
+    // Cancelling clears every slot
+    setup.client.cancel_recovery();
+    assert_eq!(setup.client.pending_recoveries().len(), 0);
+    setup.client.initiate_recovery(&recovery, &third);
+    assert_eq!(setup.client.pending_recovery().unwrap().new_signer, third);
+
+    // Executing applies the oldest and clears the rest
+    setup.client.initiate_recovery(&recovery, &first);
+    env.ledger()
+        .set_sequence_number(env.ledger().sequence() + MIN_RECOVERY_DELAY);
+    setup.client.execute_recovery();
+    assert!(setup.client.is_signer(&third));
+    assert!(!setup.client.is_signer(&first));
+    assert_eq!(setup.client.signer_count(), 1);
+    assert_eq!(setup.client.pending_recovery(), None);
+}
+
+#[test]
+fn test_cancel_recovery() {
+    let setup = setup(2);
+    let env = &setup.env;
+    add_signer(&setup);
+    let recovery = Address::generate(env);
+    let (_, new_signer) = generate_signer(env);
+    env.mock_all_auths();
+    setup.client.set_recovery(&recovery, &MIN_RECOVERY_DELAY);
+    setup.client.initiate_recovery(&recovery, &new_signer);
+
+    // A single signer may veto even though the threshold needs two
+    let payload = BytesN::random(env);
+    let signatures = vec![env, sign(env, &setup.signing_key, &payload)];
+    assert_eq!(
+        check_auth_with_context(
+            env,
+            &setup.client,
+            &payload,
+            signatures,
+            vec![
+                env,
+                contract_context(env, &setup.client.address, "cancel_recovery")
+            ],
+        ),
+        Ok(())
+    );
+    let signatures = vec![
+        env,
+        sign_with_nonce(env, &setup.signing_key, &payload, setup.client.nonce()),
+    ];
+    assert_eq!(
+        check_auth_with_context(
+            env,
+            &setup.client,
+            &payload,
+            signatures,
+            vec![
+                env,
+                contract_context(env, &setup.client.address, "set_threshold")
+            ],
+        ),
+        Err(AccountError::ThresholdNotMet)
+    );
+
+    // A key that isn't a signer can't veto, even when unknown signers are
+    // counted as unsatisfied rather than refused
+    setup.client.set_uniform_verification(&true);
+    let (stranger, _) = generate_signer(env);
+    let signatures = vec![
+        env,
+        sign_with_nonce(env, &stranger, &payload, setup.client.nonce()),
+    ];
+    assert_eq!(
+        check_auth_with_context(
+            env,
+            &setup.client,
+            &payload,
+            signatures,
+            vec![
+                env,
+                contract_context(env, &setup.client.address, "cancel_recovery")
+            ],
+        ),
+        Err(AccountError::ThresholdNotMet)
+    );
+
+    setup.client.cancel_recovery();
+    assert_eq!(setup.client.pending_recovery(), None);
+    env.ledger()
+        .set_sequence_number(env.ledger().sequence() + MIN_RECOVERY_DELAY);
+    assert_eq!(
+        setup.client.try_execute_recovery(),
+        Err(Ok(AccountError::NoPendingRecovery))
+    );
+}
+
+#[test]
+fn test_freeze() {
+    let setup = setup(1);
+    let env = &setup.env;
+    env.mock_all_auths();
+    setup.client.freeze(&setup.admin);
+    assert!(setup.client.is_frozen());
+
+    let payload = BytesN::random(env);
+    let signatures = vec![env, sign(env, &setup.signing_key, &payload)];
+    assert_eq!(
+        check_auth(env, &setup.client, &payload, signatures.clone()),
+        Err(AccountError::AccountFrozen)
+    );
+
+    // Admin functions keep working so the account can be recovered
+    add_signer(&setup);
+    setup.client.unfreeze();
+    assert!(!setup.client.is_frozen());
+    assert_eq!(check_auth(env, &setup.client, &payload, signatures), Ok(()));
+}
+
+#[test]
+fn test_freeze_token() {
+    let setup = setup(1);
+    let env = &setup.env;
+    let frozen = Address::generate(env);
+    let other = Address::generate(env);
+    let guardian = Address::generate(env);
+    env.mock_all_auths();
+    setup.client.add_guardian(&guardian);
+    setup.client.freeze_token(&guardian, &frozen);
+    assert_eq!(
+        env.events().all(),
+        vec![
+            env,
+            (
+                setup.client.address.clone(),
+                TokenFrozen {
+                    token: frozen.clone()
+                }
+                .topics(env),
+                TokenFrozen {
+                    token: frozen.clone()
+                }
+                .data(env),
+            ),
+        ]
+    );
+    assert!(setup.client.is_token_frozen(&frozen));
+    assert!(!setup.client.is_token_frozen(&other));
+    assert!(!setup.client.is_frozen());
+
+    let transfer = |token: &Address| {
+        Context::Contract(ContractContext {
+            contract: token.clone(),
+            fn_name: symbol_short!("transfer"),
+            args: (
+                setup.client.address.clone(),
+                Address::generate(env),
+                100i128,
+            )
+                .into_val(env),
+        })
+    };
+    let payload = BytesN::random(env);
+    let signed = |nonce| {
+        vec![
+            env,
+            sign_with_nonce(env, &setup.signing_key, &payload, nonce),
+        ]
+    };
+    assert_eq!(
+        check_auth_with_context(
+            env,
+            &setup.client,
+            &payload,
+            signed(0),
+            vec![env, transfer(&frozen)]
+        ),
+        Err(AccountError::TokenFrozen)
+    );
+    assert_eq!(
+        check_auth_with_context(
+            env,
+            &setup.client,
+            &payload,
+            signed(0),
+            vec![env, transfer(&other), transfer(&frozen)]
+        ),
+        Err(AccountError::TokenFrozen)
+    );
+    assert_eq!(
+        check_auth_with_context(
+            env,
+            &setup.client,
+            &payload,
+            signed(0),
+            vec![env, transfer(&other)]
+        ),
+        Ok(())
+    );
+    // Calls other than transfers still go through
+    assert_eq!(
+        check_auth_with_context(
+            env,
+            &setup.client,
+            &payload,
+            signed(1),
+            vec![env, contract_context(env, &frozen, "approve")]
+        ),
+        Ok(())
+    );
+
+    setup.client.unfreeze_token(&setup.admin, &frozen);
+    assert!(!setup.client.is_token_frozen(&frozen));
+    assert_eq!(
+        check_auth_with_context(
+            env,
+            &setup.client,
+            &payload,
+            signed(2),
+            vec![env, transfer(&frozen)]
+        ),
+        Ok(())
+    );
+    assert_eq!(
+        setup
+            .client
+            .try_freeze_token(&Address::generate(env), &frozen),
+        Err(Ok(AccountError::NotAuthorized))
+    );
+}
+
+#[test]
+fn test_guardian() {
+    let setup = setup(1);
+    let env = &setup.env;
+    let guardian = Address::generate(env);
+    env.mock_all_auths();
+    assert_eq!(
+        setup.client.try_freeze(&guardian),
+        Err(Ok(AccountError::NotAuthorized))
+    );
+    setup.client.add_guardian(&guardian);
+    assert!(setup.client.is_guardian(&guardian));
+
+    // The guardian's own authorization is what's required
+    let (_, signer) = generate_signer(env);
+    env.mock_auths(&[MockAuth {
+        address: &guardian,
+        invoke: &MockAuthInvoke {
+            contract: &setup.client.address,
+            fn_name: "freeze",
+            args: (guardian.clone(),).into_val(env),
+            sub_invokes: &[],
+        },
+    }]);
+    setup.client.freeze(&guardian);
+    assert!(setup.client.is_frozen());
+
+    // It can do nothing else
+    env.mock_auths(&[MockAuth {
+        address: &guardian,
+        invoke: &MockAuthInvoke {
+            contract: &setup.client.address,
+            fn_name: "unfreeze",
+            args: ().into_val(env),
+            sub_invokes: &[],
+        },
+    }]);
+    assert!(setup.client.try_unfreeze().is_err());
+    assert_eq!(
+        setup
+            .client
+            .try_add_signer(&guardian, &signer, &1, &None, &None, &None),
+        Err(Ok(AccountError::NotAuthorized))
+    );
+    assert!(setup.client.is_frozen());
+
+    env.mock_all_auths();
+    setup.client.remove_guardian(&guardian);
+    assert!(!setup.client.is_guardian(&guardian));
+    setup.client.unfreeze();
+    assert_eq!(
+        setup.client.try_freeze(&guardian),
+        Err(Ok(AccountError::NotAuthorized))
+    );
+}
+
+#[test]
+fn test_weight_overflow() {
+    let setup = setup(1);
+    let env = &setup.env;
+    env.mock_all_auths();
+    let (_, big) = generate_signer(env);
+    let (_, other) = generate_signer(env);
+
+    for weight in [u32::MAX, MAX_TOTAL_WEIGHT] {
+        assert_eq!(
+            setup
+                .client
+                .try_add_signer(&setup.admin, &big, &weight, &None, &None, &None),
+            Err(Ok(AccountError::WeightOverflow))
+        );
+    }
+    // Up to the cap, counting a reweighted signer once
+    setup.client.add_signer(
+        &setup.admin,
+        &big,
+        &(MAX_TOTAL_WEIGHT - 2),
+        &None,
+        &None,
+        &None,
+    );
+    setup.client.add_signer(
+        &setup.admin,
+        &big,
+        &(MAX_TOTAL_WEIGHT - 1),
+        &None,
+        &None,
+        &None,
+    );
+    assert_eq!(
+        setup
+            .client
+            .try_add_signer(&setup.admin, &other, &1, &None, &None, &None),
+        Err(Ok(AccountError::WeightOverflow))
+    );
+    setup.client.set_threshold(&MAX_TOTAL_WEIGHT);
+    assert_eq!(
+        setup.client.try_set_threshold(&(MAX_TOTAL_WEIGHT + 1)),
+        Err(Ok(AccountError::WeightOverflow))
+    );
+    assert_eq!(
+        setup.client.try_set_threshold(&u32::MAX),
+        Err(Ok(AccountError::WeightOverflow))
+    );
+}
+
+#[test]
+fn test_weight_overflow_at_auth() {
+    let setup = setup(1);
+    let env = &setup.env;
+    let second = add_signer(&setup);
+    // Weights stored before the cap existed may sum past u32::MAX
+    for signing_key in [&setup.signing_key, &second] {
+        let signer = SignerKey::Ed25519(BytesN::from_array(
+            env,
+            &signing_key.verifying_key().to_bytes(),
+        ));
+        env.as_contract(&setup.client.address, || {
+            let record = crate::signer_record(env, &signer).unwrap();
+            crate::set_signer_record(
+                env,
+                &signer,
+                &SignerRecord {
+                    weight: u32::MAX - 1,
+                    ..record
+                },
+            );
+        });
+    }
+
+    let payload = BytesN::random(env);
+    assert_eq!(
+        check_auth(
+            env,
+            &setup.client,
+            &payload,
+            vec![
+                env,
+                sign(env, &setup.signing_key, &payload),
+                sign(env, &second, &payload),
+            ],
+        ),
+        Err(AccountError::WeightOverflow)
+    );
+    assert_eq!(
+        check_auth(
+            env,
+            &setup.client,
+            &payload,
+            vec![env, sign(env, &second, &payload)],
+        ),
+        Ok(())
+    );
+}
+
+#[test]
+fn test_co_signer() {
+    let setup = setup(1);
+    let env = &setup.env;
+    let companion = add_signer(&setup);
+    let primary = BytesN::from_array(env, &setup.signing_key.verifying_key().to_bytes());
+    let companion_key = BytesN::from_array(env, &companion.verifying_key().to_bytes());
+    env.mock_all_auths();
+    setup.client.set_co_signer(&primary, &companion_key);
+    assert_eq!(
+        setup.client.co_signer(&primary),
+        Some(companion_key.clone())
+    );
+
+    let payload = BytesN::random(env);
+    assert_eq!(
+        check_auth(
+            env,
+            &setup.client,
+            &payload,
+            vec![env, sign(env, &setup.signing_key, &payload)],
+        ),
+        Err(AccountError::CoSignerRequired)
+    );
+    assert_eq!(
+        check_auth(
+            env,
+            &setup.client,
+            &payload,
+            vec![
+                env,
+                sign(env, &setup.signing_key, &payload),
+                sign(env, &companion, &payload),
+            ],
+        ),
+        Ok(())
+    );
+    // The companion needs no co-signer of its own
+    assert_eq!(
+        check_auth(
+            env,
+            &setup.client,
+            &payload,
+            vec![env, sign_with_nonce(env, &companion, &payload, 1)],
+        ),
+        Ok(())
+    );
+
+    let message = b"I accept the terms of service";
+    let digest = message_digest(env, message);
+    assert_eq!(
+        setup.client.try_verify_message(
+            &Bytes::from_slice(env, message),
+            &vec![env, sign_message(env, &setup.signing_key, &digest)]
+        ),
+        Err(Ok(AccountError::CoSignerRequired))
+    );
+
+    setup.client.clear_co_signer(&primary);
+    assert_eq!(setup.client.co_signer(&primary), None);
+    assert_eq!(
+        check_auth(
+            env,
+            &setup.client,
+            &payload,
+            vec![env, sign_with_nonce(env, &setup.signing_key, &payload, 2)],
+        ),
+        Ok(())
+    );
+
+    let (_, unknown) = generate_signer(env);
+    assert_eq!(
+        setup.client.try_set_co_signer(&primary, &unknown),
+        Err(Ok(AccountError::UnknownSigner))
+    );
+    assert_eq!(
+        setup.client.try_set_co_signer(&primary, &primary),
+        Err(Ok(AccountError::DuplicateSigner))
+    );
+}
+
+#[test]
+fn test_policy() {
+    let setup = setup_weighted(2, 3);
+    let env = &setup.env;
+    assert_eq!(
+        setup.client.policy(),
+        AccountPolicy {
+            threshold: 3,
+            total_weight: 2,
+            signer_count: 1,
+            frozen: false,
+            has_master_signer: false,
+            has_recovery: false,
+        }
+    );
+
+    env.mock_all_auths();
+    add_weighted_signer(&setup, 3);
+    setup.client.freeze(&setup.admin);
+    setup.client.set_master_signer(&Some(BytesN::random(env)));
+    setup
+        .client
+        .set_recovery(&Address::generate(env), &MIN_RECOVERY_DELAY);
+    assert_eq!(
+        setup.client.policy(),
+        AccountPolicy {
+            threshold: 3,
+            total_weight: 5,
+            signer_count: 2,
+            frozen: true,
+            has_master_signer: true,
+            has_recovery: true,
+        }
+    );
+}
+
+#[test]
+fn test_exact_signatures() {
+    let setup = setup(2);
+    let env = &setup.env;
+    let second = add_signer(&setup);
+    let third = add_signer(&setup);
+    setup.client.set_exact_signatures(&true);
+
+    let payload = BytesN::random(env);
+    assert_eq!(
+        check_auth(
+            env,
+            &setup.client,
+            &payload,
+            vec![
+                env,
+                sign(env, &setup.signing_key, &payload),
+                sign(env, &second, &payload),
+                sign(env, &third, &payload),
+            ],
+        ),
+        Err(AccountError::TooManySignatures)
+    );
+    assert_eq!(
+        check_auth(
+            env,
+            &setup.client,
+            &payload,
+            vec![
+                env,
+                sign(env, &setup.signing_key, &payload),
+                sign(env, &second, &payload),
+            ],
+        ),
+        Ok(())
+    );
+}
+
+#[test]
+fn test_exact_signatures_disabled() {
+    let setup = setup(2);
+    let env = &setup.env;
+    let second = add_signer(&setup);
+    let third = add_signer(&setup);
+    assert!(!setup.client.exact_signatures());
+
+    let payload = BytesN::random(env);
+    assert_eq!(
+        check_auth(
+            env,
+            &setup.client,
+            &payload,
+            vec![
+                env,
+                sign(env, &setup.signing_key, &payload),
+                sign(env, &second, &payload),
+                sign(env, &third, &payload),
+            ],
+        ),
+        Ok(())
+    );
+}
+
+#[test]
+fn test_signer_labels() {
+    let setup = setup(1);
+    let env = &setup.env;
+    env.mock_all_auths();
+    let (_, signer) = generate_signer(env);
+
+    setup.client.add_signer(
+        &setup.admin,
+        &signer,
+        &1,
+        &None,
+        &Some(String::from_str(env, "iPhone")),
+        &None,
+    );
+    assert_eq!(
+        setup.client.signer_label(&signer),
+        Some(String::from_str(env, "iPhone"))
+    );
+
+    // Updating the weight alone keeps the label
+    setup
+        .client
+        .add_signer(&setup.admin, &signer, &2, &None, &None, &None);
+    assert_eq!(
+        setup.client.signer_label(&signer),
+        Some(String::from_str(env, "iPhone"))
+    );
+
+    let ledger = String::from_str(env, "Ledger");
+    setup
+        .client
+        .set_signer_label(&setup.client.address, &signer, &ledger);
+    assert_eq!(setup.client.signer_label(&signer), Some(ledger.clone()));
+    setup
+        .client
+        .set_signer_label(&setup.admin, &signer, &ledger);
+
+    let (_, unlabelled) = generate_signer(env);
+    assert_eq!(setup.client.signer_label(&unlabelled), None);
+    assert_eq!(
+        setup
+            .client
+            .try_set_signer_label(&Address::generate(env), &signer, &ledger),
+        Err(Ok(AccountError::NotAuthorized))
+    );
+}
+
+#[test]
+fn test_signer_fingerprint() {
+    let setup = setup(1);
+    let env = &setup.env;
+    let (_, signer) = generate_signer(env);
+    let (_, other) = generate_signer(env);
+
+    // Matches what a client computes off-chain
+    let fingerprint = setup.client.signer_fingerprint(&signer);
+    let digest = Sha256::digest(signer.to_array());
+    assert_eq!(fingerprint.to_array(), digest[..4]);
+    assert_eq!(setup.client.signer_fingerprint(&signer), fingerprint);
+    assert_ne!(setup.client.signer_fingerprint(&other), fingerprint);
+}
+
+#[test]
+fn test_signature_order_independent() {
+    let setup = setup(3);
+    let env = &setup.env;
+    let first = setup.signing_key.clone();
+    let second = add_signer(&setup);
+    let third = add_weighted_signer(&setup, 2);
+    let payload = BytesN::random(env);
+
+    let sign_in_order = |keys: &[&SigningKey]| {
+        let nonce = setup.client.nonce();
+        let mut signatures = Vec::new(env);
+        for key in keys {
+            signatures.push_back(sign_with_nonce(env, key, &payload, nonce));
+        }
+        signatures
+    };
+
+    for keys in [
+        [&first, &second, &third],
+        [&third, &first, &second],
+        [&second, &third, &first],
+    ] {
+        let signatures = sign_in_order(&keys);
+        assert_eq!(check_auth(env, &setup.client, &payload, signatures), Ok(()));
+    }
+
+    for keys in [[&first, &second], [&second, &first]] {
+        let signatures = sign_in_order(&keys);
+        assert_eq!(
+            check_auth(env, &setup.client, &payload, signatures),
+            Err(AccountError::ThresholdNotMet)
+        );
+    }
+}
+
+#[test]
+fn test_admin() {
+    let setup = setup(1);
+    assert_eq!(setup.client.admin(), setup.admin);
+}
+
+#[test]
+fn test_max_contexts() {
+    let setup = setup(1);
+    let env = &setup.env;
+    env.mock_all_auths();
+    setup.client.set_max_contexts(&2);
+    assert_eq!(setup.client.max_contexts(), 2);
+
+    let target = Address::generate(env);
+    let mut auth_context = vec![env, contract_context(env, &target, "transfer")];
+    auth_context.push_back(contract_context(env, &target, "transfer"));
+
+    let payload = BytesN::random(env);
+    assert_eq!(
+        check_auth_with_context(
+            env,
+            &setup.client,
+            &payload,
+            vec![env, sign(env, &setup.signing_key, &payload)],
+            auth_context.clone(),
+        ),
+        Ok(())
+    );
+
+    auth_context.push_back(contract_context(env, &target, "transfer"));
+    assert_eq!(
+        check_auth_with_context(
+            env,
+            &setup.client,
+            &payload,
+            vec![
+                env,
+                sign_with_nonce(env, &setup.signing_key, &payload, setup.client.nonce()),
+            ],
+            auth_context,
+        ),
+        Err(AccountError::TooManyContexts)
+    );
+}
+
+#[test]
+fn test_uniform_verification() {
+    let setup = setup(1);
+    let env = &setup.env;
+    let (unknown, _) = generate_signer(env);
+    let payload = BytesN::random(env);
+    let other_payload = BytesN::random(env);
+
+    let bad_signatures = [
+        vec![env, sign(env, &unknown, &other_payload)],
+        vec![env, sign(env, &setup.signing_key, &other_payload)],
+    ];
+    let try_check_auth = |signatures: Vec<AccountSignature>| {
+        env.try_invoke_contract_check_auth::<AccountError>(
+            &setup.client.address,
+            &payload,
+            signatures.into_val(env),
+            &vec![env],
+        )
+    };
+
+    // By default an unknown key is rejected before its signature is checked
+    assert_eq!(
+        try_check_auth(bad_signatures[0].clone()),
+        Err(Ok(AccountError::UnknownSigner))
+    );
+
+    env.mock_all_auths();
+    setup.client.set_uniform_verification(&true);
+    for signatures in bad_signatures {
+        assert_eq!(try_check_auth(signatures), Err(Err(InvokeError::Abort)));
+    }
+
+    // A valid signature from an unknown key counts for nothing
+    let signatures = vec![env, sign(env, &unknown, &payload)];
+    assert_eq!(
+        check_auth(env, &setup.client, &payload, signatures),
+        Err(AccountError::ThresholdNotMet)
+    );
+}
+
+#[test]
+fn test_revoke_sessions() {
+    let setup = setup(1);
+    let env = &setup.env;
+    let session = add_signer(&setup);
+    let session_key = BytesN::from_array(env, &session.verifying_key().to_bytes());
+    setup
+        .client
+        .set_session_signer(&SignerKey::Ed25519(session_key));
+
+    let payload = BytesN::random(env);
+    let signatures = vec![env, sign(env, &session, &payload)];
+    assert_eq!(check_auth(env, &setup.client, &payload, signatures), Ok(()));
+
+    setup.client.revoke_sessions(&setup.client.address);
+    let revoked = SessionsRevoked { epoch: 1 };
+    assert_eq!(
+        env.events().all(),
+        vec![
+            env,
+            (
+                setup.client.address.clone(),
+                revoked.topics(env),
+                revoked.data(env)
+            )
+        ]
+    );
+    assert_eq!(setup.client.session_epoch(), 1);
+
+    let nonce = setup.client.nonce();
+    let signatures = vec![env, sign_with_nonce(env, &session, &payload, nonce)];
+    assert_eq!(
+        check_auth(env, &setup.client, &payload, signatures),
+        Err(AccountError::SessionRevoked)
+    );
+
+    // Signers outside the session scheme are unaffected
+    let signatures = vec![
+        env,
+        sign_with_nonce(env, &setup.signing_key, &payload, nonce),
+    ];
+    assert_eq!(check_auth(env, &setup.client, &payload, signatures), Ok(()));
+}
+
+#[test]
+fn test_domain_separator() {
+    let setup = setup(1);
+    let env = &setup.env;
+    let payload = BytesN::random(env);
+    let separator = BytesN::random(env);
+    let mut message = Bytes::from(separator.clone());
+    message.append(&payload.clone().into());
+    let separated: BytesN<32> = env.crypto().sha256(&message).into();
+
+    let signatures = vec![env, sign(env, &setup.signing_key, &payload)];
+    assert_eq!(check_auth(env, &setup.client, &payload, signatures), Ok(()));
+
+    env.mock_all_auths();
+    setup.client.set_domain_separator(&Some(separator.clone()));
+    assert_eq!(setup.client.domain_separator(), Some(separator));
+    let signatures = vec![
+        env,
+        sign_with_nonce(env, &setup.signing_key, &separated, setup.client.nonce()),
+    ];
+    assert_eq!(check_auth(env, &setup.client, &payload, signatures), Ok(()));
+
+    setup.client.set_domain_separator(&None);
+    let signatures = vec![
+        env,
+        sign_with_nonce(env, &setup.signing_key, &payload, setup.client.nonce()),
+    ];
+    assert_eq!(check_auth(env, &setup.client, &payload, signatures), Ok(()));
+}
+
+#[test]
+fn test_testutils_signatures() {
+    let env = Env::default();
+    let (first_key, first) = testutils::random_signer(&env);
+    let (second_key, second) = testutils::random_signer(&env);
+    let contract_id = env.register(
+        Account,
+        (
+            AdminMode::External(Address::generate(&env)),
+            vec![&env, first, second],
+            1u32,
+            2u32,
+        ),
+    );
+    let client = AccountClient::new(&env, &contract_id);
+
+    for nonce in 0..2 {
+        let payload = BytesN::random(&env);
+        let signatures = vec![
+            &env,
+            AccountSignature::Ed25519(testutils::sign_payload(&env, &first_key, &payload, nonce)),
+            AccountSignature::Ed25519(testutils::sign_payload(&env, &second_key, &payload, nonce)),
+        ];
+        assert_eq!(check_auth(&env, &client, &payload, signatures), Ok(()));
+    }
+}
+
+#[test]
+fn test_web_auth_domain_separation() {
+    let setup = setup(1);
+    let env = &setup.env;
+    let web_auth = Address::generate(env);
+    let token = Address::generate(env);
+    let payload = BytesN::random(env);
+
+    // A signature over the bare payload, as for a transfer, is no web auth
+    // assertion
+    let signatures = vec![env, sign(env, &setup.signing_key, &payload)];
+    let result = env.try_invoke_contract_check_auth::<AccountError>(
+        &setup.client.address,
+        &payload,
+        signatures.into_val(env),
+        &vec![env, contract_context(env, &web_auth, "web_auth_verify")],
+    );
+    assert!(result.is_err());
+
+    let signatures = vec![
+        env,
+        sign(env, &setup.signing_key, &web_auth_message(env, &payload)),
+    ];
+    assert_eq!(
+        check_auth_with_context(
+            env,
+            &setup.client,
+            &payload,
+            signatures,
+            vec![env, contract_context(env, &web_auth, "web_auth_verify")],
+        ),
+        Ok(())
+    );
+
+    // Nor is a web auth assertion a transfer signature
+    let payload = BytesN::random(env);
+    let signatures = vec![
+        env,
+        sign_with_nonce(
+            env,
+            &setup.signing_key,
+            &web_auth_message(env, &payload),
+            setup.client.nonce(),
+        ),
+    ];
+    let result = env.try_invoke_contract_check_auth::<AccountError>(
+        &setup.client.address,
+        &payload,
+        signatures.into_val(env),
+        &vec![env, contract_context(env, &token, "transfer")],
+    );
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_web_auth_entrypoints_tagged() {
+    let setup = setup(1);
+    let env = &setup.env;
+    let web_auth = Address::generate(env);
+
+    for fn_name in [
+        "web_auth_verify",
+        "web_auth_verify_many",
+        "web_auth_verify_full",
+        "web_auth_verify_reporting",
+        "web_auth_verify_struct",
+    ] {
+        let payload = BytesN::random(env);
+        let auth_context = vec![env, contract_context(env, &web_auth, fn_name)];
+        let nonce = setup.client.nonce();
+        let signatures = vec![
+            env,
+            sign_with_nonce(env, &setup.signing_key, &payload, nonce),
+        ];
+        let result = env.try_invoke_contract_check_auth::<AccountError>(
+            &setup.client.address,
+            &payload,
+            signatures.into_val(env),
+            &auth_context,
+        );
+        assert!(result.is_err(), "{fn_name} accepted an untagged signature");
+
+        let message = web_auth_message(env, &payload);
+        let signatures = vec![
+            env,
+            sign_with_nonce(env, &setup.signing_key, &message, nonce),
+        ];
+        assert_eq!(
+            check_auth_with_context(env, &setup.client, &payload, signatures, auth_context),
+            Ok(()),
+            "{fn_name} refused a tagged signature"
+        );
+    }
+
+    // Other functions, even with similar names, are signed untagged
+    for fn_name in [
+        "web_auth",
+        "verify",
+        "web_auth_verif",
+        "web_auth_verify_evil",
+    ] {
+        let payload = BytesN::random(env);
+        let signatures = vec![
+            env,
+            sign_with_nonce(env, &setup.signing_key, &payload, setup.client.nonce()),
+        ];
+        assert_eq!(
+            check_auth_with_context(
+                env,
+                &setup.client,
+                &payload,
+                signatures,
+                vec![env, contract_context(env, &web_auth, fn_name)],
+            ),
+            Ok(()),
+            "{fn_name} was treated as web auth"
+        );
+    }
+}
+
+#[test]
+fn test_check_signatures() {
+    let setup = setup(2);
+    let env = &setup.env;
+    let second = add_signer(&setup);
+    let (unknown, _) = generate_signer(env);
+    let payload = BytesN::random(env);
+    let first_key = SignerKey::Ed25519(BytesN::from_array(
+        env,
+        &setup.signing_key.verifying_key().to_bytes(),
+    ));
+    let second_key =
+        SignerKey::Ed25519(BytesN::from_array(env, &second.verifying_key().to_bytes()));
+
+    let signatures = vec![
+        env,
+        sign(env, &setup.signing_key, &payload),
+        sign(env, &unknown, &payload),
+    ];
+    assert_eq!(
+        setup.client.check_signatures(&payload, &signatures),
+        vec![env, first_key.clone()]
+    );
+    assert_eq!(
+        check_auth(env, &setup.client, &payload, signatures),
+        Err(AccountError::UnknownSigner)
+    );
+
+    let signatures = vec![env, sign(env, &setup.signing_key, &payload)];
+    assert_eq!(
+        setup.client.check_signatures(&payload, &signatures).len(),
+        1
+    );
+    assert_eq!(
+        check_auth(env, &setup.client, &payload, signatures),
+        Err(AccountError::ThresholdNotMet)
+    );
+
+    let signatures = vec![
+        env,
+        sign(env, &setup.signing_key, &payload),
+        sign(env, &second, &payload),
+    ];
+    assert_eq!(
+        setup.client.check_signatures(&payload, &signatures),
+        vec![env, first_key, second_key]
+    );
+    assert_eq!(check_auth(env, &setup.client, &payload, signatures), Ok(()));
+}
+
+#[test]
+#[ignore = "requires the account WASM, run `stellar contract build` first"]
+fn test_upgrade_event() {
+    let setup = setup(1);
+    let env = &setup.env;
+    env.mock_all_auths();
+    let account_wasm = std::fs::read(ACCOUNT_WASM).unwrap();
+    let new_wasm_hash = env.deployer().upload_contract_wasm(account_wasm.as_slice());
+
+    setup.client.upgrade(&new_wasm_hash, &None);
+    let upgraded = Upgraded {
+        new_wasm_hash,
+        admin: setup.admin.clone(),
+    };
+    assert_eq!(
+        env.events().all(),
+        vec![
+            env,
+            (
+                setup.client.address.clone(),
+                upgraded.topics(env),
+                upgraded.data(env)
+            )
+        ]
+    );
+}
+
+#[test]
+fn test_upgrade_same_wasm_hash() {
+    let setup = setup(1);
+    let env = &setup.env;
+    env.mock_all_auths();
+    let Some(Executable::Wasm(current_wasm_hash)) = setup.client.address.executable() else {
+        panic!("account should be a wasm contract");
+    };
+
+    assert_eq!(
+        setup.client.try_upgrade(&current_wasm_hash, &None),
+        Err(Ok(AccountError::SameWasmHash))
+    );
+}
+
+#[test]
+fn test_version() {
+    let setup = setup(1);
+    assert_eq!(setup.client.version(), VERSION);
+}
+
+#[test]
+fn test_error_messages() {
+    let errors = [
+        AccountError::UnknownSigner,
+        AccountError::TooManySignatures,
+        AccountError::ThresholdNotMet,
+        AccountError::DuplicateSigner,
+        AccountError::InvalidClientData,
+        AccountError::LastSigner,
+        AccountError::NoPendingAdmin,
+        AccountError::ContextNotAllowed,
+        AccountError::SignerExpired,
+        AccountError::BadNonce,
+        AccountError::NoSignatures,
+        AccountError::VersionMismatch,
+        AccountError::NoSigners,
+        AccountError::SpendLimitExceeded,
+        AccountError::NoRecovery,
+        AccountError::NoPendingRecovery,
+        AccountError::RecoveryNotReady,
+        AccountError::AccountFrozen,
+        AccountError::NotAuthorized,
+        AccountError::TooManyContexts,
+        AccountError::SessionRevoked,
+        AccountError::SameWasmHash,
+        AccountError::RateLimited,
+        AccountError::NoAdmin,
+        AccountError::MalformedSignature,
+        AccountError::TooManySigners,
+        AccountError::SignerRevoked,
+        AccountError::SelfDelegation,
+        AccountError::ThresholdUnsatisfiable,
+        AccountError::AccountClosed,
+        AccountError::AlreadyInitialized,
+        AccountError::UnsupportedAlgorithm,
+        AccountError::SignerExhausted,
+        AccountError::SignaturesTooLarge,
+        AccountError::InvalidPublicKey,
+        AccountError::ContextMismatch,
+        AccountError::WeightOverflow,
+        AccountError::CoSignerRequired,
+        AccountError::ChangeTooSoon,
+        AccountError::TokenFrozen,
+        AccountError::TooManyRecoveryRequests,
+        AccountError::InvalidRecoveryDelay,
+        AccountError::DelegationTooDeep,
+        AccountError::UserNotPresent,
+    ];
+    let unknown = error_message(0);
+    let mut messages = std::collections::BTreeSet::new();
+    for error in errors {
+        let message = error_message(error as u32);
+        assert!(!message.is_empty());
+        assert_ne!(message, unknown);
+        assert!(messages.insert(message), "{error:?} shares its message");
+    }
+    assert_eq!(error_message(errors.len() as u32 + 1), unknown);
+}
+
+#[test]
+fn test_data_key_names() {
+    let env = Env::default();
+    let key = BytesN::random(&env);
+    let address = Address::generate(&env);
+    let keys = [
+        DataKey::Admin,
+        DataKey::Signer(key.clone()),
+        DataKey::Threshold,
+        DataKey::Secp256r1Signer(BytesN::from_array(&env, &[4; 65])),
+        DataKey::SignerList,
+        DataKey::PendingAdmin,
+        DataKey::SignerScope(SignerKey::Ed25519(key.clone())),
+        DataKey::SignerExpiry(SignerKey::Ed25519(key.clone())),
+        DataKey::Nonce,
+        DataKey::MaxSignatures,
+        DataKey::Version,
+        DataKey::SpendLimit(key.clone()),
+        DataKey::SpendHistory(key.clone()),
+        DataKey::Recovery,
+        DataKey::PendingRecovery,
+        DataKey::Frozen,
+        DataKey::ExactSignatures,
+        DataKey::SignerLabel(key.clone()),
+        DataKey::MaxContexts,
+        DataKey::UniformVerification,
+        DataKey::SessionEpoch,
+        DataKey::SignerSession(SignerKey::Ed25519(key.clone())),
+        DataKey::DomainSeparator,
+        DataKey::MasterSigner,
+        DataKey::RateLimit,
+        DataKey::RateWindow,
+        DataKey::MaxSigners,
+        DataKey::LastAuth,
+        DataKey::Signers,
+        DataKey::Revoked(key.clone()),
+        DataKey::PersistentSigner(SignerKey::Ed25519(key.clone())),
+        DataKey::RecoveryGroup,
+        DataKey::SignerGrace(key.clone()),
+        DataKey::Initialized,
+        DataKey::SignerUses(key.clone()),
+        DataKey::MaxSigBytes,
+        DataKey::SignerFnScope(key.clone()),
+        DataKey::SignerRequiredFns(key.clone()),
+        DataKey::Guardian(address.clone()),
+        DataKey::CoSigner(key.clone()),
+        DataKey::ChangeCooldown,
+        DataKey::LastChange,
+        DataKey::FrozenToken(address.clone()),
+        DataKey::SelfAuth,
+        DataKey::FullContext,
+        DataKey::SignerTxCap(key.clone()),
+        DataKey::PendingRecoveries,
+        DataKey::MaxRecoveryRequests,
+        DataKey::MaxDelegationDepth,
+    ];
+    let mut names = std::collections::BTreeSet::new();
+    for key in &keys {
+        let name = testutils::data_key_name(key);
+        // The name an enum variant is stored under in its XDR
+        let stored: Val = key.into_val(&env);
+        let stored = Vec::<Val>::try_from_val(&env, &stored).unwrap();
+        assert_eq!(
+            Symbol::try_from_val(&env, &stored.get(0).unwrap()).unwrap(),
+            Symbol::new(&env, name)
+        );
+        assert!(names.insert(name), "{name} is used twice");
+    }
+}
+
+/// The `(key, value)` entries of a WASM's `contractmetav0` custom section
+fn contract_meta(wasm: &[u8]) -> std::vec::Vec<(std::string::String, std::string::String)> {
+    fn leb128(bytes: &[u8], pos: &mut usize) -> usize {
+        let mut value = 0;
+        let mut shift = 0;
+        loop {
+            let byte = bytes[*pos];
+            *pos += 1;
+            value |= ((byte & 0x7f) as usize) << shift;
+            if byte & 0x80 == 0 {
+                return value;
+            }
+            shift += 7;
+        }
+    }
+
+    let mut meta = std::vec::Vec::new();
+    // Past the magic number and version
+    let mut pos = 8;
+    while pos < wasm.len() {
+        let id = wasm[pos];
+        pos += 1;
+        let size = leb128(wasm, &mut pos);
+        let end = pos + size;
+        if id == 0 {
+            let mut data = pos;
+            let name_len = leb128(wasm, &mut data);
+            if &wasm[data..data + name_len] == b"contractmetav0" {
+                let mut entries = Limited::new(&wasm[data + name_len..end], Limits::none());
+                for entry in ScMetaEntry::read_xdr_iter(&mut entries) {
+                    let ScMetaEntry::ScMetaV0(ScMetaV0 { key, val }) = entry.unwrap();
+                    meta.push((key.to_utf8_string_lossy(), val.to_utf8_string_lossy()));
+                }
+            }
+        }
+        pos = end;
+    }
+    meta
+}
+
+#[test]
+#[ignore = "requires the account WASM, run `stellar contract build` first"]
+fn test_contract_meta() {
+    use std::string::ToString;
+
+    let meta = contract_meta(&std::fs::read(ACCOUNT_WASM).unwrap());
+    for (key, val) in [
+        ("name", "account"),
+        ("version", VERSION.to_string().as_str()),
+        ("sep", "45"),
+    ] {
+        assert!(
+            meta.contains(&(key.to_string(), val.to_string())),
+            "missing {key}={val} in {meta:?}"
+        );
+    }
+}
@@ -0,0 +1,257 @@
+#![cfg(test)]
+//! Randomized inputs for `__check_auth`, checking malformed signatures are
+//! refused with an `AccountError` rather than trapping
+//!
+//! The seed corpus below runs on every `cargo test`, followed by random cases
+//! decoded from bytes with `arbitrary`, as many as `FUZZ_ITERATIONS` sets. A
+//! libFuzzer target can drive `check_auth_bytes` with its own bytes.
+//!
+//! Signatures are either genuine or malformed in ways the contract can see.
+//! A well-formed signature that fails verification traps in the host by
+//! design, so cases never pair a registered key with bytes it didn't sign.
+extern crate std;
+
+use arbitrary::{Arbitrary, Unstructured};
+use rand::{rngs::OsRng, RngCore};
+use soroban_sdk::{
+    auth::{Context, ContractContext},
+    testutils::{Address as _, BytesN as _},
+    vec, Address, Bytes, BytesN, Env, IntoVal, Symbol, Vec,
+};
+use std::{env, format, vec::Vec as StdVec};
+
+use crate::{
+    testutils::{random_signer, sign_payload},
+    Account, AccountError, AccountSignature, AdminMode, Secp256r1Signature, Signature,
+};
+
+/// Random cases run after the corpus unless `FUZZ_ITERATIONS` says otherwise
+const DEFAULT_ITERATIONS: usize = 64;
+
+/// Bytes of randomness each random case is decoded from
+const RANDOM_INPUT_LEN: usize = 2048;
+
+#[derive(Arbitrary, Debug)]
+enum SignatureInput {
+    /// Signed by the registered key, carrying `nonce` unless `current_nonce`
+    Registered {
+        nonce: u64,
+        current_nonce: bool,
+    },
+    /// Signed by a key the account doesn't know
+    Unregistered {
+        nonce: u64,
+    },
+    /// Signed by the registered key but tagged with another algorithm
+    Algorithm {
+        algorithm: u32,
+    },
+    Passkey {
+        public_key: [u8; 32],
+        tag: u8,
+        authenticator_data: StdVec<u8>,
+        client_data_json: StdVec<u8>,
+        nonce: u64,
+    },
+    Delegate,
+    /// Repeats an earlier signature in the list
+    Repeat(u8),
+}
+
+#[derive(Arbitrary, Debug)]
+struct CheckAuthInput {
+    signatures: StdVec<SignatureInput>,
+    /// Contract calls being authorized
+    contexts: u8,
+}
+
+/// Runs `__check_auth` for the case `data` decodes to
+fn check_auth_bytes(data: &[u8]) {
+    if let Ok(input) = CheckAuthInput::arbitrary(&mut Unstructured::new(data)) {
+        check_auth_input(&input);
+    }
+}
+
+/// Runs `__check_auth` for `input`, panicking if it fails any way other than
+/// with an `AccountError`
+fn check_auth_input(input: &CheckAuthInput) {
+    let env = Env::default();
+    let (signing_key, public_key) = random_signer(&env);
+    let contract_id = env.register(
+        Account,
+        (
+            AdminMode::External(Address::generate(&env)),
+            vec![&env, public_key],
+            1u32,
+            1u32,
+        ),
+    );
+    let payload = BytesN::<32>::random(&env);
+
+    let mut signatures: Vec<AccountSignature> = Vec::new(&env);
+    for signature in &input.signatures {
+        let signature = match signature {
+            SignatureInput::Registered {
+                nonce,
+                current_nonce,
+            } => {
+                let nonce = if *current_nonce { 0 } else { *nonce };
+                AccountSignature::Ed25519(sign_payload(&env, &signing_key, &payload, nonce))
+            }
+            SignatureInput::Unregistered { nonce } => {
+                let (signing_key, _) = random_signer(&env);
+                AccountSignature::Ed25519(sign_payload(&env, &signing_key, &payload, *nonce))
+            }
+            SignatureInput::Algorithm { algorithm } => AccountSignature::Ed25519(Signature {
+                algorithm: *algorithm,
+                ..sign_payload(&env, &signing_key, &payload, 0)
+            }),
+            SignatureInput::Passkey {
+                public_key,
+                tag,
+                authenticator_data,
+                client_data_json,
+                nonce,
+            } => {
+                let mut point = [0u8; 65];
+                point[0] = *tag;
+                point[1..33].copy_from_slice(public_key);
+                point[33..].copy_from_slice(public_key);
+                AccountSignature::Secp256r1(Secp256r1Signature {
+                    public_key: BytesN::from_array(&env, &point),
+                    authenticator_data: Bytes::from_slice(&env, authenticator_data),
+                    client_data_json: Bytes::from_slice(&env, client_data_json),
+                    signature: BytesN::random(&env),
+                    nonce: *nonce,
+                })
+            }
+            SignatureInput::Delegate => AccountSignature::Delegate(Address::generate(&env)),
+            SignatureInput::Repeat(index) => {
+                if signatures.is_empty() {
+                    continue;
+                }
+                signatures.get(*index as u32 % signatures.len()).unwrap()
+            }
+        };
+        signatures.push_back(signature);
+    }
+
+    let mut auth_context: Vec<Context> = Vec::new(&env);
+    for i in 0..input.contexts {
+        auth_context.push_back(Context::Contract(ContractContext {
+            contract: Address::generate(&env),
+            fn_name: Symbol::new(&env, &format!("fn_{i}")),
+            args: vec![&env],
+        }));
+    }
+
+    let result = env.try_invoke_contract_check_auth::<AccountError>(
+        &contract_id,
+        &payload,
+        signatures.into_val(&env),
+        &auth_context,
+    );
+    assert!(
+        !matches!(result, Err(Err(_))),
+        "{input:?} failed without an AccountError: {result:?}"
+    );
+}
+
+fn seed_corpus() -> StdVec<CheckAuthInput> {
+    let registered = || SignatureInput::Registered {
+        nonce: 0,
+        current_nonce: true,
+    };
+    let input = |signatures, contexts| CheckAuthInput {
+        signatures,
+        contexts,
+    };
+    std::vec![
+        // No signatures, over no calls and over one
+        input(std::vec![], 0),
+        input(std::vec![], 1),
+        // A genuine signature, alone and over more calls than are allowed
+        input(std::vec![registered()], 1),
+        input(std::vec![registered()], u8::MAX),
+        // Duplicates of a genuine signature
+        input(std::vec![registered(), SignatureInput::Repeat(0)], 1),
+        input(std::vec![registered(), registered()], 1),
+        // Stale and unknown signers
+        input(
+            std::vec![SignatureInput::Registered {
+                nonce: u64::MAX,
+                current_nonce: false,
+            }],
+            1,
+        ),
+        input(std::vec![SignatureInput::Unregistered { nonce: 0 }], 1),
+        input(
+            std::vec![SignatureInput::Algorithm {
+                algorithm: u32::MAX
+            }],
+            1
+        ),
+        input(std::vec![SignatureInput::Delegate], 1),
+        // Passkey data that is empty, oversized or doesn't commit to the payload
+        input(
+            std::vec![SignatureInput::Passkey {
+                public_key: [0; 32],
+                tag: 0x04,
+                authenticator_data: std::vec![],
+                client_data_json: std::vec![],
+                nonce: 0,
+            }],
+            1,
+        ),
+        input(
+            std::vec![SignatureInput::Passkey {
+                public_key: [0xff; 32],
+                tag: 0x04,
+                authenticator_data: std::vec![0; 37],
+                client_data_json: std::vec![b'{'; 4096],
+                nonce: 0,
+            }],
+            1,
+        ),
+        input(
+            std::vec![SignatureInput::Passkey {
+                public_key: [1; 32],
+                tag: 0x02,
+                authenticator_data: std::vec![0; 37],
+                client_data_json: b"{}".to_vec(),
+                nonce: 0,
+            }],
+            1,
+        ),
+        // More signatures than the account allows
+        input(
+            core::iter::repeat_with(|| SignatureInput::Unregistered { nonce: 0 })
+                .take(40)
+                .collect(),
+            1,
+        ),
+    ]
+}
+
+fn iterations() -> usize {
+    env::var("FUZZ_ITERATIONS")
+        .ok()
+        .and_then(|iterations| iterations.parse().ok())
+        .unwrap_or(DEFAULT_ITERATIONS)
+}
+
+#[test]
+fn fuzz_check_auth_corpus() {
+    for input in seed_corpus() {
+        check_auth_input(&input);
+    }
+}
+
+#[test]
+fn fuzz_check_auth_random() {
+    let mut data = [0u8; RANDOM_INPUT_LEN];
+    for _ in 0..iterations() {
+        OsRng.fill_bytes(&mut data);
+        check_auth_bytes(&data);
+    }
+}
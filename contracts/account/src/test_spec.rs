@@ -0,0 +1,261 @@
+#![cfg(test)]
+//! The contract's interface, checked against the committed `spec.json`
+//!
+//! Client generators read `spec.json` in place of the spec in the WASM, so
+//! they needn't build it. The entries are those the SDK generates for each
+//! function, type and event, sorted by kind and name. After changing the
+//! interface on purpose, run the tests with `UPDATE_SPEC` set to rewrite the
+//! file.
+extern crate std;
+
+use soroban_sdk::xdr::{Limited, Limits, ReadXdr, ScSpecEntry};
+use std::{env, fs, string::String, vec::Vec};
+
+use crate::{
+    events, Account, AccountError, AccountPolicy, AccountSignature, AdminMode, DataKey, RateLimit,
+    RateWindow, Recovery, RecoveryRequest, Secp256r1Signature, Signature, SignerKey, SignerKind,
+    SignerRecord, SignerStatus, SpendLimit, SpendRecord,
+};
+
+const SPEC_JSON: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/spec.json");
+
+/// Produced by `stellar contract build`
+const ACCOUNT_WASM: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/../target/wasm32v1-none/release/account.wasm"
+);
+
+fn entry(xdr: &[u8]) -> ScSpecEntry {
+    ScSpecEntry::from_xdr(xdr, Limits::none()).unwrap()
+}
+
+/// Every entry in the contract's spec, listed by hand as the SDK exposes
+/// them one at a time outside the WASM
+fn spec_entries() -> Vec<ScSpecEntry> {
+    std::vec![
+        entry(&Account::spec_xdr_upgrade()),
+        entry(&Account::spec_xdr_migrate()),
+        entry(&Account::spec_xdr___constructor()),
+        entry(&Account::spec_xdr_admin()),
+        entry(&Account::spec_xdr_admin_mode()),
+        entry(&Account::spec_xdr_renounce_admin()),
+        entry(&Account::spec_xdr_close()),
+        entry(&Account::spec_xdr_set_admin()),
+        entry(&Account::spec_xdr_accept_admin()),
+        entry(&Account::spec_xdr_set_recovery()),
+        entry(&Account::spec_xdr_set_recovery_group()),
+        entry(&Account::spec_xdr_recovery_group()),
+        entry(&Account::spec_xdr_initiate_recovery()),
+        entry(&Account::spec_xdr_execute_recovery()),
+        entry(&Account::spec_xdr_cancel_recovery()),
+        entry(&Account::spec_xdr_pending_recovery()),
+        entry(&Account::spec_xdr_pending_recoveries()),
+        entry(&Account::spec_xdr_set_max_recovery_requests()),
+        entry(&Account::spec_xdr_max_recovery_requests()),
+        entry(&Account::spec_xdr_freeze()),
+        entry(&Account::spec_xdr_freeze_token()),
+        entry(&Account::spec_xdr_unfreeze_token()),
+        entry(&Account::spec_xdr_is_token_frozen()),
+        entry(&Account::spec_xdr_unfreeze()),
+        entry(&Account::spec_xdr_is_frozen()),
+        entry(&Account::spec_xdr_add_guardian()),
+        entry(&Account::spec_xdr_remove_guardian()),
+        entry(&Account::spec_xdr_is_guardian()),
+        entry(&Account::spec_xdr_set_threshold()),
+        entry(&Account::spec_xdr_add_signer()),
+        entry(&Account::spec_xdr_ensure_signer()),
+        entry(&Account::spec_xdr_signer_uses()),
+        entry(&Account::spec_xdr_signer_status()),
+        entry(&Account::spec_xdr_set_signer_label()),
+        entry(&Account::spec_xdr_signer_label()),
+        entry(&Account::spec_xdr_signer_fingerprint()),
+        entry(&Account::spec_xdr_remove_signer()),
+        entry(&Account::spec_xdr_set_change_cooldown()),
+        entry(&Account::spec_xdr_change_cooldown()),
+        entry(&Account::spec_xdr_replace_signers()),
+        entry(&Account::spec_xdr_rotate_signer()),
+        entry(&Account::spec_xdr_signer_grace()),
+        entry(&Account::spec_xdr_swap_signer()),
+        entry(&Account::spec_xdr_revoke_signer()),
+        entry(&Account::spec_xdr_unrevoke_signer()),
+        entry(&Account::spec_xdr_is_revoked()),
+        entry(&Account::spec_xdr_add_delegate_signer()),
+        entry(&Account::spec_xdr_remove_delegate_signer()),
+        entry(&Account::spec_xdr_add_secp256r1_signer()),
+        entry(&Account::spec_xdr_remove_secp256r1_signer()),
+        entry(&Account::spec_xdr_signer_weight()),
+        entry(&Account::spec_xdr_is_signer()),
+        entry(&Account::spec_xdr_signer_count()),
+        entry(&Account::spec_xdr_set_session_signer()),
+        entry(&Account::spec_xdr_revoke_sessions()),
+        entry(&Account::spec_xdr_session_epoch()),
+        entry(&Account::spec_xdr_add_scoped_signer()),
+        entry(&Account::spec_xdr_set_signer_scope()),
+        entry(&Account::spec_xdr_clear_signer_scope()),
+        entry(&Account::spec_xdr_set_signer_fn_scope()),
+        entry(&Account::spec_xdr_clear_signer_fn_scope()),
+        entry(&Account::spec_xdr_signer_fn_scope()),
+        entry(&Account::spec_xdr_set_co_signer()),
+        entry(&Account::spec_xdr_clear_co_signer()),
+        entry(&Account::spec_xdr_co_signer()),
+        entry(&Account::spec_xdr_set_signer_required_fns()),
+        entry(&Account::spec_xdr_clear_signer_required_fns()),
+        entry(&Account::spec_xdr_signer_required_fns()),
+        entry(&Account::spec_xdr_set_signer_tx_cap()),
+        entry(&Account::spec_xdr_clear_signer_tx_cap()),
+        entry(&Account::spec_xdr_signer_tx_cap()),
+        entry(&Account::spec_xdr_set_spend_limit()),
+        entry(&Account::spec_xdr_clear_spend_limit()),
+        entry(&Account::spec_xdr_spend_limit()),
+        entry(&Account::spec_xdr_signer_scope()),
+        entry(&Account::spec_xdr_explain_context()),
+        entry(&Account::spec_xdr_list_signers()),
+        entry(&Account::spec_xdr_list_signers_paged()),
+        entry(&Account::spec_xdr_list_signers_of_kind()),
+        entry(&Account::spec_xdr_signer_kind_counts()),
+        entry(&Account::spec_xdr_nonce()),
+        entry(&Account::spec_xdr_set_max_signatures()),
+        entry(&Account::spec_xdr_set_max_sig_bytes()),
+        entry(&Account::spec_xdr_max_sig_bytes()),
+        entry(&Account::spec_xdr_set_max_signers()),
+        entry(&Account::spec_xdr_max_signers()),
+        entry(&Account::spec_xdr_set_max_contexts()),
+        entry(&Account::spec_xdr_max_contexts()),
+        entry(&Account::spec_xdr_set_max_delegation_depth()),
+        entry(&Account::spec_xdr_max_delegation_depth()),
+        entry(&Account::spec_xdr_check_signatures()),
+        entry(&Account::spec_xdr_verify_message()),
+        entry(&Account::spec_xdr_authorize_hash()),
+        entry(&Account::spec_xdr_set_domain_separator()),
+        entry(&Account::spec_xdr_domain_separator()),
+        entry(&Account::spec_xdr_set_rate_limit()),
+        entry(&Account::spec_xdr_clear_rate_limit()),
+        entry(&Account::spec_xdr_rate_limit()),
+        entry(&Account::spec_xdr_last_auth()),
+        entry(&Account::spec_xdr_set_master_signer()),
+        entry(&Account::spec_xdr_master_signer()),
+        entry(&Account::spec_xdr_set_uniform_verification()),
+        entry(&Account::spec_xdr_uniform_verification()),
+        entry(&Account::spec_xdr_set_full_context()),
+        entry(&Account::spec_xdr_full_context()),
+        entry(&Account::spec_xdr_set_exact_signatures()),
+        entry(&Account::spec_xdr_exact_signatures()),
+        entry(&Account::spec_xdr_max_signatures()),
+        entry(&Account::spec_xdr_version()),
+        entry(&Account::spec_xdr_storage_version()),
+        entry(&Account::spec_xdr_threshold()),
+        entry(&Account::spec_xdr_policy()),
+        entry(&Account::spec_xdr___check_auth()),
+        entry(&DataKey::spec_xdr()),
+        entry(&AdminMode::spec_xdr()),
+        entry(&SignerKey::spec_xdr()),
+        entry(&SignerRecord::spec_xdr()),
+        entry(&SignerKind::spec_xdr()),
+        entry(&SpendLimit::spec_xdr()),
+        entry(&Recovery::spec_xdr()),
+        entry(&RecoveryRequest::spec_xdr()),
+        entry(&RateLimit::spec_xdr()),
+        entry(&RateWindow::spec_xdr()),
+        entry(&AccountPolicy::spec_xdr()),
+        entry(&SignerStatus::spec_xdr()),
+        entry(&SpendRecord::spec_xdr()),
+        entry(&Signature::spec_xdr()),
+        entry(&Secp256r1Signature::spec_xdr()),
+        entry(&AccountSignature::spec_xdr()),
+        entry(&AccountError::spec_xdr()),
+        entry(&events::SignerAdded::spec_xdr()),
+        entry(&events::SignerRemoved::spec_xdr()),
+        entry(&events::SignersReplaced::spec_xdr()),
+        entry(&events::SignerSwapped::spec_xdr()),
+        entry(&events::AdminSet::spec_xdr()),
+        entry(&events::ThresholdSet::spec_xdr()),
+        entry(&events::AdminProposed::spec_xdr()),
+        entry(&events::RecoveryInitiated::spec_xdr()),
+        entry(&events::RecoveryCancelled::spec_xdr()),
+        entry(&events::Frozen::spec_xdr()),
+        entry(&events::Unfrozen::spec_xdr()),
+        entry(&events::TokenFrozen::spec_xdr()),
+        entry(&events::TokenUnfrozen::spec_xdr()),
+        entry(&events::SessionsRevoked::spec_xdr()),
+        entry(&events::Closed::spec_xdr()),
+        entry(&events::Upgraded::spec_xdr()),
+        entry(&events::AuthRejected::spec_xdr()),
+        entry(&events::AuthSucceeded::spec_xdr()),
+    ]
+}
+
+/// `entries` as the pretty-printed JSON `spec.json` holds, in a stable order
+fn spec_json(entries: Vec<ScSpecEntry>) -> String {
+    let mut entries: Vec<serde_json::Value> = entries
+        .iter()
+        .map(|entry| serde_json::to_value(entry).unwrap())
+        .collect();
+    entries.sort_by_cached_key(|entry| {
+        let (kind, body) = entry.as_object().unwrap().iter().next().unwrap();
+        (
+            kind.clone(),
+            String::from(body["name"].as_str().unwrap_or_default()),
+        )
+    });
+    serde_json::to_string_pretty(&entries).unwrap() + "\n"
+}
+
+/// The contents of the custom section `name` in `wasm`
+fn custom_section<'a>(wasm: &'a [u8], name: &[u8]) -> Option<&'a [u8]> {
+    fn leb128(bytes: &[u8], pos: &mut usize) -> usize {
+        let mut value = 0;
+        let mut shift = 0;
+        loop {
+            let byte = bytes[*pos];
+            *pos += 1;
+            value |= ((byte & 0x7f) as usize) << shift;
+            if byte & 0x80 == 0 {
+                return value;
+            }
+            shift += 7;
+        }
+    }
+
+    // Past the magic number and version
+    let mut pos = 8;
+    while pos < wasm.len() {
+        let id = wasm[pos];
+        pos += 1;
+        let size = leb128(wasm, &mut pos);
+        let end = pos + size;
+        if id == 0 {
+            let mut data = pos;
+            let name_len = leb128(wasm, &mut data);
+            if &wasm[data..data + name_len] == name {
+                return Some(&wasm[data + name_len..end]);
+            }
+        }
+        pos = end;
+    }
+    None
+}
+
+#[test]
+fn test_spec_matches_golden() {
+    let spec = spec_json(spec_entries());
+    if env::var_os("UPDATE_SPEC").is_some() {
+        fs::write(SPEC_JSON, &spec).unwrap();
+        return;
+    }
+    assert!(
+        fs::read_to_string(SPEC_JSON).unwrap() == spec,
+        "the interface no longer matches spec.json, rerun with UPDATE_SPEC=1 if that's intended"
+    );
+}
+
+#[test]
+#[ignore = "requires the account WASM, run `stellar contract build` first"]
+fn test_spec_matches_wasm() {
+    // Catches an entry missing from `spec_entries`
+    let wasm = fs::read(ACCOUNT_WASM).unwrap();
+    let section = custom_section(&wasm, b"contractspecv0").unwrap();
+    let entries = ScSpecEntry::read_xdr_iter(&mut Limited::new(section, Limits::none()))
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(spec_json(entries), spec_json(spec_entries()));
+}
@@ -0,0 +1,138 @@
+//! Helpers for tests that authorize through an `Account`, enabled by the
+//! `testutils` feature
+//!
+//! They produce the same signatures a wallet would, so downstream test suites
+//! needn't re-derive how `__check_auth` expects them to be built.
+
+use ed25519_dalek::{Signer, SigningKey};
+use rand::rngs::OsRng;
+use soroban_sdk::{auth::Context, xdr::ToXdr, Bytes, BytesN, Env, Vec};
+
+use crate::{DataKey, Signature, ALGORITHM_ED25519, WEB_AUTH_TAG};
+
+/// A fresh ed25519 key, along with the public key to register as a signer
+pub fn random_signer(env: &Env) -> (SigningKey, BytesN<32>) {
+    let signing_key = SigningKey::generate(&mut OsRng);
+    let public_key = BytesN::from_array(env, &signing_key.verifying_key().to_bytes());
+    (signing_key, public_key)
+}
+
+/// Signs `message` bound to the account's current `nonce` with
+/// `signing_key`, carrying the nonce
+///
+/// For an ordinary authorization the message is the `__check_auth` payload
+/// itself when no domain separator is configured. Use `web_auth_message` for
+/// one that includes a SEP-45 web auth call.
+pub fn sign_payload(
+    env: &Env,
+    signing_key: &SigningKey,
+    message: &BytesN<32>,
+    nonce: u64,
+) -> Signature {
+    let message = nonce_bound_message(env, message, nonce);
+    Signature {
+        algorithm: ALGORITHM_ED25519,
+        public_key: BytesN::from_array(env, &signing_key.verifying_key().to_bytes()),
+        signature: BytesN::from_array(env, &signing_key.sign(&message.to_array()).to_bytes()),
+        nonce,
+    }
+}
+
+/// Signs `message` with `signing_key` as it is, for `verify_message`, which
+/// binds no nonce
+pub fn sign_message(env: &Env, signing_key: &SigningKey, message: &BytesN<32>) -> Signature {
+    Signature {
+        algorithm: ALGORITHM_ED25519,
+        public_key: BytesN::from_array(env, &signing_key.verifying_key().to_bytes()),
+        signature: BytesN::from_array(env, &signing_key.sign(&message.to_array()).to_bytes()),
+        nonce: 0,
+    }
+}
+
+/// What a key signs for `message` at the account's `nonce`, as `sign_payload`
+/// does. Passkeys use it as their WebAuthn challenge.
+pub fn nonce_bound_message(env: &Env, message: &BytesN<32>, nonce: u64) -> BytesN<32> {
+    let mut bound = Bytes::from(message.clone());
+    bound.append(&Bytes::from_array(env, &nonce.to_be_bytes()));
+    env.crypto().sha256(&bound).into()
+}
+
+/// The message signers sign for a web auth `payload`, when no domain
+/// separator is configured
+pub fn web_auth_message(env: &Env, payload: &BytesN<32>) -> BytesN<32> {
+    let mut message = Bytes::from_slice(env, WEB_AUTH_TAG);
+    message.append(&payload.clone().into());
+    env.crypto().sha256(&message).into()
+}
+
+/// The message signers sign for `message` over `auth_context` while the
+/// account has `full_context` set
+pub fn context_bound_message(
+    env: &Env,
+    message: &BytesN<32>,
+    auth_context: &Vec<Context>,
+) -> BytesN<32> {
+    let mut bound = Bytes::from(message.clone());
+    bound.append(
+        &env.crypto()
+            .sha256(&auth_context.clone().to_xdr(env))
+            .into(),
+    );
+    env.crypto().sha256(&bound).into()
+}
+
+/// The name of `key`'s variant, for telling apart ledger entries dumped from
+/// an account
+pub fn data_key_name(key: &DataKey) -> &'static str {
+    match key {
+        DataKey::Admin => "Admin",
+        DataKey::Signer(_) => "Signer",
+        DataKey::Threshold => "Threshold",
+        DataKey::Secp256r1Signer(_) => "Secp256r1Signer",
+        DataKey::SignerList => "SignerList",
+        DataKey::PendingAdmin => "PendingAdmin",
+        DataKey::SignerScope(_) => "SignerScope",
+        DataKey::SignerExpiry(_) => "SignerExpiry",
+        DataKey::Nonce => "Nonce",
+        DataKey::MaxSignatures => "MaxSignatures",
+        DataKey::Version => "Version",
+        DataKey::SpendLimit(_) => "SpendLimit",
+        DataKey::SpendHistory(_) => "SpendHistory",
+        DataKey::Recovery => "Recovery",
+        DataKey::PendingRecovery => "PendingRecovery",
+        DataKey::Frozen => "Frozen",
+        DataKey::ExactSignatures => "ExactSignatures",
+        DataKey::SignerLabel(_) => "SignerLabel",
+        DataKey::MaxContexts => "MaxContexts",
+        DataKey::UniformVerification => "UniformVerification",
+        DataKey::SessionEpoch => "SessionEpoch",
+        DataKey::SignerSession(_) => "SignerSession",
+        DataKey::DomainSeparator => "DomainSeparator",
+        DataKey::MasterSigner => "MasterSigner",
+        DataKey::RateLimit => "RateLimit",
+        DataKey::RateWindow => "RateWindow",
+        DataKey::MaxSigners => "MaxSigners",
+        DataKey::LastAuth => "LastAuth",
+        DataKey::Signers => "Signers",
+        DataKey::Revoked(_) => "Revoked",
+        DataKey::PersistentSigner(_) => "PersistentSigner",
+        DataKey::RecoveryGroup => "RecoveryGroup",
+        DataKey::SignerGrace(_) => "SignerGrace",
+        DataKey::Initialized => "Initialized",
+        DataKey::SignerUses(_) => "SignerUses",
+        DataKey::MaxSigBytes => "MaxSigBytes",
+        DataKey::SignerFnScope(_) => "SignerFnScope",
+        DataKey::SignerRequiredFns(_) => "SignerRequiredFns",
+        DataKey::Guardian(_) => "Guardian",
+        DataKey::CoSigner(_) => "CoSigner",
+        DataKey::ChangeCooldown => "ChangeCooldown",
+        DataKey::LastChange => "LastChange",
+        DataKey::FrozenToken(_) => "FrozenToken",
+        DataKey::SelfAuth => "SelfAuth",
+        DataKey::FullContext => "FullContext",
+        DataKey::SignerTxCap(_) => "SignerTxCap",
+        DataKey::PendingRecoveries => "PendingRecoveries",
+        DataKey::MaxRecoveryRequests => "MaxRecoveryRequests",
+        DataKey::MaxDelegationDepth => "MaxDelegationDepth",
+    }
+}
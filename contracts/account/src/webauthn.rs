@@ -0,0 +1,101 @@
+use soroban_sdk::{crypto::Hash, Bytes, BytesN, Env};
+
+/// Upper bound on the size of a `clientDataJSON` accepted for verification
+const MAX_CLIENT_DATA_LEN: usize = 1024;
+
+/// The rpIdHash, flags and signature counter every `authenticatorData` starts with
+const MIN_AUTHENTICATOR_DATA_LEN: u32 = 37;
+
+/// Offset of the flags byte in `authenticatorData`, after the rpIdHash
+const FLAGS_OFFSET: u32 = 32;
+
+/// The UP flag, set once the authenticator has confirmed a user is present
+const USER_PRESENT: u8 = 0x01;
+
+/// The `type` member of the `clientDataJSON` of an assertion
+const ASSERTION_TYPE: &[u8] = b"\"type\":\"webauthn.get\"";
+
+/// SEC1 tag of an uncompressed curve point
+const UNCOMPRESSED_POINT_TAG: u8 = 0x04;
+
+const BASE64_URL_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// Length of a 32-byte value once base64url encoded without padding
+const ENCODED_CHALLENGE_LEN: usize = 43;
+
+fn base64_url_encode(input: &[u8; 32]) -> [u8; ENCODED_CHALLENGE_LEN] {
+    let mut out = [0u8; ENCODED_CHALLENGE_LEN];
+    let mut i = 0;
+    let mut o = 0;
+    while i + 3 <= input.len() {
+        let n = (input[i] as u32) << 16 | (input[i + 1] as u32) << 8 | input[i + 2] as u32;
+        out[o] = BASE64_URL_ALPHABET[(n >> 18) as usize & 63];
+        out[o + 1] = BASE64_URL_ALPHABET[(n >> 12) as usize & 63];
+        out[o + 2] = BASE64_URL_ALPHABET[(n >> 6) as usize & 63];
+        out[o + 3] = BASE64_URL_ALPHABET[n as usize & 63];
+        i += 3;
+        o += 4;
+    }
+    // 32 bytes leaves a 2 byte remainder, encoded as 3 characters
+    let n = (input[i] as u32) << 16 | (input[i + 1] as u32) << 8;
+    out[o] = BASE64_URL_ALPHABET[(n >> 18) as usize & 63];
+    out[o + 1] = BASE64_URL_ALPHABET[(n >> 12) as usize & 63];
+    out[o + 2] = BASE64_URL_ALPHABET[(n >> 6) as usize & 63];
+    out
+}
+
+/// Checks the `clientDataJSON` is for an assertion and commits to the 32-byte
+/// `signature_payload` as its challenge, so a credential creation's client
+/// data can't stand in for it
+pub fn client_data_matches(signature_payload: &Bytes, client_data_json: &Bytes) -> bool {
+    let len = client_data_json.len() as usize;
+    if len > MAX_CLIENT_DATA_LEN || signature_payload.len() != 32 {
+        return false;
+    }
+    let mut challenge = [0u8; 32];
+    signature_payload.copy_into_slice(&mut challenge);
+
+    let mut buf = [0u8; MAX_CLIENT_DATA_LEN];
+    client_data_json.copy_into_slice(&mut buf[..len]);
+    let json = &buf[..len];
+
+    let mut expected = [0u8; 13 + ENCODED_CHALLENGE_LEN + 1];
+    expected[..13].copy_from_slice(b"\"challenge\":\"");
+    expected[13..13 + ENCODED_CHALLENGE_LEN].copy_from_slice(&base64_url_encode(&challenge));
+    expected[13 + ENCODED_CHALLENGE_LEN] = b'"';
+
+    json.windows(ASSERTION_TYPE.len())
+        .any(|window| window == ASSERTION_TYPE)
+        && json
+            .windows(expected.len())
+            .any(|window| window == expected)
+}
+
+/// Checks the `authenticatorData` flags the user as present, i.e. the
+/// authenticator got a gesture for this assertion rather than signing silently
+pub fn user_present(authenticator_data: &Bytes) -> bool {
+    authenticator_data
+        .get(FLAGS_OFFSET)
+        .is_some_and(|flags| flags & USER_PRESENT != 0)
+}
+
+/// Checks an assertion's fields have the shape the host's verification
+/// expects, so malformed input is rejected rather than trapping
+pub fn well_formed(
+    public_key: &BytesN<65>,
+    authenticator_data: &Bytes,
+    client_data_json: &Bytes,
+) -> bool {
+    public_key.get(0) == Some(UNCOMPRESSED_POINT_TAG)
+        && authenticator_data.len() >= MIN_AUTHENTICATOR_DATA_LEN
+        && !client_data_json.is_empty()
+        && client_data_json.len() as usize <= MAX_CLIENT_DATA_LEN
+}
+
+/// Computes the digest an authenticator signs for a WebAuthn assertion
+pub fn signed_digest(env: &Env, authenticator_data: &Bytes, client_data_json: &Bytes) -> Hash<32> {
+    let mut message = authenticator_data.clone();
+    message.append(&env.crypto().sha256(client_data_json).into());
+    env.crypto().sha256(&message)
+}
@@ -0,0 +1,139 @@
+#![no_std]
+
+use soroban_sdk::{
+    contract, contracterror, contractimpl, contracttype, vec, Address, Bytes, BytesN, Env, IntoVal,
+    InvokeError, Symbol, Val, Vec,
+};
+
+#[contract]
+pub struct AccountFactory;
+
+#[derive(Clone)]
+#[contracttype]
+pub enum DataKey {
+    Admin,
+    AccountWasmHash,
+}
+
+/// The `External` case of the account's `AdminMode`, which is all the factory
+/// deploys with
+#[contracttype]
+pub enum AdminMode {
+    External(Address),
+}
+
+#[contracterror]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum FactoryError {
+    MigrationFailed = 1,
+}
+
+/// Prefixed to a user id before hashing it into a salt, so a derived salt
+/// never coincides with one chosen some other way
+pub const USER_SALT_TAG: &[u8] = b"SEP-45 account salt";
+
+fn require_admin(env: &Env) {
+    let admin: Address = env.storage().instance().get(&DataKey::Admin).unwrap();
+    admin.require_auth();
+}
+
+#[contractimpl]
+impl AccountFactory {
+    /// Arguments:
+    /// - admin: The address allowed to update the account WASM
+    /// - account_wasm_hash: The hash of the uploaded `Account` WASM to deploy
+    pub fn __constructor(env: Env, admin: Address, account_wasm_hash: BytesN<32>) {
+        env.storage().instance().set(&DataKey::Admin, &admin);
+        env.storage()
+            .instance()
+            .set(&DataKey::AccountWasmHash, &account_wasm_hash);
+    }
+
+    pub fn account_wasm_hash(env: Env) -> BytesN<32> {
+        env.storage()
+            .instance()
+            .get(&DataKey::AccountWasmHash)
+            .unwrap()
+    }
+
+    /// Switches the WASM used for accounts deployed from now on. Accounts
+    /// already deployed are unaffected.
+    pub fn set_account_wasm_hash(env: Env, account_wasm_hash: BytesN<32>) {
+        require_admin(&env);
+
+        env.storage()
+            .instance()
+            .set(&DataKey::AccountWasmHash, &account_wasm_hash);
+    }
+
+    /// The address `deploy` will produce for `salt`, so it can be funded
+    /// ahead of deployment
+    pub fn predict_address(env: Env, salt: BytesN<32>) -> Address {
+        env.deployer()
+            .with_current_contract(salt)
+            .deployed_address()
+    }
+
+    /// The salt `deploy_for` uses for `user_id`, letting a service derive one
+    /// wallet address per user from a stable identifier
+    pub fn salt_for(env: Env, user_id: Bytes) -> BytesN<32> {
+        let mut tagged = Bytes::from_slice(&env, USER_SALT_TAG);
+        tagged.append(&user_id);
+        env.crypto().sha256(&tagged).to_bytes()
+    }
+
+    /// The address `deploy_for` will produce for `user_id`
+    pub fn predict_address_for(env: Env, user_id: Bytes) -> Address {
+        Self::predict_address(env.clone(), Self::salt_for(env, user_id))
+    }
+
+    /// Deploys an `Account` administered by `admin` with `signer` as its
+    /// only signer, at an address derived from this factory and `salt`
+    pub fn deploy(env: Env, salt: BytesN<32>, admin: Address, signer: BytesN<32>) -> Address {
+        let account_wasm_hash = Self::account_wasm_hash(env.clone());
+
+        env.deployer().with_current_contract(salt).deploy_v2(
+            account_wasm_hash,
+            (AdminMode::External(admin), vec![&env, signer], 1u32, 1u32),
+        )
+    }
+
+    /// `deploy` with the salt derived from `user_id` by `salt_for`
+    pub fn deploy_for(env: Env, user_id: Bytes, admin: Address, signer: BytesN<32>) -> Address {
+        Self::deploy(env.clone(), Self::salt_for(env, user_id), admin, signer)
+    }
+
+    /// Upgrades `account` to `new_wasm_hash` and runs the new code's `migrate`
+    /// in the same transaction, so the new code never runs un-migrated
+    ///
+    /// An account can't do this itself: its new code only takes effect once
+    /// the upgrading invocation returns, and a contract can't call back into
+    /// itself. Both calls require authorization from the account's admin.
+    ///
+    /// The host reports a missing `migrate` function the same way as one that
+    /// traps, so either way `MigrationFailed` is returned and the upgrade is
+    /// rolled back. Use the account's own `upgrade` for code that has nothing
+    /// to migrate.
+    pub fn upgrade_and_migrate(
+        env: Env,
+        account: Address,
+        new_wasm_hash: BytesN<32>,
+    ) -> Result<(), FactoryError> {
+        let expected_version: Option<u32> = None;
+        let args: Vec<Val> = (new_wasm_hash, expected_version).into_val(&env);
+        env.invoke_contract::<()>(&account, &Symbol::new(&env, "upgrade"), args);
+
+        let migrated = env.try_invoke_contract::<(), InvokeError>(
+            &account,
+            &Symbol::new(&env, "migrate"),
+            Vec::new(&env),
+        );
+        if migrated.is_err() {
+            return Err(FactoryError::MigrationFailed);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test;
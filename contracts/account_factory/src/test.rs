@@ -0,0 +1,245 @@
+#![cfg(test)]
+extern crate std;
+
+use soroban_sdk::{
+    contract, contractimpl, symbol_short,
+    testutils::{Address as _, BytesN as _},
+    vec, Address, Bytes, BytesN, Env, Symbol, Vec,
+};
+
+use crate::{AccountFactory, AccountFactoryClient, FactoryError, USER_SALT_TAG};
+
+/// Produced by `stellar contract build`
+const ACCOUNT_WASM: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/../target/wasm32v1-none/release/account.wasm"
+);
+
+struct Setup {
+    env: Env,
+    client: AccountFactoryClient<'static>,
+}
+
+/// Registers a factory deploying the WASM returned by `account_wasm_hash`
+fn setup(account_wasm_hash: impl FnOnce(&Env) -> BytesN<32>) -> Setup {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let admin = Address::generate(&env);
+    let account_wasm_hash = account_wasm_hash(&env);
+    let contract_id = env.register(AccountFactory, (admin, account_wasm_hash));
+    let client = AccountFactoryClient::new(&env, &contract_id);
+
+    Setup { env, client }
+}
+
+#[test]
+fn test_set_account_wasm_hash() {
+    let setup = setup(BytesN::random);
+    let account_wasm_hash = BytesN::random(&setup.env);
+
+    setup.client.set_account_wasm_hash(&account_wasm_hash);
+    assert_eq!(setup.client.account_wasm_hash(), account_wasm_hash);
+}
+
+#[test]
+#[should_panic(expected = "Error(Auth, InvalidAction)")]
+fn test_set_account_wasm_hash_requires_admin() {
+    let setup = setup(BytesN::random);
+    setup.env.set_auths(&[]);
+
+    setup
+        .client
+        .set_account_wasm_hash(&BytesN::random(&setup.env));
+}
+
+#[test]
+fn test_predict_address() {
+    let setup = setup(BytesN::random);
+    let env = &setup.env;
+    let salt = BytesN::random(env);
+
+    assert_eq!(
+        setup.client.predict_address(&salt),
+        setup.client.predict_address(&salt)
+    );
+    assert_ne!(
+        setup.client.predict_address(&salt),
+        setup.client.predict_address(&BytesN::random(env))
+    );
+}
+
+#[test]
+fn test_salt_for() {
+    let setup = setup(BytesN::random);
+    let env = &setup.env;
+    let user_id = Bytes::from_slice(env, b"user-1234");
+
+    let salt = setup.client.salt_for(&user_id);
+    assert_eq!(setup.client.salt_for(&user_id), salt);
+    assert_ne!(
+        setup.client.salt_for(&Bytes::from_slice(env, b"user-1235")),
+        salt
+    );
+    assert_ne!(setup.client.salt_for(&Bytes::new(env)), salt);
+
+    let mut tagged = Bytes::from_slice(env, USER_SALT_TAG);
+    tagged.append(&user_id);
+    assert_eq!(salt, env.crypto().sha256(&tagged).to_bytes());
+
+    let predicted = setup.client.predict_address_for(&user_id);
+    assert_eq!(setup.client.predict_address_for(&user_id), predicted);
+    assert_eq!(setup.client.predict_address(&salt), predicted);
+}
+
+#[test]
+#[ignore = "requires the account WASM, run `stellar contract build` first"]
+fn test_deploy_for() {
+    let account_wasm = std::fs::read(ACCOUNT_WASM).unwrap();
+    let setup = setup(|env| env.deployer().upload_contract_wasm(account_wasm.as_slice()));
+    let env = &setup.env;
+    let user_id = Bytes::from_slice(env, b"user-1234");
+
+    let predicted = setup.client.predict_address_for(&user_id);
+    let deployed = setup
+        .client
+        .deploy_for(&user_id, &Address::generate(env), &BytesN::random(env));
+    assert_eq!(predicted, deployed);
+}
+
+#[test]
+#[ignore = "requires the account WASM, run `stellar contract build` first"]
+fn test_deploy() {
+    let account_wasm = std::fs::read(ACCOUNT_WASM).unwrap();
+    let setup = setup(|env| env.deployer().upload_contract_wasm(account_wasm.as_slice()));
+    let env = &setup.env;
+    let admin = Address::generate(env);
+
+    let first = setup
+        .client
+        .deploy(&BytesN::random(env), &admin, &BytesN::random(env));
+    let second = setup
+        .client
+        .deploy(&BytesN::random(env), &admin, &BytesN::random(env));
+    assert_ne!(first, second);
+
+    for account in [first, second] {
+        let threshold: u32 =
+            env.invoke_contract(&account, &Symbol::new(env, "threshold"), Vec::new(env));
+        assert_eq!(threshold, 1);
+    }
+}
+
+#[test]
+#[ignore = "requires the account WASM, run `stellar contract build` first"]
+fn test_deploy_matches_prediction() {
+    let account_wasm = std::fs::read(ACCOUNT_WASM).unwrap();
+    let setup = setup(|env| env.deployer().upload_contract_wasm(account_wasm.as_slice()));
+    let env = &setup.env;
+    let salt = BytesN::random(env);
+
+    let predicted = setup.client.predict_address(&salt);
+    let deployed = setup
+        .client
+        .deploy(&salt, &Address::generate(env), &BytesN::random(env));
+    assert_eq!(predicted, deployed);
+}
+
+/// Stands in for an account whose new code brings storage up to date
+#[contract]
+struct MigratingAccount;
+
+#[contractimpl]
+impl MigratingAccount {
+    pub fn upgrade(env: Env, new_wasm_hash: BytesN<32>, _expected_version: Option<u32>) {
+        env.storage()
+            .instance()
+            .set(&symbol_short!("wasm"), &new_wasm_hash);
+    }
+
+    pub fn migrate(env: Env) {
+        env.storage()
+            .instance()
+            .set(&symbol_short!("migrated"), &true);
+    }
+}
+
+/// Stands in for an account whose new code has nothing to migrate
+#[contract]
+struct UnversionedAccount;
+
+#[contractimpl]
+impl UnversionedAccount {
+    pub fn upgrade(env: Env, new_wasm_hash: BytesN<32>, _expected_version: Option<u32>) {
+        env.storage()
+            .instance()
+            .set(&symbol_short!("wasm"), &new_wasm_hash);
+    }
+}
+
+#[test]
+fn test_upgrade_and_migrate() {
+    let setup = setup(BytesN::random);
+    let env = &setup.env;
+    let new_wasm_hash = BytesN::random(env);
+
+    let account = env.register(MigratingAccount, ());
+    setup.client.upgrade_and_migrate(&account, &new_wasm_hash);
+    env.as_contract(&account, || {
+        let storage = env.storage().instance();
+        assert_eq!(storage.get(&symbol_short!("wasm")), Some(new_wasm_hash));
+        assert_eq!(storage.get(&symbol_short!("migrated")), Some(true));
+    });
+}
+
+#[test]
+fn test_upgrade_without_migrate() {
+    let setup = setup(BytesN::random);
+    let env = &setup.env;
+    let new_wasm_hash = BytesN::random(env);
+
+    let account = env.register(UnversionedAccount, ());
+    assert_eq!(
+        setup
+            .client
+            .try_upgrade_and_migrate(&account, &new_wasm_hash),
+        Err(Ok(FactoryError::MigrationFailed))
+    );
+    env.as_contract(&account, || {
+        let storage = env.storage().instance();
+        assert_eq!(storage.get::<_, BytesN<32>>(&symbol_short!("wasm")), None);
+    });
+}
+
+#[test]
+#[ignore = "requires the account WASM, run `stellar contract build` first"]
+fn test_upgrade_and_migrate_account() {
+    let account_wasm = std::fs::read(ACCOUNT_WASM).unwrap();
+    let setup = setup(|env| env.deployer().upload_contract_wasm(account_wasm.as_slice()));
+    let env = &setup.env;
+    let account = setup.client.deploy(
+        &BytesN::random(env),
+        &Address::generate(env),
+        &BytesN::random(env),
+    );
+
+    // Wind the account back to unversioned storage
+    env.as_contract(&account, || {
+        env.storage()
+            .instance()
+            .remove(&vec![env, Symbol::new(env, "Version")]);
+    });
+
+    // The same code under a new hash, with an empty custom section appended
+    let mut new_wasm = account_wasm.clone();
+    new_wasm.extend_from_slice(&[0, 2, 1, b'x']);
+    let new_wasm_hash = env.deployer().upload_contract_wasm(new_wasm.as_slice());
+
+    setup.client.upgrade_and_migrate(&account, &new_wasm_hash);
+    let storage_version: u32 = env.invoke_contract(
+        &account,
+        &Symbol::new(env, "storage_version"),
+        Vec::new(env),
+    );
+    assert_eq!(storage_version, 6);
+}
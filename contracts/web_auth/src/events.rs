@@ -0,0 +1,40 @@
+use soroban_sdk::{contractevent, Address, BytesN, String, Symbol, Vec};
+
+use crate::{AccountType, Memo};
+
+#[contractevent(topics = ["web_auth", "verified"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Verified {
+    pub account: Address,
+    pub account_memo: Option<u64>,
+    pub memo: Option<Memo>,
+    pub home_domain: String,
+    pub account_type: AccountType,
+}
+
+#[contractevent(topics = ["web_auth", "break_glass_used"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BreakGlassUsed {
+    pub account: Address,
+    pub until: u32,
+}
+
+#[contractevent(topics = ["contract", "upgraded"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Upgraded {
+    pub new_wasm_hash: BytesN<32>,
+    pub admin: Address,
+}
+
+#[contractevent(topics = ["required_args", "set"], data_format = "single-value")]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RequiredArgsSet {
+    pub required_args: Vec<Symbol>,
+}
+
+#[contractevent(topics = ["web_auth", "domains_set"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DomainsSet {
+    pub home_domain: String,
+    pub web_auth_domain: String,
+}
@@ -1,48 +1,1730 @@
 #![no_std]
-use soroban_sdk::{contract, contracterror, contractimpl, Address, Env, Map, String, Symbol};
+use soroban_sdk::{
+    contract, contracterror, contractimpl, contractmeta, contracttype, panic_with_error, vec,
+    xdr::ToXdr, Address, Bytes, BytesN, Env, Executable, IntoVal, Map, String, Symbol, Vec,
+};
+
+mod events;
+mod strkey;
 
 #[contract]
 pub struct WebAuthContract;
 
+#[derive(Clone)]
+#[contracttype]
+pub enum DataKey {
+    Admin,
+    RequiredArgs,
+    HomeDomain,
+    MaxValidity,
+    StrictArgs,
+    /// A nonce consumed before they were kept per account, still checked
+    /// until it expires
+    Nonce(String),
+    WebAuthDomainAccount,
+    ClientDomains,
+    MinValidity,
+    ClientDomainPolicy,
+    WebAuthDomain,
+    NetworkId,
+    MemoPolicy,
+    /// Set once the constructor has run
+    Initialized,
+    ServerAccounts,
+    AllowClassic,
+    /// Last ledger sequence break glass mode stays on for
+    BreakGlass,
+    /// Seconds of clock drift tolerated when comparing `exp` to the ledger
+    ClockSkew,
+    /// Successful verifications for an account
+    LoginCount(Address),
+    /// Contract asked whether each verified account is allowed
+    PolicyContract,
+    /// Contract asked whether each account is one this deployment serves
+    AccountRegistry,
+    /// A nonce consumed by a challenge for the account, in temporary storage
+    /// until the challenge expires
+    AccountNonce(Address, String),
+}
+
 #[contracterror]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum WebAuthError {
     MissingArgument = 1,
+    HomeDomainMismatch = 2,
+    InvalidExpiration = 3,
+    ChallengeExpired = 4,
+    ValidityWindowViolation = 5,
+    UnexpectedArgument = 6,
+    NonceReused = 7,
+    DomainAccountMismatch = 8,
+    ClientDomainNotAllowed = 9,
+    MissingAccount = 10,
+    MissingWebAuthDomainAccount = 11,
+    InvalidMemo = 12,
+    ConflictingMemo = 13,
+    InvalidAddress = 14,
+    SameWasmHash = 15,
+    NetworkMismatch = 16,
+    WebAuthDomainMismatch = 17,
+    ArgumentTooLarge = 18,
+    AlreadyInitialized = 19,
+    ClassicAccountNotAllowed = 20,
+    UnknownArgumentKey = 21,
+    NoAccounts = 22,
+    AdminNotSet = 23,
+    ChallengeBindingMismatch = 24,
+    EmptyDomain = 25,
+    PolicyRejected = 26,
+    AccountNotRegistered = 27,
+    InvalidValidityWindow = 28,
+}
+
+/// A short description of the `WebAuthError` with discriminant `code`, for
+/// client tooling to show in place of the bare number
+pub fn error_message(code: u32) -> &'static str {
+    match code {
+        1 => "a required argument is missing",
+        2 => "home domain does not match",
+        3 => "expiration is not a valid timestamp",
+        4 => "challenge has expired",
+        5 => "expiration is outside the allowed validity window",
+        6 => "unexpected argument",
+        7 => "nonce has already been used",
+        8 => "web auth domain account does not match",
+        9 => "client domain is not allowed",
+        10 => "account is missing",
+        11 => "web auth domain account is missing",
+        12 => "memo is invalid",
+        13 => "memo conflicts with a muxed account",
+        14 => "address is invalid",
+        15 => "contract is already running this WASM",
+        16 => "challenge is for another network",
+        17 => "web auth domain does not match",
+        18 => "too many arguments, or an argument is too long",
+        19 => "contract is already initialized",
+        20 => "classic accounts are not allowed",
+        21 => "argument key is not recognized",
+        22 => "no accounts to verify",
+        23 => "admin is not set",
+        24 => "client data hash does not match the challenge",
+        25 => "domain is empty",
+        26 => "account was rejected by the policy contract",
+        27 => "account is not in the account registry",
+        28 => "minimum validity exceeds the maximum",
+        _ => "unknown error",
+    }
+}
+
+/// Whether challenges may, or must, name a client domain account
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ClientDomainPolicy {
+    Forbidden,
+    Optional,
+    Required,
+}
+
+/// Whether challenges must carry a `memo` attributing them to a sub-user
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MemoPolicy {
+    Optional,
+    Required,
+}
+
+/// Whether a verified account is a contract (`C...`) or a classic
+/// (`G...`) Stellar account
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AccountType {
+    Contract,
+    Classic,
+}
+
+impl AccountType {
+    fn of(address: &Address) -> AccountType {
+        if strkey::is_classic(address) {
+            AccountType::Classic
+        } else {
+            AccountType::Contract
+        }
+    }
+}
+
+/// A Stellar memo identifying a sub-user of a pooled account
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Memo {
+    Id(u64),
+    Text(String),
+}
+
+/// A typed SEP-45 challenge, accepted by `web_auth_verify_struct`
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Challenge {
+    pub account: Address,
+    pub home_domain: String,
+    pub web_auth_domain: String,
+    pub web_auth_domain_account: Address,
+    pub client_domain_account: Option<Address>,
+    pub nonce: String,
+    pub exp: u64,
+}
+
+/// The fields of a challenge `web_auth_verify_full` verified
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct WebAuthResult {
+    pub account: Address,
+    pub home_domain: String,
+    /// Whether the challenge named a client domain account, which then
+    /// authorized it too
+    pub client_domain_present: bool,
+    pub exp: u64,
+}
+
+/// A server account accepted alongside `web_auth_domain_account`, such as
+/// the previous one while a key rotation completes
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ServerAccount {
+    pub account: Address,
+    /// Unix timestamp, in seconds, after which the account is no longer
+    /// accepted
+    pub expires_at: Option<u64>,
+}
+
+/// Every configured parameter of a deployment, as returned by `config`
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct WebAuthConfig {
+    pub admin: Address,
+    pub home_domain: String,
+    pub web_auth_domain: String,
+    pub web_auth_domain_account: Address,
+    pub server_accounts: Vec<ServerAccount>,
+    pub network_id: Option<BytesN<32>>,
+    pub min_validity: u64,
+    pub max_validity: u64,
+    pub required_args: Vec<Symbol>,
+    pub strict_args: bool,
+    pub client_domain_policy: ClientDomainPolicy,
+    pub client_domains: Vec<Address>,
+    pub memo_policy: MemoPolicy,
+    pub allow_classic: bool,
+    pub clock_skew: u64,
+}
+
+/// A decoded challenge, common to both verification entrypoints
+struct Verification {
+    account: Address,
+    account_memo: Option<u64>,
+    memo: Option<Memo>,
+    home_domain: String,
+    web_auth_domain: Option<String>,
+    web_auth_domain_account: Option<Address>,
+    client_domain_account: Option<Address>,
+    nonce: String,
+    exp: u64,
+    /// Last ledger sequence the challenge may be verified in
+    max_ledger: Option<u32>,
+}
+
+/// The code version of this release, bumped with every released change
+pub const VERSION: u32 = 1;
+
+// Embedded in the WASM for deployment tooling. The version must stay in step
+// with `VERSION`.
+contractmeta!(key = "name", val = "web_auth");
+contractmeta!(key = "version", val = "1");
+contractmeta!(key = "sep", val = "45");
+
+/// Argument keys holding addresses that must authorize the challenge
+const ADDRESS_ARGS: [&str; 3] = [
+    "account",
+    "web_auth_domain_account",
+    "client_domain_account",
+];
+
+/// Parses a base-10 unsigned integer argument
+fn parse_u64(value: &String) -> Option<u64> {
+    let len = value.len() as usize;
+    if len == 0 || len > 20 {
+        return None;
+    }
+
+    let mut buf = [0u8; 20];
+    value.copy_into_slice(&mut buf[..len]);
+
+    let mut result: u64 = 0;
+    for digit in &buf[..len] {
+        if !digit.is_ascii_digit() {
+            return None;
+        }
+        result = result.checked_mul(10)?.checked_add((digit - b'0') as u64)?;
+    }
+    Some(result)
+}
+
+/// Parses a hex encoded 32-byte hash, in either case
+fn parse_hash(value: &String) -> Option<[u8; 32]> {
+    if value.len() != 64 {
+        return None;
+    }
+    let mut buf = [0u8; 64];
+    value.copy_into_slice(&mut buf);
+
+    let nibble = |digit: u8| (digit as char).to_digit(16).map(|n| n as u8);
+    let mut hash = [0u8; 32];
+    for (byte, pair) in hash.iter_mut().zip(buf.chunks(2)) {
+        *byte = nibble(pair[0])? << 4 | nibble(pair[1])?;
+    }
+    Some(hash)
+}
+
+/// The XDR encoding of the `ScVal::Map` holding the recognized entries of
+/// `args`
+fn canonical_bytes(env: &Env, args: &Map<Symbol, String>) -> Bytes {
+    let mut recognized = Map::new(env);
+    for key in RECOGNIZED_ARGS {
+        let key = Symbol::new(env, key);
+        if let Some(value) = args.get(key.clone()) {
+            recognized.set(key, value);
+        }
+    }
+    recognized.to_xdr(env)
+}
+
+/// The value a passkey client binds into its `client_data_hash` argument:
+/// the sha256 of the challenge's canonical bytes, leaving that argument out
+fn challenge_binding(env: &Env, args: &Map<Symbol, String>) -> BytesN<32> {
+    let mut args = args.clone();
+    args.remove(Symbol::new(env, "client_data_hash"));
+    env.crypto().sha256(&canonical_bytes(env, &args)).into()
+}
+
+/// Longest text a Stellar text memo holds, in bytes
+const MAX_TEXT_MEMO_LEN: usize = 28;
+
+/// Parses a `memo` argument, read as an id memo when it is all digits and a
+/// text memo otherwise. An empty value carries no memo.
+fn parse_memo(value: &String) -> Result<Option<Memo>, WebAuthError> {
+    let len = value.len() as usize;
+    if len == 0 {
+        return Ok(None);
+    }
+    if len > MAX_TEXT_MEMO_LEN {
+        return Err(WebAuthError::InvalidMemo);
+    }
+
+    let mut buf = [0u8; MAX_TEXT_MEMO_LEN];
+    value.copy_into_slice(&mut buf[..len]);
+    if buf[..len].iter().all(u8::is_ascii_digit) {
+        let Some(id) = parse_u64(value) else {
+            return Err(WebAuthError::InvalidMemo);
+        };
+        return Ok(Some(Memo::Id(id)));
+    }
+    Ok(Some(Memo::Text(value.clone())))
+}
+
+/// Length of a muxed account strkey
+const MUXED_ADDRESS_LEN: usize = 69;
+
+/// Whether `address` is a muxed account strkey (`M...`)
+fn is_muxed(address: &String) -> bool {
+    if address.len() as usize != MUXED_ADDRESS_LEN {
+        return false;
+    }
+
+    let mut buf = [0u8; MUXED_ADDRESS_LEN];
+    address.copy_into_slice(&mut buf);
+    buf[0] == b'M'
+}
+
+/// Requires the admin's authorization, failing with `AdminNotSet` rather
+/// than trapping if the entry is missing
+fn require_admin(env: &Env) -> Address {
+    let Some(admin) = env.storage().instance().get::<_, Address>(&DataKey::Admin) else {
+        panic_with_error!(env, WebAuthError::AdminNotSet);
+    };
+    admin.require_auth();
+    admin
+}
+
+trait Upgradable {
+    fn upgrade(e: Env, new_wasm_hash: BytesN<32>) -> Result<(), WebAuthError>;
+}
+
+#[contractimpl]
+impl Upgradable for WebAuthContract {
+    /// Replaces the contract code
+    fn upgrade(env: Env, new_wasm_hash: BytesN<32>) -> Result<(), WebAuthError> {
+        let admin = require_admin(&env);
+
+        if env.current_contract_address().executable()
+            == Some(Executable::Wasm(new_wasm_hash.clone()))
+        {
+            return Err(WebAuthError::SameWasmHash);
+        }
+
+        env.deployer()
+            .update_current_contract_wasm(new_wasm_hash.clone());
+        events::Upgraded {
+            new_wasm_hash,
+            admin,
+        }
+        .publish(&env);
+        Ok(())
+    }
+}
+
+/// Clock drift, in seconds, tolerated unless the admin configures otherwise
+const DEFAULT_CLOCK_SKEW: u64 = 30;
+
+/// Approximate time between ledgers, used to convert challenge lifetimes to TTLs
+const LEDGER_CLOSE_SECONDS: u64 = 5;
+
+const DAY_IN_LEDGERS: u32 = 17280;
+
+/// Login counters live for 30 days past an account's last verification
+const LOGIN_COUNT_BUMP_AMOUNT: u32 = 30 * DAY_IN_LEDGERS;
+const LOGIN_COUNT_LIFETIME_THRESHOLD: u32 = LOGIN_COUNT_BUMP_AMOUNT - DAY_IN_LEDGERS;
+
+/// Argument keys that make up a SEP-45 challenge
+const RECOGNIZED_ARGS: [&str; 12] = [
+    "account",
+    "account_memo",
+    "client_data_hash",
+    "client_domain",
+    "client_domain_account",
+    "exp",
+    "home_domain",
+    "max_ledger",
+    "memo",
+    "nonce",
+    "web_auth_domain",
+    "web_auth_domain_account",
+];
+
+/// Most entries a challenge's argument map may hold
+const MAX_ARGS: u32 = 16;
+
+/// Longest value, in bytes, a challenge argument may hold. A domain name is
+/// at most 253 bytes.
+const MAX_ARG_LEN: u32 = 256;
+
+/// `EnvelopeType::SorobanAuthorization`, the discriminant of the preimage the
+/// host hashes into an account's `__check_auth` payload
+const ENVELOPE_TYPE_SOROBAN_AUTHORIZATION: u32 = 9;
+
+/// The entry point challenges are authorized for
+const VERIFY_FN_NAME: &[u8] = b"web_auth_verify";
+
+/// The function a policy contract answers with whether an account may
+/// authenticate, taking the account and returning a `bool`
+pub const POLICY_FN_NAME: &str = "is_allowed";
+
+/// The function an account registry answers with whether it knows an
+/// account, taking the account and returning a `bool`
+pub const REGISTRY_FN_NAME: &str = "is_known";
+
+/// Argument keys every challenge must carry regardless of configuration
+const MANDATORY_ARGS: [&str; 5] = [
+    "account",
+    "exp",
+    "home_domain",
+    "nonce",
+    "web_auth_domain_account",
+];
+
+/// Where checks report a failed one, either stopping at the first or, for
+/// `diagnose`, collecting every one and carrying on
+struct Failures {
+    collected: Option<Vec<WebAuthError>>,
+}
+
+impl Failures {
+    fn first() -> Self {
+        Failures { collected: None }
+    }
+
+    fn collect(env: &Env) -> Self {
+        Failures {
+            collected: Some(Vec::new(env)),
+        }
+    }
+
+    /// Reports `error`, returning it only when stopping at the first
+    fn fail(&mut self, error: WebAuthError) -> Result<(), WebAuthError> {
+        let Some(collected) = &mut self.collected else {
+            return Err(error);
+        };
+        if !collected.contains(error) {
+            collected.push_back(error);
+        }
+        Ok(())
+    }
+}
+
+/// The error reported when challenge argument `key` is absent
+fn missing(env: &Env, key: &Symbol) -> WebAuthError {
+    if *key == Symbol::new(env, "account") {
+        WebAuthError::MissingAccount
+    } else if *key == Symbol::new(env, "web_auth_domain_account") {
+        WebAuthError::MissingWebAuthDomainAccount
+    } else {
+        WebAuthError::MissingArgument
+    }
+}
+
+fn client_domains(env: &Env) -> Vec<Address> {
+    env.storage()
+        .instance()
+        .get(&DataKey::ClientDomains)
+        .unwrap_or(Vec::new(env))
+}
+
+fn server_accounts(env: &Env) -> Vec<ServerAccount> {
+    env.storage()
+        .instance()
+        .get(&DataKey::ServerAccounts)
+        .unwrap_or(Vec::new(env))
+}
+
+/// Whether challenges may name `address` as the server account
+fn is_server_account(env: &Env, address: &Address) -> bool {
+    let current: Address = env
+        .storage()
+        .instance()
+        .get(&DataKey::WebAuthDomainAccount)
+        .unwrap();
+    let now = env.ledger().timestamp();
+    *address == current
+        || server_accounts(env).iter().any(|server| {
+            server.account == *address && server.expires_at.is_none_or(|expiry| now <= expiry)
+        })
+}
+
+fn required_args(env: &Env) -> Vec<Symbol> {
+    let required_args: Vec<Symbol> = env
+        .storage()
+        .instance()
+        .get(&DataKey::RequiredArgs)
+        .unwrap_or(Vec::new(env));
+
+    if required_args.is_empty() {
+        vec![
+            env,
+            Symbol::new(env, "account"),
+            Symbol::new(env, "web_auth_domain_account"),
+        ]
+    } else {
+        required_args
+    }
+}
+
+/// Checks the domain a challenge is for and the domain of the server that
+/// issued it, reporting which of the two disagrees with the configuration
+fn check_domains(
+    env: &Env,
+    home_domain: &String,
+    web_auth_domain: Option<&String>,
+    failures: &mut Failures,
+) -> Result<(), WebAuthError> {
+    let storage = env.storage().instance();
+    let expected_home_domain: String = storage.get(&DataKey::HomeDomain).unwrap();
+    if *home_domain != expected_home_domain {
+        failures.fail(WebAuthError::HomeDomainMismatch)?;
+    }
+
+    // Once a web auth domain is configured a challenge must name it, or a
+    // client could skip the check by leaving the argument out
+    let expected_web_auth_domain: String = storage.get(&DataKey::WebAuthDomain).unwrap();
+    match web_auth_domain {
+        Some(web_auth_domain) if *web_auth_domain != expected_web_auth_domain => {
+            failures.fail(WebAuthError::WebAuthDomainMismatch)?
+        }
+        None if !expected_web_auth_domain.is_empty() => {
+            failures.fail(WebAuthError::MissingArgument)?
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Checks a challenge hasn't expired and expires within the validity window
+fn check_expiry(env: &Env, challenge: &Verification) -> Result<(), WebAuthError> {
+    check_exp(env, challenge.exp)?;
+    if challenge
+        .max_ledger
+        .is_some_and(|max_ledger| env.ledger().sequence() > max_ledger)
+    {
+        return Err(WebAuthError::ChallengeExpired);
+    }
+    Ok(())
+}
+
+/// Checks an `exp` timestamp hasn't passed and falls within the validity
+/// window, allowing for clock skew
+fn check_exp(env: &Env, challenge_exp: u64) -> Result<(), WebAuthError> {
+    // A challenge within the skew allowance of a bound still meets it
+    let exp = challenge_exp.saturating_add(clock_skew(env));
+    let now = env.ledger().timestamp();
+    if exp < now {
+        return Err(WebAuthError::ChallengeExpired);
+    }
+    let max_validity: u64 = env.storage().instance().get(&DataKey::MaxValidity).unwrap();
+    let min_validity: u64 = env
+        .storage()
+        .instance()
+        .get(&DataKey::MinValidity)
+        .unwrap_or(0);
+    if exp - now < min_validity || challenge_exp.saturating_sub(now) > max_validity {
+        return Err(WebAuthError::ValidityWindowViolation);
+    }
+    Ok(())
+}
+
+fn clock_skew(env: &Env) -> u64 {
+    env.storage()
+        .instance()
+        .get(&DataKey::ClockSkew)
+        .unwrap_or(DEFAULT_CLOCK_SKEW)
+}
+
+/// The last ledger break glass mode stays on for, if it is on
+fn break_glass(env: &Env) -> Option<u32> {
+    env.storage()
+        .instance()
+        .get::<_, u32>(&DataKey::BreakGlass)
+        .filter(|until| env.ledger().sequence() <= *until)
+}
+
+/// Checks a decoded challenge against the configuration without requiring
+/// authorization or consuming its nonce
+///
+/// While break glass mode is on the domain and expiry checks are skipped.
+fn validate(env: &Env, challenge: &Verification) -> Result<(), WebAuthError> {
+    check_challenge(env, challenge, &mut Failures::first())
+}
+
+/// The checks `validate` applies, reporting each failure to `failures`
+fn check_challenge(
+    env: &Env,
+    challenge: &Verification,
+    failures: &mut Failures,
+) -> Result<(), WebAuthError> {
+    // Challenges signed for one network must not be replayable on another.
+    // Deployments constructed before the id was recorded have none to check.
+    let network_id: Option<BytesN<32>> = env.storage().instance().get(&DataKey::NetworkId);
+    if network_id.is_some_and(|network_id| network_id != env.ledger().network_id()) {
+        failures.fail(WebAuthError::NetworkMismatch)?;
+    }
+
+    if break_glass(env).is_none() {
+        check_domains(
+            env,
+            &challenge.home_domain,
+            challenge.web_auth_domain.as_ref(),
+            failures,
+        )?;
+        if let Err(error) = check_expiry(env, challenge) {
+            failures.fail(error)?;
+        }
+    } else if challenge.exp.saturating_sub(env.ledger().timestamp())
+        > WebAuthContract::max_validity(env.clone())
+    {
+        // A stale challenge may be accepted, but one expiring too far off
+        // would outlive what its nonce is remembered for
+        failures.fail(WebAuthError::ValidityWindowViolation)?;
+    }
+
+    // Checked here rather than when decoding so typed challenges, which
+    // carry no memo, can't skip it
+    if challenge.memo.is_none() && WebAuthContract::memo_policy(env.clone()) == MemoPolicy::Required
+    {
+        failures.fail(WebAuthError::MissingArgument)?;
+    }
+
+    // Each account's nonces are its own, so accounts can't use up each
+    // other's or crowd a shared set
+    let storage = env.storage().temporary();
+    if storage.has(&DataKey::AccountNonce(
+        challenge.account.clone(),
+        challenge.nonce.clone(),
+    )) || storage.has(&DataKey::Nonce(challenge.nonce.clone()))
+    {
+        failures.fail(WebAuthError::NonceReused)?;
+    }
+
+    if !WebAuthContract::allow_classic(env.clone())
+        && AccountType::of(&challenge.account) == AccountType::Classic
+    {
+        failures.fail(WebAuthError::ClassicAccountNotAllowed)?;
+    }
+
+    // Binding the server account stops a client substituting a key it
+    // controls, so a challenge can't leave it out either
+    match &challenge.web_auth_domain_account {
+        Some(address) if !is_server_account(env, address) => {
+            failures.fail(WebAuthError::DomainAccountMismatch)?
+        }
+        Some(_) => {}
+        None => failures.fail(WebAuthError::MissingWebAuthDomainAccount)?,
+    }
+
+    let client_domain_policy: ClientDomainPolicy = env
+        .storage()
+        .instance()
+        .get(&DataKey::ClientDomainPolicy)
+        .unwrap();
+    match (client_domain_policy, &challenge.client_domain_account) {
+        (ClientDomainPolicy::Required, None) => failures.fail(WebAuthError::MissingArgument)?,
+        (ClientDomainPolicy::Forbidden, Some(_)) => {
+            failures.fail(WebAuthError::ClientDomainNotAllowed)?
+        }
+        _ => {}
+    }
+
+    if let Some(address) = &challenge.client_domain_account {
+        let client_domains = client_domains(env);
+        if !client_domains.is_empty() && !client_domains.contains(address) {
+            failures.fail(WebAuthError::ClientDomainNotAllowed)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Requires `address` to authorize the invocation
+///
+/// This contract's own address is rejected, as the host would authorize it
+/// without any signature.
+fn authorize(env: &Env, address: &Address) -> Result<(), WebAuthError> {
+    if *address == env.current_contract_address() {
+        return Err(WebAuthError::InvalidAddress);
+    }
+    address.require_auth();
+    Ok(())
+}
+
+/// The addresses a challenge needs authorization from: the account, then
+/// any server and client domain accounts
+fn signing_addresses(env: &Env, challenge: Verification) -> Vec<Address> {
+    let mut addresses = Vec::from_array(env, [challenge.account]);
+    for address in [
+        challenge.web_auth_domain_account,
+        challenge.client_domain_account,
+    ]
+    .into_iter()
+    .flatten()
+    {
+        addresses.push_back(address);
+    }
+    addresses
+}
+
+fn verify(env: &Env, challenge: Verification) -> Result<Address, WebAuthError> {
+    let account = challenge.account.clone();
+    verify_accounts(env, challenge, vec![env, account.clone()])?;
+    Ok(account)
+}
+
+/// Verifies a challenge for every one of `accounts` at once, consuming its
+/// nonce a single time
+fn verify_accounts(
+    env: &Env,
+    challenge: Verification,
+    accounts: Vec<Address>,
+) -> Result<(), WebAuthError> {
+    validate(env, &challenge)?;
+    // Unknown accounts are turned away before anyone is asked to sign
+    for account in accounts.iter() {
+        check_registry(env, &account)?;
+    }
+
+    // Consumed nonces only need to outlive the challenge they belong to
+    let now = env.ledger().timestamp();
+    // A batch shares the nonce of the challenge's own account
+    let nonce_key = DataKey::AccountNonce(challenge.account.clone(), challenge.nonce);
+    // The challenge is accepted until its skew allowance runs out, and in
+    // break glass mode it may already have expired
+    let accepted_for = challenge
+        .exp
+        .saturating_add(clock_skew(env))
+        .saturating_sub(now);
+    // A nonce forgotten while its challenge is still accepted could be
+    // replayed, so a challenge outliving any entry is refused
+    let ttl = u32::try_from((accepted_for / LEDGER_CLOSE_SECONDS).saturating_add(1))
+        .ok()
+        .filter(|ttl| *ttl <= env.storage().max_ttl())
+        .ok_or(WebAuthError::ValidityWindowViolation)?;
+    env.storage().temporary().set(&nonce_key, &());
+    env.storage().temporary().extend_ttl(&nonce_key, ttl, ttl);
+
+    for account in accounts.iter() {
+        authorize(env, &account)?;
+    }
+    if let Some(address) = &challenge.web_auth_domain_account {
+        authorize(env, address)?;
+    }
+    // `require_auth` traps rather than returning when an address refuses, so
+    // a client domain that doesn't sign aborts the invocation with an auth
+    // error that no `WebAuthError` can replace
+    if let Some(address) = &challenge.client_domain_account {
+        authorize(env, address)?;
+    }
+    for account in accounts.iter() {
+        check_policy(env, &account)?;
+    }
+
+    for account in accounts.iter() {
+        record_login(env, &account);
+        if let Some(until) = break_glass(env) {
+            events::BreakGlassUsed {
+                account: account.clone(),
+                until,
+            }
+            .publish(env);
+        }
+        // The memo ID belongs to the challenge's own account
+        let account_memo = challenge
+            .account_memo
+            .filter(|_| account == challenge.account);
+        events::Verified {
+            account_type: AccountType::of(&account),
+            account,
+            account_memo,
+            memo: challenge.memo.clone(),
+            home_domain: challenge.home_domain.clone(),
+        }
+        .publish(env);
+    }
+
+    Ok(())
+}
+
+fn login_count(env: &Env, account: &Address) -> u64 {
+    env.storage()
+        .persistent()
+        .get(&DataKey::LoginCount(account.clone()))
+        .unwrap_or(0)
+}
+
+fn record_login(env: &Env, account: &Address) {
+    let key = DataKey::LoginCount(account.clone());
+    let storage = env.storage().persistent();
+    storage.set(&key, &login_count(env, account).saturating_add(1));
+    storage.extend_ttl(
+        &key,
+        LOGIN_COUNT_LIFETIME_THRESHOLD,
+        LOGIN_COUNT_BUMP_AMOUNT,
+    );
+}
+
+/// Asks the policy contract, if any, whether `account` may authenticate
+fn check_policy(env: &Env, account: &Address) -> Result<(), WebAuthError> {
+    let Some(policy) = env
+        .storage()
+        .instance()
+        .get::<_, Address>(&DataKey::PolicyContract)
+    else {
+        return Ok(());
+    };
+    let allowed: bool = env.invoke_contract(
+        &policy,
+        &Symbol::new(env, POLICY_FN_NAME),
+        vec![env, account.into_val(env)],
+    );
+    if !allowed {
+        return Err(WebAuthError::PolicyRejected);
+    }
+    Ok(())
+}
+
+/// Asks the account registry, if any, whether it knows `account`
+fn check_registry(env: &Env, account: &Address) -> Result<(), WebAuthError> {
+    let Some(registry) = env
+        .storage()
+        .instance()
+        .get::<_, Address>(&DataKey::AccountRegistry)
+    else {
+        return Ok(());
+    };
+    let known: bool = env.invoke_contract(
+        &registry,
+        &Symbol::new(env, REGISTRY_FN_NAME),
+        vec![env, account.into_val(env)],
+    );
+    if !known {
+        return Err(WebAuthError::AccountNotRegistered);
+    }
+    Ok(())
+}
+
+/// Decodes a challenge from its arguments, checking their presence and form
+fn decode(env: &Env, args: &Map<Symbol, String>) -> Result<Verification, WebAuthError> {
+    // Stopping at the first failure, every field decoded if none was found
+    Ok(decode_checked(env, args, &mut Failures::first())?.unwrap())
+}
+
+/// The checks `decode` applies, reporting each failure to `failures`
+///
+/// Returns the challenge whenever its fields could be decoded, even if
+/// other arguments failed, so it can still be validated.
+fn decode_checked(
+    env: &Env,
+    args: &Map<Symbol, String>,
+    failures: &mut Failures,
+) -> Result<Option<Verification>, WebAuthError> {
+    // Bounding the input first keeps the cost of rejecting it predictable
+    if args.len() > MAX_ARGS || args.values().iter().any(|value| value.len() > MAX_ARG_LEN) {
+        failures.fail(WebAuthError::ArgumentTooLarge)?;
+        return Ok(None);
+    }
+
+    for key in required_args(env).iter() {
+        if !args.contains_key(key.clone()) {
+            failures.fail(missing(env, &key))?;
+        }
+    }
+
+    if WebAuthContract::strict_args(env.clone()) {
+        let required_args = required_args(env);
+        for key in args.keys().iter() {
+            let recognized = RECOGNIZED_ARGS
+                .iter()
+                .any(|recognized| key == Symbol::new(env, recognized));
+            if !recognized && !required_args.contains(&key) {
+                failures.fail(WebAuthError::UnexpectedArgument)?;
+            }
+        }
+    }
+
+    let account = args.get(Symbol::new(env, "account"));
+    if account.is_none() {
+        failures.fail(WebAuthError::MissingAccount)?;
+    }
+
+    // A muxed account already carries its memo ID in the address
+    let account_memo = match args.get(Symbol::new(env, "account_memo")) {
+        Some(memo) => {
+            let memo = parse_u64(&memo);
+            if memo.is_none() {
+                failures.fail(WebAuthError::InvalidMemo)?;
+            } else if account.as_ref().is_some_and(is_muxed) {
+                failures.fail(WebAuthError::ConflictingMemo)?;
+            }
+            memo
+        }
+        None => None,
+    };
+
+    let memo = match args
+        .get(Symbol::new(env, "memo"))
+        .map(|memo| parse_memo(&memo))
+    {
+        Some(Err(error)) => {
+            failures.fail(error)?;
+            None
+        }
+        memo => memo.and_then(Result::ok).flatten(),
+    };
+
+    for key in ADDRESS_ARGS {
+        if let Some(address) = args.get(Symbol::new(env, key)) {
+            if !strkey::is_valid_address(&address) {
+                failures.fail(WebAuthError::InvalidAddress)?;
+            }
+        }
+    }
+    // Only the address arguments are authorized, so an address under any
+    // other key is one the signer may wrongly believe they're vouching for
+    let required_args = required_args(env);
+    for (key, value) in args.iter() {
+        let recognized = RECOGNIZED_ARGS
+            .iter()
+            .any(|recognized| key == Symbol::new(env, recognized));
+        if !recognized
+            && !required_args.contains(&key)
+            && (strkey::is_valid_address(&value) || is_muxed(&value))
+        {
+            failures.fail(WebAuthError::UnexpectedArgument)?;
+        }
+    }
+
+    let home_domain = args.get(Symbol::new(env, "home_domain"));
+    if home_domain.is_none() {
+        failures.fail(WebAuthError::MissingArgument)?;
+    }
+
+    let exp = match args.get(Symbol::new(env, "exp")) {
+        Some(exp) => {
+            let exp = parse_u64(&exp);
+            if exp.is_none() {
+                failures.fail(WebAuthError::InvalidExpiration)?;
+            }
+            exp
+        }
+        None => {
+            failures.fail(WebAuthError::MissingArgument)?;
+            None
+        }
+    };
+
+    let max_ledger = match args.get(Symbol::new(env, "max_ledger")) {
+        Some(max_ledger) => {
+            let max_ledger = parse_u64(&max_ledger).and_then(|max| max.try_into().ok());
+            if max_ledger.is_none() {
+                failures.fail(WebAuthError::InvalidExpiration)?;
+            }
+            max_ledger
+        }
+        None => None,
+    };
+
+    let nonce = args.get(Symbol::new(env, "nonce"));
+    if nonce.is_none() {
+        failures.fail(WebAuthError::MissingArgument)?;
+    }
+
+    if !args.contains_key(Symbol::new(env, "web_auth_domain_account")) {
+        failures.fail(WebAuthError::MissingWebAuthDomainAccount)?;
+    }
+
+    if let Some(client_data_hash) = args.get(Symbol::new(env, "client_data_hash")) {
+        if parse_hash(&client_data_hash) != Some(challenge_binding(env, args).to_array()) {
+            failures.fail(WebAuthError::ChallengeBindingMismatch)?;
+        }
+    }
+
+    // Only well-formed addresses can be decoded, the host trapping on others
+    let address_arg = |key| {
+        args.get(Symbol::new(env, key))
+            .filter(strkey::is_valid_address)
+            .map(|address| Address::from_string(&address))
+    };
+    let (Some(account), Some(home_domain), Some(exp), Some(nonce)) =
+        (address_arg("account"), home_domain, exp, nonce)
+    else {
+        return Ok(None);
+    };
+    Ok(Some(Verification {
+        account,
+        account_memo,
+        memo,
+        home_domain,
+        web_auth_domain: args.get(Symbol::new(env, "web_auth_domain")),
+        web_auth_domain_account: address_arg("web_auth_domain_account"),
+        client_domain_account: address_arg("client_domain_account"),
+        nonce,
+        exp,
+        max_ledger,
+    }))
 }
 
 #[contractimpl]
 impl WebAuthContract {
+    /// Arguments:
+    /// - admin: The address allowed to update the configuration
+    /// - home_domain: The home domain challenges must be issued for
+    /// - web_auth_domain: The domain of the server issuing challenges
+    /// - web_auth_domain_account: The server's SIGNING_KEY challenges must name
+    /// - max_validity: How far in the future, in seconds, a challenge may expire
+    /// - required_args: The argument keys every challenge must contain. When
+    ///   empty, `account` and `web_auth_domain_account` are required.
+    /// - client_domain_policy: Whether challenges may name a client domain account
+    #[allow(clippy::too_many_arguments)]
+    pub fn __constructor(
+        env: Env,
+        admin: Address,
+        home_domain: String,
+        web_auth_domain: String,
+        web_auth_domain_account: Address,
+        max_validity: u64,
+        required_args: Vec<Symbol>,
+        client_domain_policy: ClientDomainPolicy,
+    ) -> Result<(), WebAuthError> {
+        if env.storage().instance().has(&DataKey::Initialized) {
+            return Err(WebAuthError::AlreadyInitialized);
+        }
+
+        env.storage().instance().set(&DataKey::Initialized, &());
+        env.storage().instance().set(&DataKey::Admin, &admin);
+        env.storage()
+            .instance()
+            .set(&DataKey::NetworkId, &env.ledger().network_id());
+        env.storage()
+            .instance()
+            .set(&DataKey::HomeDomain, &home_domain);
+        env.storage()
+            .instance()
+            .set(&DataKey::WebAuthDomain, &web_auth_domain);
+        env.storage()
+            .instance()
+            .set(&DataKey::WebAuthDomainAccount, &web_auth_domain_account);
+        env.storage()
+            .instance()
+            .set(&DataKey::MaxValidity, &max_validity);
+        env.storage()
+            .instance()
+            .set(&DataKey::RequiredArgs, &required_args);
+        env.storage()
+            .instance()
+            .set(&DataKey::ClientDomainPolicy, &client_domain_policy);
+        Ok(())
+    }
+
+    /// The id of the network this deployment was constructed on, the only
+    /// one it verifies challenges for
+    pub fn network_id(env: Env) -> Option<BytesN<32>> {
+        env.storage().instance().get(&DataKey::NetworkId)
+    }
+
+    pub fn client_domain_policy(env: Env) -> ClientDomainPolicy {
+        env.storage()
+            .instance()
+            .get(&DataKey::ClientDomainPolicy)
+            .unwrap()
+    }
+
+    /// Whether classic (`G...`) accounts may authenticate, as well as
+    /// contract accounts. They may unless the admin turns this off.
+    pub fn allow_classic(env: Env) -> bool {
+        env.storage()
+            .instance()
+            .get(&DataKey::AllowClassic)
+            .unwrap_or(true)
+    }
+
+    pub fn set_allow_classic(env: Env, allow_classic: bool) {
+        require_admin(&env);
+
+        env.storage()
+            .instance()
+            .set(&DataKey::AllowClassic, &allow_classic);
+    }
+
+    /// Seconds a challenge may be past its `exp`, or short of the minimum
+    /// validity, and still be accepted
+    pub fn clock_skew(env: Env) -> u64 {
+        clock_skew(&env)
+    }
+
+    pub fn set_clock_skew(env: Env, clock_skew: u64) {
+        require_admin(&env);
+
+        env.storage()
+            .instance()
+            .set(&DataKey::ClockSkew, &clock_skew);
+    }
+
+    pub fn memo_policy(env: Env) -> MemoPolicy {
+        env.storage()
+            .instance()
+            .get(&DataKey::MemoPolicy)
+            .unwrap_or(MemoPolicy::Optional)
+    }
+
+    /// Sets whether challenges must carry a `memo`, as when a single account
+    /// is shared by many users. Typed challenges carry none, so while one is
+    /// required `web_auth_verify_struct` refuses them all.
+    pub fn set_memo_policy(env: Env, memo_policy: MemoPolicy) {
+        require_admin(&env);
+
+        env.storage()
+            .instance()
+            .set(&DataKey::MemoPolicy, &memo_policy);
+    }
+
+    /// The code version of the running WASM
+    pub fn version(_env: Env) -> u32 {
+        VERSION
+    }
+
+    /// The address allowed to reconfigure the contract, failing with
+    /// `AdminNotSet` if the entry is missing
+    pub fn admin(env: Env) -> Result<Address, WebAuthError> {
+        env.storage()
+            .instance()
+            .get(&DataKey::Admin)
+            .ok_or(WebAuthError::AdminNotSet)
+    }
+
+    /// Toggles rejecting challenges that carry arguments outside the recognized set
+    pub fn set_strict_args(env: Env, strict: bool) {
+        require_admin(&env);
+
+        env.storage().instance().set(&DataKey::StrictArgs, &strict);
+    }
+
+    pub fn strict_args(env: Env) -> bool {
+        env.storage()
+            .instance()
+            .get(&DataKey::StrictArgs)
+            .unwrap_or(false)
+    }
+
+    /// Turns on break glass mode for the next `duration_ledgers` ledgers,
+    /// for when the server's signing infrastructure is down
+    ///
+    /// While on, challenges are accepted whatever domains they name and
+    /// however stale their expiry, though it may still be no further off
+    /// than `max_validity`, and every verification publishes a
+    /// `BreakGlassUsed` event. Every address a challenge names
+    /// must still authorize it, and nonces still can't be reused.
+    pub fn break_glass(env: Env, duration_ledgers: u32) {
+        require_admin(&env);
+
+        env.storage().instance().set(
+            &DataKey::BreakGlass,
+            &env.ledger().sequence().saturating_add(duration_ledgers),
+        );
+    }
+
+    /// Turns break glass mode off before it expires
+    pub fn end_break_glass(env: Env) {
+        require_admin(&env);
+
+        env.storage().instance().remove(&DataKey::BreakGlass);
+    }
+
+    /// The last ledger break glass mode stays on for, while it is on
+    pub fn break_glass_until(env: Env) -> Option<u32> {
+        break_glass(&env)
+    }
+
+    /// Reads the whole configuration at once
+    pub fn config(env: Env) -> Result<WebAuthConfig, WebAuthError> {
+        Ok(WebAuthConfig {
+            admin: Self::admin(env.clone())?,
+            home_domain: Self::home_domain(env.clone()),
+            web_auth_domain: Self::web_auth_domain(env.clone()),
+            web_auth_domain_account: Self::web_auth_domain_account(env.clone()),
+            server_accounts: server_accounts(&env),
+            network_id: Self::network_id(env.clone()),
+            min_validity: Self::min_validity(env.clone()),
+            max_validity: Self::max_validity(env.clone()),
+            required_args: required_args(&env),
+            strict_args: Self::strict_args(env.clone()),
+            client_domain_policy: Self::client_domain_policy(env.clone()),
+            client_domains: client_domains(&env),
+            memo_policy: Self::memo_policy(env.clone()),
+            allow_classic: Self::allow_classic(env.clone()),
+            clock_skew: clock_skew(&env),
+        })
+    }
+
+    /// Describes the challenge arguments this deployment understands, mapped
+    /// to whether each one is required
+    pub fn manifest(env: Env) -> Map<Symbol, bool> {
+        let required_args = required_args(&env);
+
+        let mut manifest = Map::new(&env);
+        for key in RECOGNIZED_ARGS {
+            let required = MANDATORY_ARGS.contains(&key);
+            manifest.set(Symbol::new(&env, key), required);
+        }
+        for key in required_args.iter() {
+            manifest.set(key, true);
+        }
+        manifest
+    }
+
+    /// Bounds how far in the future, in seconds, a challenge may expire,
+    /// failing with `InvalidValidityWindow` if no expiry could meet both
+    pub fn set_validity_window(
+        env: Env,
+        min_validity: u64,
+        max_validity: u64,
+    ) -> Result<(), WebAuthError> {
+        require_admin(&env);
+        if min_validity > max_validity {
+            return Err(WebAuthError::InvalidValidityWindow);
+        }
+
+        env.storage()
+            .instance()
+            .set(&DataKey::MinValidity, &min_validity);
+        env.storage()
+            .instance()
+            .set(&DataKey::MaxValidity, &max_validity);
+        Ok(())
+    }
+
+    pub fn min_validity(env: Env) -> u64 {
+        env.storage()
+            .instance()
+            .get(&DataKey::MinValidity)
+            .unwrap_or(0)
+    }
+
+    pub fn max_validity(env: Env) -> u64 {
+        env.storage().instance().get(&DataKey::MaxValidity).unwrap()
+    }
+
+    pub fn home_domain(env: Env) -> String {
+        env.storage().instance().get(&DataKey::HomeDomain).unwrap()
+    }
+
+    pub fn set_home_domain(env: Env, home_domain: String) -> Result<(), WebAuthError> {
+        require_admin(&env);
+        if home_domain.is_empty() {
+            return Err(WebAuthError::EmptyDomain);
+        }
+
+        env.storage()
+            .instance()
+            .set(&DataKey::HomeDomain, &home_domain);
+        Ok(())
+    }
+
+    pub fn web_auth_domain(env: Env) -> String {
+        env.storage()
+            .instance()
+            .get(&DataKey::WebAuthDomain)
+            .unwrap()
+    }
+
+    /// Moves the server domain challenges must name, e.g. when the server
+    /// starts issuing challenges from a new host
+    pub fn set_web_auth_domain(env: Env, web_auth_domain: String) -> Result<(), WebAuthError> {
+        require_admin(&env);
+        if web_auth_domain.is_empty() {
+            return Err(WebAuthError::EmptyDomain);
+        }
+
+        env.storage()
+            .instance()
+            .set(&DataKey::WebAuthDomain, &web_auth_domain);
+        Ok(())
+    }
+
+    /// Replaces the home and web auth domains together, so challenges are
+    /// never checked against one old and one new domain
+    pub fn set_domains(
+        env: Env,
+        home_domain: String,
+        web_auth_domain: String,
+    ) -> Result<(), WebAuthError> {
+        require_admin(&env);
+        if home_domain.is_empty() || web_auth_domain.is_empty() {
+            return Err(WebAuthError::EmptyDomain);
+        }
+
+        env.storage()
+            .instance()
+            .set(&DataKey::HomeDomain, &home_domain);
+        env.storage()
+            .instance()
+            .set(&DataKey::WebAuthDomain, &web_auth_domain);
+        events::DomainsSet {
+            home_domain,
+            web_auth_domain,
+        }
+        .publish(&env);
+        Ok(())
+    }
+
+    pub fn web_auth_domain_account(env: Env) -> Address {
+        env.storage()
+            .instance()
+            .get(&DataKey::WebAuthDomainAccount)
+            .unwrap()
+    }
+
+    /// Rotates the server account challenges must name, e.g. after the server
+    /// changes its SIGNING_KEY
+    pub fn set_web_auth_domain_account(env: Env, web_auth_domain_account: Address) {
+        require_admin(&env);
+
+        env.storage()
+            .instance()
+            .set(&DataKey::WebAuthDomainAccount, &web_auth_domain_account);
+    }
+
+    /// Accepts challenges naming `account` as the server account as well as
+    /// `web_auth_domain_account`, until `expires_at` if set. Adding an
+    /// account already accepted replaces its expiry.
+    ///
+    /// On rotating the SIGNING_KEY, adding the previous account with a short
+    /// expiry keeps challenges already issued with it valid.
+    pub fn add_server_account(env: Env, account: Address, expires_at: Option<u64>) {
+        require_admin(&env);
+
+        let mut servers = server_accounts(&env);
+        if let Some(index) = servers.iter().position(|server| server.account == account) {
+            servers.remove(index as u32);
+        }
+        servers.push_back(ServerAccount {
+            account,
+            expires_at,
+        });
+        env.storage()
+            .instance()
+            .set(&DataKey::ServerAccounts, &servers);
+    }
+
+    /// Stops accepting challenges naming `account`, unless it is the current
+    /// `web_auth_domain_account`
+    pub fn expire_server_account(env: Env, account: Address) {
+        require_admin(&env);
+
+        let mut servers = server_accounts(&env);
+        if let Some(index) = servers.iter().position(|server| server.account == account) {
+            servers.remove(index as u32);
+            env.storage()
+                .instance()
+                .set(&DataKey::ServerAccounts, &servers);
+        }
+    }
+
+    pub fn server_accounts(env: Env) -> Vec<ServerAccount> {
+        server_accounts(&env)
+    }
+
+    /// Permits challenges naming `client_domain_account`. While no client
+    /// domains are permitted, any client domain account is accepted.
+    pub fn add_client_domain(env: Env, client_domain_account: Address) {
+        require_admin(&env);
+
+        let mut client_domains = client_domains(&env);
+        if !client_domains.contains(&client_domain_account) {
+            client_domains.push_back(client_domain_account);
+            env.storage()
+                .instance()
+                .set(&DataKey::ClientDomains, &client_domains);
+        }
+    }
+
+    pub fn remove_client_domain(env: Env, client_domain_account: Address) {
+        require_admin(&env);
+
+        let mut client_domains = client_domains(&env);
+        if let Some(index) = client_domains.first_index_of(&client_domain_account) {
+            client_domains.remove(index);
+            env.storage()
+                .instance()
+                .set(&DataKey::ClientDomains, &client_domains);
+        }
+    }
+
+    pub fn client_domains(env: Env) -> Vec<Address> {
+        client_domains(&env)
+    }
+
+    pub fn required_args(env: Env) -> Vec<Symbol> {
+        required_args(&env)
+    }
+
+    /// Sets the contract whose `is_allowed(account) -> bool` each account
+    /// must pass once a challenge has passed the standard checks, or clears
+    /// it. A rejection fails with `PolicyRejected`, and a policy contract
+    /// that traps fails the verification with it.
+    pub fn set_policy_contract(env: Env, policy: Option<Address>) {
+        require_admin(&env);
+
+        match policy {
+            Some(policy) => env
+                .storage()
+                .instance()
+                .set(&DataKey::PolicyContract, &policy),
+            None => env.storage().instance().remove(&DataKey::PolicyContract),
+        }
+    }
+
+    pub fn policy_contract(env: Env) -> Option<Address> {
+        env.storage().instance().get(&DataKey::PolicyContract)
+    }
+
+    /// Limits verification to the accounts `registry` knows, by its
+    /// `is_known(account) -> bool`, e.g. those deployed by one factory.
+    /// Clearing it serves any account. Each account is looked up before
+    /// its authorization is required, an unknown one failing with
+    /// `AccountNotRegistered`.
+    pub fn set_account_registry(env: Env, registry: Option<Address>) {
+        require_admin(&env);
+
+        match registry {
+            Some(registry) => env
+                .storage()
+                .instance()
+                .set(&DataKey::AccountRegistry, &registry),
+            None => env.storage().instance().remove(&DataKey::AccountRegistry),
+        }
+    }
+
+    pub fn account_registry(env: Env) -> Option<Address> {
+        env.storage().instance().get(&DataKey::AccountRegistry)
+    }
+
+    /// Replaces the argument keys every challenge must contain, each of
+    /// which must be one this contract recognizes. When empty, `account`
+    /// and `web_auth_domain_account` are required.
+    pub fn set_required_args(env: Env, required_args: Vec<Symbol>) -> Result<(), WebAuthError> {
+        require_admin(&env);
+
+        for key in required_args.iter() {
+            if !RECOGNIZED_ARGS
+                .iter()
+                .any(|recognized| key == Symbol::new(&env, recognized))
+            {
+                return Err(WebAuthError::UnknownArgumentKey);
+            }
+        }
+        env.storage()
+            .instance()
+            .set(&DataKey::RequiredArgs, &required_args);
+        events::RequiredArgsSet { required_args }.publish(&env);
+        Ok(())
+    }
+
     /// Verifies the client is authorized to authenticate with the server
     ///
     /// Arguments:
     /// - account: The client account address
+    /// - account_memo: A memo ID identifying a user of a pooled account (optional)
+    /// - memo: A text or id memo identifying a user of a pooled account,
+    ///   required when the memo policy is `Required` (optional)
     /// - home_domain: The home domain
     /// - web_auth_domain: The server's domain
     /// - web_auth_domain_account: The server's SIGNING_KEY
     /// - client_domain: The client domain (optional)
     /// - client_domain_account: The client domain's SIGNING_KEY (optional)
     /// - nonce: A random string generated by the server to prevent replay attacks
-    pub fn web_auth_verify(env: Env, args: Map<Symbol, String>) -> Result<(), WebAuthError> {
-        if let Some(address) = args.get(Symbol::new(&env, "account")) {
-            let addr = Address::from_string(&address);
-            addr.require_auth();
-        } else {
-            return Err(WebAuthError::MissingArgument);
+    /// - exp: The unix timestamp after which the challenge is no longer valid
+    /// - max_ledger: The last ledger sequence the challenge is valid in (optional)
+    /// - client_data_hash: The hex `challenge_binding` of the other arguments,
+    ///   for passkey clients binding the challenge into their client data (optional)
+    ///
+    /// Which arguments must be present is configured at construction. Returns
+    /// the authenticated `account` address.
+    pub fn web_auth_verify(env: Env, args: Map<Symbol, String>) -> Result<Address, WebAuthError> {
+        verify(&env, decode(&env, &args)?)
+    }
+
+    /// How many times `account` has been verified, counting each account of
+    /// a batch. Counters of accounts that stop logging in expire with their
+    /// storage.
+    pub fn login_count(env: Env, account: Address) -> u64 {
+        login_count(&env, &account)
+    }
+
+    /// Verifies one challenge for several accounts together, e.g. for a batch
+    /// login, checking the shared fields once
+    ///
+    /// Every one of `accounts` must authorize this invocation, as must the
+    /// challenge's own `account`, which is added to them if missing. The
+    /// server and any client domain authorize once for the whole batch.
+    pub fn web_auth_verify_many(
+        env: Env,
+        args: Map<Symbol, String>,
+        accounts: Vec<Address>,
+    ) -> Result<(), WebAuthError> {
+        if accounts.is_empty() {
+            return Err(WebAuthError::NoAccounts);
         }
+        let challenge = decode(&env, &args)?;
 
-        if let Some(web_auth_domain_account) =
-            args.get(Symbol::new(&env, "web_auth_domain_account"))
-        {
-            let addr = Address::from_string(&web_auth_domain_account);
-            addr.require_auth();
-        } else {
-            return Err(WebAuthError::MissingArgument);
+        let mut batch = vec![&env, challenge.account.clone()];
+        for account in accounts.iter() {
+            if batch.contains(&account) {
+                continue;
+            }
+            if !Self::allow_classic(env.clone())
+                && AccountType::of(&account) == AccountType::Classic
+            {
+                return Err(WebAuthError::ClassicAccountNotAllowed);
+            }
+            batch.push_back(account);
         }
+        verify_accounts(&env, challenge, batch)
+    }
 
-        if let Some(client_domain_account) = args.get(Symbol::new(&env, "client_domain_account")) {
-            let addr = Address::from_string(&client_domain_account);
-            addr.require_auth();
+    /// Verifies a challenge as `web_auth_verify` does, returning a summary of
+    /// the verified fields instead of just the account
+    pub fn web_auth_verify_full(
+        env: Env,
+        args: Map<Symbol, String>,
+    ) -> Result<WebAuthResult, WebAuthError> {
+        let challenge = decode(&env, &args)?;
+        let home_domain = challenge.home_domain.clone();
+        let client_domain_present = challenge.client_domain_account.is_some();
+        let exp = challenge.exp;
+        Ok(WebAuthResult {
+            account: verify(&env, challenge)?,
+            home_domain,
+            client_domain_present,
+            exp,
+        })
+    }
+
+    /// Verifies a challenge as `web_auth_verify` does, reporting for each
+    /// recognized optional argument whether the verified challenge carried it
+    pub fn web_auth_verify_reporting(
+        env: Env,
+        args: Map<Symbol, String>,
+    ) -> Result<Map<Symbol, bool>, WebAuthError> {
+        verify(&env, decode(&env, &args)?)?;
+
+        let mut report = Map::new(&env);
+        for key in RECOGNIZED_ARGS {
+            if !MANDATORY_ARGS.contains(&key) {
+                let key = Symbol::new(&env, key);
+                report.set(key.clone(), args.contains_key(key));
+            }
         }
+        Ok(report)
+    }
 
-        Ok(())
+    /// The XDR encoding of the `ScVal::Map` holding the recognized entries
+    /// of `args`, for clients to hash or sign
+    ///
+    /// Map entries are always ordered by key, so the encoding is the same
+    /// whatever order the arguments were inserted in. Arguments this
+    /// contract doesn't recognize are left out.
+    pub fn canonical_challenge_bytes(env: Env, args: Map<Symbol, String>) -> Bytes {
+        canonical_bytes(&env, &args)
+    }
+
+    /// The sha256 of `canonical_challenge_bytes(args)`, leaving out any
+    /// `client_data_hash`. A challenge carrying a `client_data_hash` is only
+    /// accepted when it is this value, hex encoded.
+    pub fn challenge_binding(env: Env, args: Map<Symbol, String>) -> BytesN<32> {
+        challenge_binding(&env, &args)
+    }
+
+    /// The payload the host passes to an account's `__check_auth` when it
+    /// authorizes `web_auth_verify(args)` with the given credential `nonce`
+    /// and `signature_expiration_ledger`
+    ///
+    /// This is the sha256 of the `HashIdPreimage::SorobanAuthorization` for a
+    /// root invocation of this contract with no sub-invocations. `Account`
+    /// signers sign it tagged with `WEB_AUTH_TAG` rather than as-is.
+    pub fn expected_payload(
+        env: Env,
+        args: Map<Symbol, String>,
+        nonce: i64,
+        signature_expiration_ledger: u32,
+    ) -> BytesN<32> {
+        let mut preimage = Bytes::new(&env);
+        preimage.extend_from_array(&ENVELOPE_TYPE_SOROBAN_AUTHORIZATION.to_be_bytes());
+        preimage.append(&env.ledger().network_id().into());
+        preimage.extend_from_array(&nonce.to_be_bytes());
+        preimage.extend_from_array(&signature_expiration_ledger.to_be_bytes());
+        // SorobanAuthorizedFunction::ContractFn
+        preimage.extend_from_array(&0u32.to_be_bytes());
+        // Both encodings are an ScVal, so drop their 4-byte type discriminant
+        // to get at the bare ScAddress
+        let address = env.current_contract_address().to_xdr(&env);
+        preimage.append(&address.slice(4..));
+        preimage.extend_from_array(&(VERIFY_FN_NAME.len() as u32).to_be_bytes());
+        preimage.extend_from_slice(VERIFY_FN_NAME);
+        let padding = (4 - VERIFY_FN_NAME.len() % 4) % 4;
+        preimage.extend_from_slice(&[0u8; 3][..padding]);
+        // A single argument, the map itself
+        preimage.extend_from_array(&1u32.to_be_bytes());
+        preimage.append(&args.to_xdr(&env));
+        // No sub-invocations
+        preimage.extend_from_array(&0u32.to_be_bytes());
+        env.crypto().sha256(&preimage).into()
+    }
+
+    /// Whether a challenge expiring at `exp` would currently pass the expiry
+    /// checks, allowing for clock skew, so wallets can fetch a fresh
+    /// challenge before prompting the user to sign a stale one
+    ///
+    /// Always true while break glass mode is on, as expiry isn't checked.
+    pub fn is_fresh(env: Env, exp: u64) -> bool {
+        break_glass(&env).is_some() || check_exp(&env, exp).is_ok()
+    }
+
+    /// Runs every check `web_auth_verify` applies to `args` without requiring
+    /// any authorization or consuming the nonce, so clients can reject a
+    /// malformed challenge before signing it
+    pub fn validate_challenge(env: Env, args: Map<Symbol, String>) -> Result<(), WebAuthError> {
+        validate(&env, &decode(&env, &args)?)
+    }
+
+    /// Every check `validate_challenge` fails `args` on, rather than just the
+    /// first, so a client can fix them all at once. Empty when `args` would
+    /// pass.
+    ///
+    /// A challenge whose account, `home_domain`, `exp` or `nonce` can't be
+    /// decoded isn't checked against the configuration, so those failures
+    /// are only reported once the arguments are fixed. An oversized
+    /// challenge reports `ArgumentTooLarge` alone.
+    pub fn diagnose(env: Env, args: Map<Symbol, String>) -> Vec<WebAuthError> {
+        let mut failures = Failures::collect(&env);
+        if let Ok(Some(challenge)) = decode_checked(&env, &args, &mut failures) {
+            let _ = check_challenge(&env, &challenge, &mut failures);
+        }
+        failures.collected.unwrap()
+    }
+
+    /// The addresses `web_auth_verify` requires authorization from for `args`,
+    /// in the order it requires them: the account, then any server and
+    /// client domain accounts
+    pub fn auth_addresses(
+        env: Env,
+        args: Map<Symbol, String>,
+    ) -> Result<Vec<Address>, WebAuthError> {
+        Ok(signing_addresses(&env, decode(&env, &args)?))
+    }
+
+    /// Checks `args` as `validate_challenge` does, and that the account
+    /// registry knows the account, returning the addresses that must then
+    /// authorize it in the order `auth_addresses` gives
+    ///
+    /// Nothing is authorized and the nonce is left unconsumed, so a caller
+    /// composing this into its own flow must require the authorizations and
+    /// see the challenge is only used once. The policy contract, consulted
+    /// after authorization, isn't asked.
+    pub fn validate_and_collect(
+        env: Env,
+        args: Map<Symbol, String>,
+    ) -> Result<Vec<Address>, WebAuthError> {
+        let challenge = decode(&env, &args)?;
+        validate(&env, &challenge)?;
+        check_registry(&env, &challenge.account)?;
+        Ok(signing_addresses(&env, challenge))
+    }
+
+    /// Requires `account` to authorize the invocation, as `web_auth_verify`
+    /// does for each address a challenge names
+    pub fn require_auth_for(env: Env, account: Address) -> Result<(), WebAuthError> {
+        authorize(&env, &account)
+    }
+
+    /// Verifies a typed challenge, applying the same checks as
+    /// `web_auth_verify` apart from the configurable argument requirements
+    pub fn web_auth_verify_struct(env: Env, challenge: Challenge) -> Result<Address, WebAuthError> {
+        verify(
+            &env,
+            Verification {
+                account: challenge.account,
+                account_memo: None,
+                memo: None,
+                home_domain: challenge.home_domain,
+                web_auth_domain: Some(challenge.web_auth_domain),
+                web_auth_domain_account: Some(challenge.web_auth_domain_account),
+                client_domain_account: challenge.client_domain_account,
+                nonce: challenge.nonce,
+                exp: challenge.exp,
+                max_ledger: None,
+            },
+        )
     }
 }
+
+#[cfg(test)]
+mod test;
+#[cfg(test)]
+mod test_account;
+#[cfg(test)]
+mod test_fuzz;
+#[cfg(test)]
+mod test_spec;
@@ -0,0 +1,77 @@
+use soroban_sdk::{Address, String};
+
+/// Length of an account (`G...`) or contract (`C...`) strkey
+const ADDRESS_LEN: usize = 56;
+
+/// Length once base32 decoded: version byte, 32-byte key and 2-byte checksum
+const DECODED_LEN: usize = 35;
+
+const VERSION_ACCOUNT: u8 = 6 << 3;
+const VERSION_CONTRACT: u8 = 2 << 3;
+
+fn base32_value(c: u8) -> Option<u8> {
+    match c {
+        b'A'..=b'Z' => Some(c - b'A'),
+        b'2'..=b'7' => Some(c - b'2' + 26),
+        _ => None,
+    }
+}
+
+/// CRC16-XModem, the checksum strkeys carry
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0;
+    for byte in data {
+        crc ^= (*byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// Checks `address` is a well-formed account or contract strkey, which
+/// `Address::from_string` would otherwise trap on
+pub fn is_valid_address(address: &String) -> bool {
+    if address.len() as usize != ADDRESS_LEN {
+        return false;
+    }
+
+    let mut encoded = [0u8; ADDRESS_LEN];
+    address.copy_into_slice(&mut encoded);
+
+    let mut decoded = [0u8; DECODED_LEN];
+    let mut bits: u32 = 0;
+    let mut bit_count = 0;
+    let mut o = 0;
+    for c in encoded {
+        let Some(value) = base32_value(c) else {
+            return false;
+        };
+        bits = (bits << 5) | value as u32;
+        bit_count += 5;
+        if bit_count >= 8 {
+            bit_count -= 8;
+            decoded[o] = (bits >> bit_count) as u8;
+            o += 1;
+        }
+    }
+
+    let version = decoded[0];
+    if version != VERSION_ACCOUNT && version != VERSION_CONTRACT {
+        return false;
+    }
+
+    let checksum = u16::from_le_bytes([decoded[DECODED_LEN - 2], decoded[DECODED_LEN - 1]]);
+    checksum == crc16(&decoded[..DECODED_LEN - 2])
+}
+
+/// Whether `address` is a classic account (`G...`) rather than a contract
+pub fn is_classic(address: &Address) -> bool {
+    let mut encoded = [0u8; ADDRESS_LEN];
+    address.to_string().copy_into_slice(&mut encoded);
+    encoded[0] == b'G'
+}
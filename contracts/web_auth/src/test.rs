@@ -0,0 +1,2420 @@
+#![cfg(test)]
+extern crate std;
+
+use std::{
+    string::ToString,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use rand::{rngs::OsRng, RngCore};
+use soroban_sdk::{
+    contract, contractimpl,
+    testutils::{
+        storage::{Persistent as _, Temporary as _},
+        Address as _, Events as _, Ledger, MockAuth, MockAuthInvoke,
+    },
+    vec,
+    xdr::{
+        AccountId, Limited, Limits, PublicKey, ReadXdr, ScAddress, ScMetaEntry, ScMetaV0, ScVal,
+        ToXdr, Uint256,
+    },
+    Address, BytesN, Env, Event, Executable, IntoVal, InvokeError, Map, String, Symbol, TryFromVal,
+    Val, Vec,
+};
+
+use crate::{
+    error_message,
+    events::{BreakGlassUsed, DomainsSet, RequiredArgsSet, Upgraded, Verified},
+    AccountType, Challenge, ClientDomainPolicy, DataKey, Memo, MemoPolicy, ServerAccount,
+    WebAuthConfig, WebAuthContract, WebAuthContractClient, WebAuthError, WebAuthResult,
+    LEDGER_CLOSE_SECONDS, VERSION,
+};
+
+/// Produced by `stellar contract build`
+const WEB_AUTH_WASM: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/../target/wasm32v1-none/release/web_auth.wasm"
+);
+
+const HOME_DOMAIN: &str = "localhost:8080";
+const MAX_VALIDITY: u64 = 900;
+const NOW: u64 = 1_700_000_000;
+
+static NONCE: AtomicU64 = AtomicU64::new(0);
+
+struct Setup {
+    env: Env,
+    admin: Address,
+    client: WebAuthContractClient<'static>,
+    account: Address,
+    server: Address,
+}
+
+fn setup(required_args: &[&str]) -> Setup {
+    setup_with_policy(required_args, ClientDomainPolicy::Optional)
+}
+
+fn setup_with_policy(required_args: &[&str], client_domain_policy: ClientDomainPolicy) -> Setup {
+    let env = Env::default();
+    env.mock_all_auths();
+    env.ledger().set_timestamp(NOW);
+
+    let required_args: Vec<Symbol> =
+        Vec::from_iter(&env, required_args.iter().map(|key| Symbol::new(&env, key)));
+    let admin = Address::generate(&env);
+    let home_domain = String::from_str(&env, HOME_DOMAIN);
+    let web_auth_domain = String::from_str(&env, HOME_DOMAIN);
+    let server = Address::generate(&env);
+    let contract_id = env.register(
+        WebAuthContract,
+        (
+            admin.clone(),
+            home_domain,
+            web_auth_domain,
+            server.clone(),
+            MAX_VALIDITY,
+            required_args,
+            client_domain_policy,
+        ),
+    );
+    let client = WebAuthContractClient::new(&env, &contract_id);
+    // Tests pin exact expiry boundaries; `test_clock_skew` covers the allowance
+    client.set_clock_skew(&0);
+
+    Setup {
+        account: Address::generate(&env),
+        server,
+        env,
+        admin,
+        client,
+    }
+}
+
+fn challenge(setup: &Setup) -> Map<Symbol, String> {
+    let env = &setup.env;
+    let mut args = Map::new(env);
+    args.set(Symbol::new(env, "account"), setup.account.to_string());
+    args.set(
+        Symbol::new(env, "home_domain"),
+        String::from_str(env, HOME_DOMAIN),
+    );
+    args.set(
+        Symbol::new(env, "web_auth_domain"),
+        String::from_str(env, HOME_DOMAIN),
+    );
+    args.set(
+        Symbol::new(env, "web_auth_domain_account"),
+        setup.server.to_string(),
+    );
+    args.set(
+        Symbol::new(env, "nonce"),
+        String::from_str(env, &NONCE.fetch_add(1, Ordering::Relaxed).to_string()),
+    );
+    args.set(Symbol::new(env, "exp"), String::from_str(env, "1700000300"));
+    args
+}
+
+#[test]
+fn test_verify() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+
+    let account = setup.client.web_auth_verify(&challenge(&setup));
+    assert_eq!(account, setup.account);
+    assert_eq!(env.auths().len(), 2);
+}
+
+#[test]
+fn test_login_count() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+    assert_eq!(setup.client.login_count(&setup.account), 0);
+
+    for count in 1..=3 {
+        setup.client.web_auth_verify(&challenge(&setup));
+        assert_eq!(setup.client.login_count(&setup.account), count);
+    }
+    // Rejected challenges aren't counted
+    let mut args = challenge(&setup);
+    args.set(Symbol::new(env, "exp"), String::from_str(env, "1"));
+    assert!(setup.client.try_web_auth_verify(&args).is_err());
+    assert_eq!(setup.client.login_count(&setup.account), 3);
+    assert_eq!(setup.client.login_count(&setup.server), 0);
+
+    env.as_contract(&setup.client.address, || {
+        assert_eq!(
+            env.storage()
+                .persistent()
+                .get_ttl(&DataKey::LoginCount(setup.account.clone())),
+            30 * 17280
+        );
+    });
+}
+
+#[contract]
+struct AllowingPolicy;
+
+#[contractimpl]
+impl AllowingPolicy {
+    pub fn is_allowed(_env: Env, _account: Address) -> bool {
+        true
+    }
+}
+
+#[contract]
+struct RejectingPolicy;
+
+#[contractimpl]
+impl RejectingPolicy {
+    pub fn is_allowed(_env: Env, _account: Address) -> bool {
+        false
+    }
+}
+
+#[test]
+fn test_policy_contract() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+    let allowing = env.register(AllowingPolicy, ());
+    let rejecting = env.register(RejectingPolicy, ());
+    assert_eq!(setup.client.policy_contract(), None);
+
+    setup.client.set_policy_contract(&Some(rejecting.clone()));
+    assert_eq!(env.auths()[0].0, setup.admin);
+    assert_eq!(setup.client.policy_contract(), Some(rejecting));
+    let args = challenge(&setup);
+    assert_eq!(
+        setup.client.try_web_auth_verify(&args),
+        Err(Ok(WebAuthError::PolicyRejected))
+    );
+    // A rejected challenge is left unconsumed and uncounted
+    assert_eq!(setup.client.login_count(&setup.account), 0);
+
+    setup.client.set_policy_contract(&Some(allowing));
+    assert_eq!(setup.client.web_auth_verify(&args), setup.account);
+    assert_eq!(setup.client.login_count(&setup.account), 1);
+
+    setup.client.set_policy_contract(&None);
+    assert_eq!(setup.client.policy_contract(), None);
+    setup.client.web_auth_verify(&challenge(&setup));
+}
+
+#[contract]
+struct MockRegistry;
+
+#[contractimpl]
+impl MockRegistry {
+    pub fn register(env: Env, account: Address) {
+        env.storage().instance().set(&account, &());
+    }
+
+    pub fn is_known(env: Env, account: Address) -> bool {
+        env.storage().instance().has(&account)
+    }
+}
+
+#[test]
+fn test_account_registry() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+    let registry = env.register(MockRegistry, ());
+    let registry_client = MockRegistryClient::new(env, &registry);
+    assert_eq!(setup.client.account_registry(), None);
+
+    setup.client.set_account_registry(&Some(registry.clone()));
+    assert_eq!(env.auths()[0].0, setup.admin);
+    assert_eq!(setup.client.account_registry(), Some(registry));
+    let args = challenge(&setup);
+    assert_eq!(
+        setup.client.try_web_auth_verify(&args),
+        Err(Ok(WebAuthError::AccountNotRegistered))
+    );
+    assert!(env.auths().is_empty());
+
+    registry_client.register(&setup.account);
+    assert_eq!(setup.client.web_auth_verify(&args), setup.account);
+
+    // Every account of a batch must be known
+    let other = Address::generate(env);
+    assert_eq!(
+        setup
+            .client
+            .try_web_auth_verify_many(&challenge(&setup), &vec![env, other.clone()]),
+        Err(Ok(WebAuthError::AccountNotRegistered))
+    );
+    registry_client.register(&other);
+    setup
+        .client
+        .web_auth_verify_many(&challenge(&setup), &vec![env, other]);
+
+    setup.client.set_account_registry(&None);
+    assert_eq!(setup.client.account_registry(), None);
+    setup.client.web_auth_verify(&challenge(&setup));
+}
+
+#[test]
+fn test_reinitialize() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+
+    let result = env.as_contract(&setup.client.address, || {
+        WebAuthContract::__constructor(
+            env.clone(),
+            Address::generate(env),
+            String::from_str(env, "attacker.example"),
+            String::from_str(env, "attacker.example"),
+            Address::generate(env),
+            MAX_VALIDITY,
+            Vec::new(env),
+            ClientDomainPolicy::Optional,
+        )
+    });
+    assert_eq!(result, Err(WebAuthError::AlreadyInitialized));
+    assert_eq!(setup.client.admin(), setup.admin);
+    assert_eq!(
+        setup.client.web_auth_verify(&challenge(&setup)),
+        setup.account
+    );
+}
+
+#[test]
+fn test_canonical_challenge_bytes() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+    let args = challenge(&setup);
+
+    // The same arguments inserted in reverse order
+    let mut reversed: Map<Symbol, String> = Map::new(env);
+    let entries: std::vec::Vec<_> = args.iter().collect();
+    for (key, value) in entries.into_iter().rev() {
+        reversed.set(key, value);
+    }
+    let canonical = setup.client.canonical_challenge_bytes(&args);
+    assert_eq!(setup.client.canonical_challenge_bytes(&reversed), canonical);
+    assert_eq!(canonical, args.clone().to_xdr(env));
+
+    let mut extra = args.clone();
+    extra.set(
+        Symbol::new(env, "unrecognized"),
+        String::from_str(env, "ignored"),
+    );
+    assert_eq!(setup.client.canonical_challenge_bytes(&extra), canonical);
+}
+
+#[test]
+fn test_verify_full() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+    let expected = WebAuthResult {
+        account: setup.account.clone(),
+        home_domain: String::from_str(env, HOME_DOMAIN),
+        client_domain_present: false,
+        exp: 1700000300,
+    };
+    assert_eq!(
+        setup.client.web_auth_verify_full(&challenge(&setup)),
+        expected
+    );
+
+    let mut args = challenge(&setup);
+    args.set(
+        Symbol::new(env, "client_domain"),
+        String::from_str(env, "wallet.example.com"),
+    );
+    args.set(
+        Symbol::new(env, "client_domain_account"),
+        Address::generate(env).to_string(),
+    );
+    args.set(Symbol::new(env, "exp"), String::from_str(env, "1700000600"));
+    let expected = WebAuthResult {
+        client_domain_present: true,
+        exp: 1700000600,
+        ..expected
+    };
+    assert_eq!(setup.client.web_auth_verify_full(&args), expected);
+    assert_eq!(env.auths().len(), 3);
+}
+
+#[test]
+fn test_verify_reporting() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+    let report = |present: &[&str]| {
+        let mut report = Map::new(env);
+        for key in [
+            "account_memo",
+            "client_data_hash",
+            "client_domain",
+            "client_domain_account",
+            "max_ledger",
+            "memo",
+            "web_auth_domain",
+        ] {
+            report.set(Symbol::new(env, key), present.contains(&key));
+        }
+        report
+    };
+
+    assert_eq!(
+        setup.client.web_auth_verify_reporting(&challenge(&setup)),
+        report(&["web_auth_domain"])
+    );
+
+    let mut args = challenge(&setup);
+    args.set(
+        Symbol::new(env, "client_domain"),
+        String::from_str(env, "wallet.example.com"),
+    );
+    args.set(
+        Symbol::new(env, "client_domain_account"),
+        Address::generate(env).to_string(),
+    );
+    args.set(Symbol::new(env, "memo"), String::from_str(env, "42"));
+    assert_eq!(
+        setup.client.web_auth_verify_reporting(&args),
+        report(&[
+            "client_domain",
+            "client_domain_account",
+            "memo",
+            "web_auth_domain",
+        ])
+    );
+
+    let mut args = challenge(&setup);
+    args.set(Symbol::new(env, "account_memo"), String::from_str(env, "7"));
+    assert_eq!(
+        setup.client.web_auth_verify_reporting(&args),
+        report(&["account_memo", "web_auth_domain"])
+    );
+
+    // Nothing is reported for a challenge that fails verification
+    let mut args = challenge(&setup);
+    args.set(
+        Symbol::new(env, "memo"),
+        String::from_str(env, "99999999999999999999"),
+    );
+    assert_eq!(
+        setup.client.try_web_auth_verify_reporting(&args),
+        Err(Ok(WebAuthError::InvalidMemo))
+    );
+}
+
+#[test]
+fn test_default_required_args() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+    assert_eq!(
+        setup.client.required_args(),
+        vec![
+            env,
+            Symbol::new(env, "account"),
+            Symbol::new(env, "web_auth_domain_account"),
+        ]
+    );
+
+    let mut args = challenge(&setup);
+    args.remove(Symbol::new(env, "web_auth_domain_account"));
+    assert_eq!(
+        setup.client.try_web_auth_verify(&args),
+        Err(Ok(WebAuthError::MissingWebAuthDomainAccount))
+    );
+}
+
+#[test]
+fn test_custom_required_args() {
+    let setup = setup(&[
+        "account",
+        "web_auth_domain_account",
+        "nonce",
+        "client_domain_account",
+    ]);
+    let env = &setup.env;
+    assert_eq!(setup.client.required_args().len(), 4);
+
+    let mut args = challenge(&setup);
+    assert_eq!(
+        setup.client.try_web_auth_verify(&args),
+        Err(Ok(WebAuthError::MissingArgument))
+    );
+
+    let client_domain = Address::generate(env);
+    args.set(
+        Symbol::new(env, "client_domain_account"),
+        client_domain.to_string(),
+    );
+    setup.client.web_auth_verify(&args);
+    assert_eq!(env.auths().len(), 3);
+}
+
+#[test]
+fn test_home_domain_mismatch() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+    assert_eq!(
+        setup.client.home_domain(),
+        String::from_str(env, HOME_DOMAIN)
+    );
+
+    let mut args = challenge(&setup);
+    args.set(
+        Symbol::new(env, "home_domain"),
+        String::from_str(env, "evil.example"),
+    );
+    assert_eq!(
+        setup.client.try_web_auth_verify(&args),
+        Err(Ok(WebAuthError::HomeDomainMismatch))
+    );
+}
+
+#[test]
+fn test_set_home_domain() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+    let new_domain = String::from_str(env, "example.com");
+
+    assert_eq!(
+        setup.client.try_set_home_domain(&String::from_str(env, "")),
+        Err(Ok(WebAuthError::EmptyDomain))
+    );
+    assert_eq!(
+        setup.client.home_domain(),
+        String::from_str(env, HOME_DOMAIN)
+    );
+
+    setup.client.set_home_domain(&new_domain);
+    assert_eq!(env.auths()[0].0, setup.admin);
+    assert_eq!(setup.client.home_domain(), new_domain);
+    assert_eq!(
+        setup.client.try_web_auth_verify(&challenge(&setup)),
+        Err(Ok(WebAuthError::HomeDomainMismatch))
+    );
+
+    let mut args = challenge(&setup);
+    args.set(Symbol::new(env, "home_domain"), new_domain);
+    setup.client.web_auth_verify(&args);
+}
+
+#[test]
+fn test_web_auth_domain_mismatch() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+    assert_eq!(
+        setup.client.web_auth_domain(),
+        String::from_str(env, HOME_DOMAIN)
+    );
+
+    let mut args = challenge(&setup);
+    args.set(
+        Symbol::new(env, "web_auth_domain"),
+        String::from_str(env, "evil.example"),
+    );
+    assert_eq!(
+        setup.client.try_web_auth_verify(&args),
+        Err(Ok(WebAuthError::WebAuthDomainMismatch))
+    );
+
+    // Leaving the domain out doesn't skip the check
+    args.remove(Symbol::new(env, "web_auth_domain"));
+    assert_eq!(
+        setup.client.try_web_auth_verify(&args),
+        Err(Ok(WebAuthError::MissingArgument))
+    );
+
+    let mut challenge = typed_challenge(&setup);
+    challenge.web_auth_domain = String::from_str(env, "evil.example");
+    assert_eq!(
+        setup.client.try_web_auth_verify_struct(&challenge),
+        Err(Ok(WebAuthError::WebAuthDomainMismatch))
+    );
+}
+
+#[test]
+fn test_domain_pair() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+    let web_auth_domain = String::from_str(env, "auth.example.com");
+    setup.client.set_web_auth_domain(&web_auth_domain);
+
+    let domains = |home_domain: &str, web_auth_domain: &str| {
+        let mut args = challenge(&setup);
+        args.set(
+            Symbol::new(env, "home_domain"),
+            String::from_str(env, home_domain),
+        );
+        args.set(
+            Symbol::new(env, "web_auth_domain"),
+            String::from_str(env, web_auth_domain),
+        );
+        setup.client.try_validate_challenge(&args)
+    };
+    assert_eq!(domains(HOME_DOMAIN, "auth.example.com"), Ok(Ok(())));
+    assert_eq!(
+        domains("evil.example", "auth.example.com"),
+        Err(Ok(WebAuthError::HomeDomainMismatch))
+    );
+    assert_eq!(
+        domains(HOME_DOMAIN, HOME_DOMAIN),
+        Err(Ok(WebAuthError::WebAuthDomainMismatch))
+    );
+    // Swapping the two domains is caught at the first one checked
+    assert_eq!(
+        domains("auth.example.com", HOME_DOMAIN),
+        Err(Ok(WebAuthError::HomeDomainMismatch))
+    );
+}
+
+#[test]
+fn test_set_web_auth_domain() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+    let new_domain = String::from_str(env, "auth.example.com");
+
+    assert_eq!(
+        setup
+            .client
+            .try_set_web_auth_domain(&String::from_str(env, "")),
+        Err(Ok(WebAuthError::EmptyDomain))
+    );
+    assert_eq!(
+        setup.client.web_auth_domain(),
+        String::from_str(env, HOME_DOMAIN)
+    );
+
+    setup.client.set_web_auth_domain(&new_domain);
+    assert_eq!(env.auths()[0].0, setup.admin);
+    assert_eq!(setup.client.web_auth_domain(), new_domain);
+    assert_eq!(
+        setup.client.try_web_auth_verify(&challenge(&setup)),
+        Err(Ok(WebAuthError::WebAuthDomainMismatch))
+    );
+
+    let mut args = challenge(&setup);
+    args.set(Symbol::new(env, "web_auth_domain"), new_domain);
+    setup.client.web_auth_verify(&args);
+}
+
+#[test]
+fn test_set_domains() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+    let old_domain = String::from_str(env, HOME_DOMAIN);
+    let home_domain = String::from_str(env, "example.com");
+    let web_auth_domain = String::from_str(env, "auth.example.com");
+    let with_domains = |home: &String, web_auth: &String| {
+        let mut args = challenge(&setup);
+        args.set(Symbol::new(env, "home_domain"), home.clone());
+        args.set(Symbol::new(env, "web_auth_domain"), web_auth.clone());
+        args
+    };
+
+    // A rejected rotation leaves both domains as they were
+    let empty = String::from_str(env, "");
+    assert_eq!(
+        setup.client.try_set_domains(&home_domain, &empty),
+        Err(Ok(WebAuthError::EmptyDomain))
+    );
+    assert_eq!(
+        setup.client.try_set_domains(&empty, &web_auth_domain),
+        Err(Ok(WebAuthError::EmptyDomain))
+    );
+    assert_eq!(setup.client.home_domain(), old_domain);
+    assert_eq!(setup.client.web_auth_domain(), old_domain);
+
+    setup.client.set_domains(&home_domain, &web_auth_domain);
+    assert_eq!(env.auths()[0].0, setup.admin);
+    let set = DomainsSet {
+        home_domain: home_domain.clone(),
+        web_auth_domain: web_auth_domain.clone(),
+    };
+    assert_eq!(
+        env.events().all(),
+        vec![
+            env,
+            (setup.client.address.clone(), set.topics(env), set.data(env))
+        ]
+    );
+    assert_eq!(setup.client.home_domain(), home_domain);
+    assert_eq!(setup.client.web_auth_domain(), web_auth_domain);
+
+    // Only the new pair verifies, never a mix of old and new
+    assert_eq!(
+        setup
+            .client
+            .try_web_auth_verify(&with_domains(&old_domain, &old_domain)),
+        Err(Ok(WebAuthError::HomeDomainMismatch))
+    );
+    assert_eq!(
+        setup
+            .client
+            .try_web_auth_verify(&with_domains(&home_domain, &old_domain)),
+        Err(Ok(WebAuthError::WebAuthDomainMismatch))
+    );
+    assert_eq!(
+        setup
+            .client
+            .try_web_auth_verify(&with_domains(&old_domain, &web_auth_domain)),
+        Err(Ok(WebAuthError::HomeDomainMismatch))
+    );
+    setup
+        .client
+        .web_auth_verify(&with_domains(&home_domain, &web_auth_domain));
+}
+
+#[test]
+fn test_validate_challenge() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+    let args = challenge(&setup);
+
+    assert_eq!(setup.client.try_validate_challenge(&args), Ok(Ok(())));
+    assert!(env.auths().is_empty());
+
+    // Validating leaves the nonce unconsumed for the real verification
+    assert_eq!(setup.client.web_auth_verify(&args), setup.account);
+    assert_eq!(
+        setup.client.try_validate_challenge(&args),
+        Err(Ok(WebAuthError::NonceReused))
+    );
+}
+
+#[test]
+fn test_validate_challenge_errors() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+
+    let mut missing_account = challenge(&setup);
+    missing_account.remove(Symbol::new(env, "account"));
+    let mut wrong_domain = challenge(&setup);
+    wrong_domain.set(
+        Symbol::new(env, "home_domain"),
+        String::from_str(env, "evil.example"),
+    );
+    let expired = challenge_with_exp(&setup, "1699999999");
+    let bad_exp = challenge_with_exp(&setup, "soon");
+
+    for (args, error) in [
+        (missing_account, WebAuthError::MissingAccount),
+        (wrong_domain, WebAuthError::HomeDomainMismatch),
+        (expired, WebAuthError::ChallengeExpired),
+        (bad_exp, WebAuthError::InvalidExpiration),
+    ] {
+        assert_eq!(setup.client.try_validate_challenge(&args), Err(Ok(error)));
+        assert_eq!(setup.client.try_web_auth_verify(&args), Err(Ok(error)));
+    }
+}
+
+#[test]
+fn test_diagnose() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+    assert_eq!(setup.client.diagnose(&challenge(&setup)), vec![env]);
+
+    let mut args = challenge_with_exp(&setup, "1699999999");
+    args.remove(Symbol::new(env, "web_auth_domain_account"));
+    args.set(
+        Symbol::new(env, "home_domain"),
+        String::from_str(env, "evil.example"),
+    );
+    args.set(
+        Symbol::new(env, "web_auth_domain"),
+        String::from_str(env, "evil.example"),
+    );
+    args.set(
+        Symbol::new(env, "account_memo"),
+        String::from_str(env, "none"),
+    );
+    assert_eq!(
+        setup.client.diagnose(&args),
+        vec![
+            env,
+            WebAuthError::MissingWebAuthDomainAccount,
+            WebAuthError::InvalidMemo,
+            WebAuthError::HomeDomainMismatch,
+            WebAuthError::WebAuthDomainMismatch,
+            WebAuthError::ChallengeExpired,
+        ]
+    );
+    // Verifying stops at the first of them
+    assert_eq!(
+        setup.client.try_web_auth_verify(&args),
+        Err(Ok(WebAuthError::MissingWebAuthDomainAccount))
+    );
+    assert!(env.auths().is_empty());
+
+    // Without an account there is no challenge to check the configuration of
+    let mut args = challenge_with_exp(&setup, "soon");
+    args.remove(Symbol::new(env, "account"));
+    assert_eq!(
+        setup.client.diagnose(&args),
+        vec![
+            env,
+            WebAuthError::MissingAccount,
+            WebAuthError::InvalidExpiration,
+        ]
+    );
+
+    // Diagnosing leaves the nonce unconsumed, which verifying then does
+    let args = challenge(&setup);
+    setup.client.web_auth_verify(&args);
+    assert_eq!(
+        setup.client.diagnose(&args),
+        vec![env, WebAuthError::NonceReused]
+    );
+}
+
+#[test]
+fn test_require_auth_for() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+
+    setup.client.require_auth_for(&setup.account);
+    assert_eq!(env.auths()[0].0, setup.account);
+
+    assert_eq!(
+        setup.client.try_require_auth_for(&setup.client.address),
+        Err(Ok(WebAuthError::InvalidAddress))
+    );
+
+    let mut args = challenge(&setup);
+    args.set(
+        Symbol::new(env, "account"),
+        setup.client.address.to_string(),
+    );
+    assert_eq!(
+        setup.client.try_web_auth_verify(&args),
+        Err(Ok(WebAuthError::InvalidAddress))
+    );
+}
+
+#[test]
+fn test_network_id() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+    assert_eq!(setup.client.network_id(), Some(env.ledger().network_id()));
+    setup.client.web_auth_verify(&challenge(&setup));
+
+    env.ledger().set_network_id([1; 32]);
+    assert_eq!(
+        setup.client.try_web_auth_verify(&challenge(&setup)),
+        Err(Ok(WebAuthError::NetworkMismatch))
+    );
+    assert_eq!(
+        setup.client.try_validate_challenge(&challenge(&setup)),
+        Err(Ok(WebAuthError::NetworkMismatch))
+    );
+}
+
+fn challenge_with_exp(setup: &Setup, exp: &str) -> Map<Symbol, String> {
+    let mut args = challenge(setup);
+    args.set(
+        Symbol::new(&setup.env, "exp"),
+        String::from_str(&setup.env, exp),
+    );
+    args
+}
+
+#[test]
+fn test_expiration() {
+    let setup = setup(&[]);
+
+    setup
+        .client
+        .web_auth_verify(&challenge_with_exp(&setup, "1700000000"));
+    setup
+        .client
+        .web_auth_verify(&challenge_with_exp(&setup, "1700000900"));
+    assert_eq!(
+        setup
+            .client
+            .try_web_auth_verify(&challenge_with_exp(&setup, "1699999999")),
+        Err(Ok(WebAuthError::ChallengeExpired))
+    );
+    assert_eq!(
+        setup
+            .client
+            .try_web_auth_verify(&challenge_with_exp(&setup, "1700000901")),
+        Err(Ok(WebAuthError::ValidityWindowViolation))
+    );
+}
+
+#[test]
+fn test_break_glass() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+    env.ledger().set_sequence_number(100);
+    let relaxed = |nonce: &str| {
+        let mut args = challenge_with_exp(&setup, "1699999999");
+        args.set(
+            Symbol::new(env, "home_domain"),
+            String::from_str(env, "outage.example.com"),
+        );
+        args.set(Symbol::new(env, "nonce"), String::from_str(env, nonce));
+        args
+    };
+    assert_eq!(
+        setup.client.try_web_auth_verify(&relaxed("1")),
+        Err(Ok(WebAuthError::HomeDomainMismatch))
+    );
+
+    setup.client.break_glass(&10);
+    assert_eq!(setup.client.break_glass_until(), Some(110));
+    assert_eq!(setup.client.web_auth_verify(&relaxed("1")), setup.account);
+    let used = BreakGlassUsed {
+        account: setup.account.clone(),
+        until: 110,
+    };
+    assert_eq!(
+        env.events().all().slice(..1),
+        vec![
+            env,
+            (
+                setup.client.address.clone(),
+                used.topics(env),
+                used.data(env)
+            )
+        ]
+    );
+    // The account must still authorize, and nonces stay single-use
+    assert_eq!(env.auths()[0].0, setup.account);
+    assert_eq!(
+        setup.client.try_web_auth_verify(&relaxed("1")),
+        Err(Ok(WebAuthError::NonceReused))
+    );
+
+    env.ledger().set_sequence_number(111);
+    assert_eq!(setup.client.break_glass_until(), None);
+    assert_eq!(
+        setup.client.try_web_auth_verify(&relaxed("2")),
+        Err(Ok(WebAuthError::HomeDomainMismatch))
+    );
+    setup.client.web_auth_verify(&challenge(&setup));
+    assert_eq!(env.events().all().len(), 1);
+
+    // Nor may a challenge expire further off than the validity window allows
+    setup.client.break_glass(&10);
+    setup.client.web_auth_verify(&challenge_with_exp(
+        &setup,
+        &(NOW + MAX_VALIDITY).to_string(),
+    ));
+    for exp in [NOW + MAX_VALIDITY + 1, NOW + 5 * (1 << 32) + 5, u64::MAX] {
+        let args = challenge_with_exp(&setup, &exp.to_string());
+        assert_eq!(
+            setup.client.try_web_auth_verify(&args),
+            Err(Ok(WebAuthError::ValidityWindowViolation))
+        );
+    }
+}
+
+#[test]
+fn test_end_break_glass() {
+    let setup = setup(&[]);
+    setup.client.break_glass(&10);
+    setup.client.end_break_glass();
+
+    assert_eq!(setup.client.break_glass_until(), None);
+    assert_eq!(
+        setup
+            .client
+            .try_web_auth_verify(&challenge_with_exp(&setup, "1699999999")),
+        Err(Ok(WebAuthError::ChallengeExpired))
+    );
+}
+
+#[test]
+fn test_max_ledger() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+    let sequence = 100;
+    env.ledger().set_sequence_number(sequence);
+    let with_max_ledger = |max_ledger: u32| {
+        let mut args = challenge(&setup);
+        args.set(
+            Symbol::new(env, "max_ledger"),
+            String::from_str(env, &max_ledger.to_string()),
+        );
+        args
+    };
+
+    setup.client.web_auth_verify(&with_max_ledger(sequence));
+    setup
+        .client
+        .web_auth_verify(&with_max_ledger(sequence + 10));
+    assert_eq!(
+        setup
+            .client
+            .try_web_auth_verify(&with_max_ledger(sequence - 1)),
+        Err(Ok(WebAuthError::ChallengeExpired))
+    );
+
+    // Both bounds must hold when the challenge carries both
+    let mut args = with_max_ledger(sequence + 10);
+    args.set(Symbol::new(env, "exp"), String::from_str(env, "1699999999"));
+    assert_eq!(
+        setup.client.try_web_auth_verify(&args),
+        Err(Ok(WebAuthError::ChallengeExpired))
+    );
+    let args = with_max_ledger(sequence + 10);
+    env.ledger().set_sequence_number(sequence + 11);
+    assert_eq!(
+        setup.client.try_web_auth_verify(&args),
+        Err(Ok(WebAuthError::ChallengeExpired))
+    );
+
+    let mut args = challenge(&setup);
+    args.set(
+        Symbol::new(env, "max_ledger"),
+        String::from_str(env, "4294967296"),
+    );
+    assert_eq!(
+        setup.client.try_web_auth_verify(&args),
+        Err(Ok(WebAuthError::InvalidExpiration))
+    );
+}
+
+#[test]
+fn test_malformed_expiration() {
+    let setup = setup(&[]);
+
+    for exp in ["", "soon", "-1", "99999999999999999999999"] {
+        assert_eq!(
+            setup
+                .client
+                .try_web_auth_verify(&challenge_with_exp(&setup, exp)),
+            Err(Ok(WebAuthError::InvalidExpiration))
+        );
+    }
+}
+
+#[test]
+fn test_verified_event() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+
+    setup.client.web_auth_verify(&challenge(&setup));
+    let verified = Verified {
+        account: setup.account.clone(),
+        account_memo: None,
+        memo: None,
+        home_domain: String::from_str(env, HOME_DOMAIN),
+        account_type: AccountType::Contract,
+    };
+    assert_eq!(
+        env.events().all(),
+        vec![
+            env,
+            (
+                setup.client.address.clone(),
+                verified.topics(env),
+                verified.data(env)
+            )
+        ]
+    );
+
+    let mut args = challenge(&setup);
+    args.remove(Symbol::new(env, "account"));
+    assert!(setup.client.try_web_auth_verify(&args).is_err());
+    assert_eq!(env.events().all(), vec![env]);
+}
+
+#[test]
+fn test_unexpected_argument() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+    let mut args = challenge(&setup);
+    args.set(
+        Symbol::new(env, "smuggled"),
+        String::from_str(env, "payload"),
+    );
+
+    assert!(!setup.client.strict_args());
+    setup.client.web_auth_verify(&args);
+
+    setup.client.set_strict_args(&true);
+    assert_eq!(
+        setup.client.try_web_auth_verify(&args),
+        Err(Ok(WebAuthError::UnexpectedArgument))
+    );
+    setup.client.web_auth_verify(&challenge(&setup));
+}
+
+#[test]
+fn test_nonce_reused() {
+    let setup = setup(&[]);
+    let args = challenge(&setup);
+
+    setup.client.web_auth_verify(&args);
+    assert_eq!(
+        setup.client.try_web_auth_verify(&args),
+        Err(Ok(WebAuthError::NonceReused))
+    );
+    setup.client.web_auth_verify(&challenge(&setup));
+}
+
+#[test]
+fn test_nonce_per_account() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+    let other = Address::generate(env);
+    let args = challenge(&setup);
+    let mut other_args = args.clone();
+    other_args.set(Symbol::new(env, "account"), other.to_string());
+
+    // The same nonce is separate for each account
+    setup.client.web_auth_verify(&args);
+    assert_eq!(setup.client.web_auth_verify(&other_args), other);
+    for args in [&args, &other_args] {
+        assert_eq!(
+            setup.client.try_web_auth_verify(args),
+            Err(Ok(WebAuthError::NonceReused))
+        );
+    }
+
+    // Kept only as long as the challenge could be used
+    let nonce = args.get(Symbol::new(env, "nonce")).unwrap();
+    let key = DataKey::AccountNonce(setup.account.clone(), nonce.clone());
+    env.as_contract(&setup.client.address, || {
+        assert_eq!(
+            env.storage().temporary().get_ttl(&key),
+            300 / LEDGER_CLOSE_SECONDS as u32 + 1
+        );
+    });
+
+    // A nonce consumed before they were kept per account stays used
+    let args = challenge(&setup);
+    let nonce = args.get(Symbol::new(env, "nonce")).unwrap();
+    env.as_contract(&setup.client.address, || {
+        env.storage().temporary().set(&DataKey::Nonce(nonce), &());
+    });
+    assert_eq!(
+        setup.client.try_web_auth_verify(&args),
+        Err(Ok(WebAuthError::NonceReused))
+    );
+}
+
+#[test]
+fn test_invalid_validity_window() {
+    let setup = setup(&[]);
+
+    assert_eq!(
+        setup.client.try_set_validity_window(&601, &600),
+        Err(Ok(WebAuthError::InvalidValidityWindow))
+    );
+    assert_eq!(setup.client.min_validity(), 0);
+    assert_eq!(setup.client.max_validity(), MAX_VALIDITY);
+
+    // A window of a single expiry is still valid
+    setup.client.set_validity_window(&600, &600);
+    assert_eq!(setup.client.min_validity(), 600);
+    assert_eq!(setup.client.max_validity(), 600);
+}
+
+#[test]
+fn test_config() {
+    let setup = setup_with_policy(&["account", "memo"], ClientDomainPolicy::Required);
+    let env = &setup.env;
+    let previous = Address::generate(env);
+    let wallet = Address::generate(env);
+    setup.client.set_validity_window(&60, &600);
+    setup.client.set_strict_args(&true);
+    setup.client.add_client_domain(&wallet);
+    setup.client.set_memo_policy(&MemoPolicy::Required);
+    setup.client.set_allow_classic(&false);
+    setup.client.set_clock_skew(&45);
+    setup.client.add_server_account(&previous, &Some(NOW + 60));
+
+    assert_eq!(
+        setup.client.config(),
+        WebAuthConfig {
+            admin: setup.admin.clone(),
+            home_domain: String::from_str(env, HOME_DOMAIN),
+            web_auth_domain: String::from_str(env, HOME_DOMAIN),
+            web_auth_domain_account: setup.server.clone(),
+            server_accounts: vec![
+                env,
+                ServerAccount {
+                    account: previous,
+                    expires_at: Some(NOW + 60),
+                }
+            ],
+            network_id: Some(env.ledger().network_id()),
+            min_validity: 60,
+            max_validity: 600,
+            required_args: vec![env, Symbol::new(env, "account"), Symbol::new(env, "memo")],
+            strict_args: true,
+            client_domain_policy: ClientDomainPolicy::Required,
+            client_domains: vec![env, wallet],
+            memo_policy: MemoPolicy::Required,
+            allow_classic: false,
+            clock_skew: 45,
+        }
+    );
+}
+
+#[test]
+fn test_manifest() {
+    let setup = setup(&[
+        "account",
+        "web_auth_domain_account",
+        "client_domain",
+        "memo",
+    ]);
+    let env = &setup.env;
+
+    let expected = [
+        ("account", true),
+        ("account_memo", false),
+        ("client_data_hash", false),
+        ("client_domain", true),
+        ("client_domain_account", false),
+        ("exp", true),
+        ("home_domain", true),
+        ("max_ledger", false),
+        ("memo", true),
+        ("nonce", true),
+        ("web_auth_domain", false),
+        ("web_auth_domain_account", true),
+    ];
+    let mut manifest = Map::new(env);
+    for (key, required) in expected {
+        manifest.set(Symbol::new(env, key), required);
+    }
+    assert_eq!(setup.client.manifest(), manifest);
+}
+
+fn hex(bytes: &[u8]) -> std::string::String {
+    bytes
+        .iter()
+        .map(|byte| std::format!("{byte:02x}"))
+        .collect()
+}
+
+#[test]
+fn test_client_data_hash() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+    let mut args = challenge(&setup);
+    let binding = setup.client.challenge_binding(&args);
+    let canonical = setup.client.canonical_challenge_bytes(&args);
+    assert_eq!(binding, env.crypto().sha256(&canonical).to_bytes());
+
+    args.set(
+        Symbol::new(env, "client_data_hash"),
+        String::from_str(env, &hex(&binding.to_array())),
+    );
+    // The binding leaves the hash itself out, so adding it changes nothing
+    assert_eq!(setup.client.challenge_binding(&args), binding);
+    assert_eq!(setup.client.web_auth_verify(&args), setup.account);
+
+    // Upper case hex is as good as lower case
+    let mut args = challenge(&setup);
+    let binding = setup.client.challenge_binding(&args);
+    args.set(
+        Symbol::new(env, "client_data_hash"),
+        String::from_str(env, &hex(&binding.to_array()).to_uppercase()),
+    );
+    setup.client.validate_challenge(&args);
+}
+
+#[test]
+fn test_client_data_hash_mismatch() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+    let args = challenge(&setup);
+    let binding = hex(&setup.client.challenge_binding(&args).to_array());
+
+    // Bound to a different challenge
+    let mut other = challenge(&setup);
+    other.set(
+        Symbol::new(env, "client_data_hash"),
+        String::from_str(env, &binding),
+    );
+    // Not a hash at all
+    let mut malformed = args.clone();
+    malformed.set(
+        Symbol::new(env, "client_data_hash"),
+        String::from_str(env, &binding[..62]),
+    );
+    let mut not_hex = args.clone();
+    not_hex.set(
+        Symbol::new(env, "client_data_hash"),
+        String::from_str(env, &binding.replace(|c: char| c.is_ascii_digit(), "g")),
+    );
+    for args in [other, malformed, not_hex] {
+        assert_eq!(
+            setup.client.try_web_auth_verify(&args),
+            Err(Ok(WebAuthError::ChallengeBindingMismatch))
+        );
+    }
+}
+
+#[test]
+fn test_domain_account_mismatch() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+    let mut args = challenge(&setup);
+    args.set(
+        Symbol::new(env, "web_auth_domain_account"),
+        Address::generate(env).to_string(),
+    );
+
+    assert_eq!(
+        setup.client.try_web_auth_verify(&args),
+        Err(Ok(WebAuthError::DomainAccountMismatch))
+    );
+}
+
+#[test]
+fn test_set_web_auth_domain_account() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+    let rotated = Address::generate(env);
+    setup.client.set_web_auth_domain_account(&rotated);
+    assert_eq!(setup.client.web_auth_domain_account(), rotated);
+
+    // Challenges naming the previous server account are now rejected
+    let mut args = challenge(&setup);
+    assert_eq!(
+        setup.client.try_web_auth_verify(&args),
+        Err(Ok(WebAuthError::DomainAccountMismatch))
+    );
+
+    args.set(
+        Symbol::new(env, "web_auth_domain_account"),
+        rotated.to_string(),
+    );
+    assert_eq!(setup.client.web_auth_verify(&args), setup.account);
+}
+
+#[test]
+fn test_server_account_rotation() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+    let previous = setup.server.clone();
+    let rotated = Address::generate(env);
+    setup.client.set_web_auth_domain_account(&rotated);
+    setup.client.add_server_account(&previous, &Some(NOW + 60));
+    assert_eq!(
+        setup.client.server_accounts(),
+        vec![
+            env,
+            ServerAccount {
+                account: previous.clone(),
+                expires_at: Some(NOW + 60),
+            }
+        ]
+    );
+
+    // Challenges issued with either key verify while the rotation completes
+    let mut args = challenge(&setup);
+    assert_eq!(setup.client.web_auth_verify(&args), setup.account);
+    args.set(
+        Symbol::new(env, "web_auth_domain_account"),
+        rotated.to_string(),
+    );
+    args.set(Symbol::new(env, "nonce"), String::from_str(env, "2"));
+    assert_eq!(setup.client.web_auth_verify(&args), setup.account);
+
+    env.ledger().set_timestamp(NOW + 61);
+    let mut args = challenge(&setup);
+    args.set(Symbol::new(env, "nonce"), String::from_str(env, "3"));
+    assert_eq!(
+        setup.client.try_web_auth_verify(&args),
+        Err(Ok(WebAuthError::DomainAccountMismatch))
+    );
+}
+
+#[test]
+fn test_expire_server_account() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+    let extra = Address::generate(env);
+    setup.client.add_server_account(&extra, &None);
+
+    let mut args = challenge(&setup);
+    args.set(
+        Symbol::new(env, "web_auth_domain_account"),
+        extra.to_string(),
+    );
+    assert_eq!(setup.client.web_auth_verify(&args), setup.account);
+
+    setup.client.expire_server_account(&extra);
+    assert!(setup.client.server_accounts().is_empty());
+    args.set(Symbol::new(env, "nonce"), String::from_str(env, "2"));
+    assert_eq!(
+        setup.client.try_web_auth_verify(&args),
+        Err(Ok(WebAuthError::DomainAccountMismatch))
+    );
+}
+
+fn with_client_domain(setup: &Setup, client_domain_account: &Address) -> Map<Symbol, String> {
+    let env = &setup.env;
+    let mut args = challenge(setup);
+    args.set(
+        Symbol::new(env, "client_domain"),
+        String::from_str(env, "wallet.example.com"),
+    );
+    args.set(
+        Symbol::new(env, "client_domain_account"),
+        client_domain_account.to_string(),
+    );
+    args
+}
+
+#[test]
+fn test_set_required_args() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+    let required_args = vec![
+        env,
+        Symbol::new(env, "account"),
+        Symbol::new(env, "client_domain"),
+    ];
+    setup.client.set_required_args(&required_args);
+    let set = RequiredArgsSet {
+        required_args: required_args.clone(),
+    };
+    assert_eq!(
+        env.events().all(),
+        vec![
+            env,
+            (setup.client.address.clone(), set.topics(env), set.data(env))
+        ]
+    );
+    assert_eq!(setup.client.required_args(), required_args);
+
+    assert_eq!(
+        setup.client.try_web_auth_verify(&challenge(&setup)),
+        Err(Ok(WebAuthError::MissingArgument))
+    );
+    let args = with_client_domain(&setup, &Address::generate(env));
+    assert_eq!(setup.client.web_auth_verify(&args), setup.account);
+
+    // The server account is checked even when the list leaves it out
+    let mut args = args;
+    args.remove(Symbol::new(env, "web_auth_domain_account"));
+    assert_eq!(
+        setup.client.try_web_auth_verify(&args),
+        Err(Ok(WebAuthError::MissingWebAuthDomainAccount))
+    );
+}
+
+#[test]
+fn test_set_unknown_required_arg() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+
+    assert_eq!(
+        setup
+            .client
+            .try_set_required_args(&vec![env, Symbol::new(env, "favourite_colour")]),
+        Err(Ok(WebAuthError::UnknownArgumentKey))
+    );
+    assert_eq!(
+        setup.client.required_args(),
+        vec![
+            env,
+            Symbol::new(env, "account"),
+            Symbol::new(env, "web_auth_domain_account")
+        ]
+    );
+}
+
+#[test]
+fn test_client_domain_refuses_auth() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+    let client_domain_account = Address::generate(env);
+    let args = with_client_domain(&setup, &client_domain_account);
+
+    // The account and server sign, the client domain doesn't
+    let invoke = MockAuthInvoke {
+        contract: &setup.client.address,
+        fn_name: "web_auth_verify",
+        args: (args.clone(),).into_val(env),
+        sub_invokes: &[],
+    };
+    env.mock_auths(&[
+        MockAuth {
+            address: &setup.account,
+            invoke: &invoke,
+        },
+        MockAuth {
+            address: &setup.server,
+            invoke: &invoke,
+        },
+    ]);
+    assert_eq!(
+        setup.client.try_web_auth_verify(&args),
+        Err(Err(InvokeError::Abort))
+    );
+
+    env.mock_auths(&[
+        MockAuth {
+            address: &setup.account,
+            invoke: &invoke,
+        },
+        MockAuth {
+            address: &setup.server,
+            invoke: &invoke,
+        },
+        MockAuth {
+            address: &client_domain_account,
+            invoke: &invoke,
+        },
+    ]);
+    assert_eq!(setup.client.web_auth_verify(&args), setup.account);
+}
+
+#[test]
+fn test_verify_many() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+    let other = Address::generate(env);
+    let args = challenge(&setup);
+    let accounts = vec![env, setup.account.clone(), other.clone()];
+
+    setup.client.web_auth_verify_many(&args, &accounts);
+    let verified = |account: &Address| Verified {
+        account: account.clone(),
+        account_memo: None,
+        memo: None,
+        home_domain: String::from_str(env, HOME_DOMAIN),
+        account_type: AccountType::Contract,
+    };
+    let (first, second) = (verified(&setup.account), verified(&other));
+    assert_eq!(
+        env.events().all(),
+        vec![
+            env,
+            (
+                setup.client.address.clone(),
+                first.topics(env),
+                first.data(env)
+            ),
+            (
+                setup.client.address.clone(),
+                second.topics(env),
+                second.data(env)
+            )
+        ]
+    );
+    // Each account, and the server once, authorized the whole batch
+    assert_eq!(env.auths().len(), 3);
+
+    // The nonce is consumed for the batch as a whole
+    assert_eq!(
+        setup.client.try_web_auth_verify_many(&args, &accounts),
+        Err(Ok(WebAuthError::NonceReused))
+    );
+    assert_eq!(
+        setup
+            .client
+            .try_web_auth_verify_many(&challenge(&setup), &Vec::new(env)),
+        Err(Ok(WebAuthError::NoAccounts))
+    );
+}
+
+#[test]
+fn test_verify_many_requires_every_account() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+    let other = Address::generate(env);
+    let args = challenge(&setup);
+    let accounts = vec![env, setup.account.clone(), other.clone()];
+
+    let invoke = MockAuthInvoke {
+        contract: &setup.client.address,
+        fn_name: "web_auth_verify_many",
+        args: (args.clone(), accounts.clone()).into_val(env),
+        sub_invokes: &[],
+    };
+    for signers in [[&setup.account, &setup.server], [&other, &setup.server]] {
+        env.mock_auths(&[
+            MockAuth {
+                address: signers[0],
+                invoke: &invoke,
+            },
+            MockAuth {
+                address: signers[1],
+                invoke: &invoke,
+            },
+        ]);
+        assert_eq!(
+            setup.client.try_web_auth_verify_many(&args, &accounts),
+            Err(Err(InvokeError::Abort))
+        );
+    }
+
+    env.mock_auths(&[
+        MockAuth {
+            address: &setup.account,
+            invoke: &invoke,
+        },
+        MockAuth {
+            address: &other,
+            invoke: &invoke,
+        },
+        MockAuth {
+            address: &setup.server,
+            invoke: &invoke,
+        },
+    ]);
+    assert_eq!(
+        setup.client.try_web_auth_verify_many(&args, &accounts),
+        Ok(Ok(()))
+    );
+}
+
+#[test]
+fn test_client_domain_allowlist() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+    let allowed = Address::generate(env);
+    let disallowed = Address::generate(env);
+    setup.client.add_client_domain(&allowed);
+
+    let args = with_client_domain(&setup, &allowed);
+    assert_eq!(setup.client.web_auth_verify(&args), setup.account);
+
+    let args = with_client_domain(&setup, &disallowed);
+    assert_eq!(
+        setup.client.try_web_auth_verify(&args),
+        Err(Ok(WebAuthError::ClientDomainNotAllowed))
+    );
+
+    setup.client.remove_client_domain(&allowed);
+    assert_eq!(setup.client.client_domains().len(), 0);
+}
+
+#[test]
+fn test_empty_client_domain_allowlist() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+
+    let args = with_client_domain(&setup, &Address::generate(env));
+    assert_eq!(setup.client.web_auth_verify(&args), setup.account);
+}
+
+#[test]
+fn test_missing_account() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+    let mut args = challenge(&setup);
+    args.remove(Symbol::new(env, "account"));
+
+    assert_eq!(
+        setup.client.try_web_auth_verify(&args),
+        Err(Ok(WebAuthError::MissingAccount))
+    );
+}
+
+#[test]
+fn test_missing_account_when_not_required() {
+    let setup = setup(&["web_auth_domain_account"]);
+    let env = &setup.env;
+    let mut args = challenge(&setup);
+    args.remove(Symbol::new(env, "account"));
+
+    assert_eq!(
+        setup.client.try_web_auth_verify(&args),
+        Err(Ok(WebAuthError::MissingAccount))
+    );
+}
+
+#[test]
+fn test_account_memo() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+    let mut args = challenge(&setup);
+    args.set(
+        Symbol::new(env, "account_memo"),
+        String::from_str(env, "42"),
+    );
+
+    assert_eq!(setup.client.web_auth_verify(&args), setup.account);
+    let verified = Verified {
+        account: setup.account.clone(),
+        account_memo: Some(42),
+        memo: None,
+        home_domain: String::from_str(env, HOME_DOMAIN),
+        account_type: AccountType::Contract,
+    };
+    assert_eq!(
+        env.events().all(),
+        vec![
+            env,
+            (
+                setup.client.address.clone(),
+                verified.topics(env),
+                verified.data(env)
+            )
+        ]
+    );
+}
+
+#[test]
+fn test_invalid_account_memo() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+    let mut args = challenge(&setup);
+    args.set(
+        Symbol::new(env, "account_memo"),
+        String::from_str(env, "-1"),
+    );
+
+    assert_eq!(
+        setup.client.try_web_auth_verify(&args),
+        Err(Ok(WebAuthError::InvalidMemo))
+    );
+}
+
+#[test]
+fn test_stray_address_argument() {
+    let setup = setup(&["refund_account"]);
+    let env = &setup.env;
+    let mut args = challenge(&setup);
+    args.set(
+        Symbol::new(env, "refund_account"),
+        String::from_str(env, "ops@example.com"),
+    );
+    setup.client.web_auth_verify(&args);
+
+    for stray in [
+        Address::generate(env).to_string(),
+        setup.account.to_string(),
+        String::from_str(
+            env,
+            "MA7QYNF7SOWQ3GLR2BGMZEHXAVIRZA4KVWLTJJFC7MGXUA74P7UJUAAAAAAAAAAAACJUQ",
+        ),
+    ] {
+        let mut args = challenge(&setup);
+        args.set(
+            Symbol::new(env, "refund_account"),
+            String::from_str(env, "x"),
+        );
+        args.set(Symbol::new(env, "smuggled"), stray);
+        assert_eq!(
+            setup.client.try_web_auth_verify(&args),
+            Err(Ok(WebAuthError::UnexpectedArgument))
+        );
+    }
+
+    // An argument the deployment requires may hold an address
+    let mut args = challenge(&setup);
+    args.set(
+        Symbol::new(env, "refund_account"),
+        Address::generate(env).to_string(),
+    );
+    setup.client.web_auth_verify(&args);
+}
+
+#[test]
+fn test_auth_addresses() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+    let mut args = challenge(&setup);
+    assert_eq!(
+        setup.client.auth_addresses(&args),
+        vec![env, setup.account.clone(), setup.server.clone()]
+    );
+
+    let client_domain_account = Address::generate(env);
+    args.set(
+        Symbol::new(env, "client_domain"),
+        String::from_str(env, "wallet.example.com"),
+    );
+    args.set(
+        Symbol::new(env, "client_domain_account"),
+        client_domain_account.to_string(),
+    );
+    assert_eq!(
+        setup.client.auth_addresses(&args),
+        vec![
+            env,
+            setup.account.clone(),
+            setup.server.clone(),
+            client_domain_account
+        ]
+    );
+    // The addresses are exactly the ones verifying requires authorization from
+    setup.client.web_auth_verify(&args);
+    assert_eq!(env.auths().len(), 3);
+}
+
+#[test]
+fn test_validate_and_collect() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+    let mut args = challenge(&setup);
+    assert_eq!(
+        setup.client.validate_and_collect(&args),
+        vec![env, setup.account.clone(), setup.server.clone()]
+    );
+    assert!(env.auths().is_empty());
+
+    let client_domain_account = Address::generate(env);
+    args.set(
+        Symbol::new(env, "client_domain"),
+        String::from_str(env, "wallet.example.com"),
+    );
+    args.set(
+        Symbol::new(env, "client_domain_account"),
+        client_domain_account.to_string(),
+    );
+    assert_eq!(
+        setup.client.validate_and_collect(&args),
+        vec![
+            env,
+            setup.account.clone(),
+            setup.server.clone(),
+            client_domain_account
+        ]
+    );
+
+    // Structural failures are returned as verifying would
+    let mut wrong_domain = challenge(&setup);
+    wrong_domain.set(
+        Symbol::new(env, "home_domain"),
+        String::from_str(env, "evil.example"),
+    );
+    let expired = challenge_with_exp(&setup, "1699999999");
+    let mut missing_account = challenge(&setup);
+    missing_account.remove(Symbol::new(env, "account"));
+    for (args, error) in [
+        (wrong_domain, WebAuthError::HomeDomainMismatch),
+        (expired, WebAuthError::ChallengeExpired),
+        (missing_account, WebAuthError::MissingAccount),
+    ] {
+        assert_eq!(setup.client.try_validate_and_collect(&args), Err(Ok(error)));
+    }
+
+    // The nonce is left for the real verification to consume
+    setup.client.web_auth_verify(&args);
+    assert_eq!(
+        setup.client.try_validate_and_collect(&args),
+        Err(Ok(WebAuthError::NonceReused))
+    );
+
+    // Unknown accounts are refused before the caller asks anyone to sign
+    let registry = env.register(MockRegistry, ());
+    setup.client.set_account_registry(&Some(registry));
+    assert_eq!(
+        setup.client.try_validate_and_collect(&challenge(&setup)),
+        Err(Ok(WebAuthError::AccountNotRegistered))
+    );
+}
+
+#[test]
+fn test_memo_with_muxed_account() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+    let mut args = challenge(&setup);
+    args.set(
+        Symbol::new(env, "account"),
+        String::from_str(
+            env,
+            "MA7QYNF7SOWQ3GLR2BGMZEHXAVIRZA4KVWLTJJFC7MGXUA74P7UJUAAAAAAAAAAAACJUQ",
+        ),
+    );
+    args.set(
+        Symbol::new(env, "account_memo"),
+        String::from_str(env, "42"),
+    );
+
+    assert_eq!(
+        setup.client.try_web_auth_verify(&args),
+        Err(Ok(WebAuthError::ConflictingMemo))
+    );
+}
+
+/// Verifies `args` and returns the memo its `Verified` event reports
+/// A classic `G...` account address
+fn classic_account(env: &Env) -> Address {
+    let mut key = [0u8; 32];
+    OsRng.fill_bytes(&mut key);
+    let address = ScAddress::Account(AccountId(PublicKey::PublicKeyTypeEd25519(Uint256(key))));
+    Address::try_from_val(env, &ScVal::Address(address)).unwrap()
+}
+
+#[test]
+fn test_account_type() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+    let classic = classic_account(env);
+    let verified_type = |account: &Address, nonce: &str| {
+        let mut args = challenge(&setup);
+        args.set(Symbol::new(env, "account"), account.to_string());
+        args.set(Symbol::new(env, "nonce"), String::from_str(env, nonce));
+        setup.client.web_auth_verify(&args);
+        let (_, _, data) = env.events().all().last().unwrap();
+        let verified: Map<Symbol, Val> = data.into_val(env);
+        AccountType::try_from_val(
+            env,
+            &verified.get(Symbol::new(env, "account_type")).unwrap(),
+        )
+        .unwrap()
+    };
+
+    assert!(setup.client.allow_classic());
+    assert_eq!(verified_type(&setup.account, "1"), AccountType::Contract);
+    assert_eq!(verified_type(&classic, "2"), AccountType::Classic);
+}
+
+#[test]
+fn test_classic_account_not_allowed() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+    setup.client.set_allow_classic(&false);
+    assert!(!setup.client.allow_classic());
+
+    let mut args = challenge(&setup);
+    args.set(
+        Symbol::new(env, "account"),
+        classic_account(env).to_string(),
+    );
+    assert_eq!(
+        setup.client.try_web_auth_verify(&args),
+        Err(Ok(WebAuthError::ClassicAccountNotAllowed))
+    );
+
+    // Contract accounts are unaffected
+    assert_eq!(
+        setup.client.web_auth_verify(&challenge(&setup)),
+        setup.account
+    );
+}
+
+fn verified_memo(setup: &Setup, args: &Map<Symbol, String>) -> Option<Memo> {
+    let env = &setup.env;
+    setup.client.web_auth_verify(args);
+    let (_, _, data) = env.events().all().last().unwrap();
+    let verified: Map<Symbol, Val> = data.into_val(env);
+    verified
+        .get(Symbol::new(env, "memo"))
+        .unwrap()
+        .into_val(env)
+}
+
+#[test]
+fn test_id_memo() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+    let mut args = challenge(&setup);
+    args.set(Symbol::new(env, "memo"), String::from_str(env, "1234567"));
+
+    assert_eq!(verified_memo(&setup, &args), Some(Memo::Id(1234567)));
+}
+
+#[test]
+fn test_text_memo() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+    let mut args = challenge(&setup);
+    args.set(Symbol::new(env, "memo"), String::from_str(env, "user-42"));
+
+    assert_eq!(
+        verified_memo(&setup, &args),
+        Some(Memo::Text(String::from_str(env, "user-42")))
+    );
+
+    // Longer than a text memo holds, or digits overflowing an id memo
+    for memo in ["a text memo well over 28 bytes", "99999999999999999999"] {
+        let mut args = challenge(&setup);
+        args.set(Symbol::new(env, "memo"), String::from_str(env, memo));
+        assert_eq!(
+            setup.client.try_web_auth_verify(&args),
+            Err(Ok(WebAuthError::InvalidMemo))
+        );
+    }
+}
+
+#[test]
+fn test_required_memo() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+    assert_eq!(setup.client.memo_policy(), MemoPolicy::Optional);
+    setup.client.set_memo_policy(&MemoPolicy::Required);
+    assert_eq!(setup.client.memo_policy(), MemoPolicy::Required);
+
+    let mut args = challenge(&setup);
+    assert_eq!(
+        setup.client.try_web_auth_verify(&args),
+        Err(Ok(WebAuthError::MissingArgument))
+    );
+    args.set(Symbol::new(env, "memo"), String::from_str(env, ""));
+    assert_eq!(
+        setup.client.try_web_auth_verify(&args),
+        Err(Ok(WebAuthError::MissingArgument))
+    );
+
+    args.set(Symbol::new(env, "memo"), String::from_str(env, "7"));
+    assert_eq!(verified_memo(&setup, &args), Some(Memo::Id(7)));
+
+    // Typed challenges have no memo to give, so can't be verified at all
+    assert_eq!(
+        setup
+            .client
+            .try_web_auth_verify_struct(&typed_challenge(&setup)),
+        Err(Ok(WebAuthError::MissingArgument))
+    );
+    setup.client.set_memo_policy(&MemoPolicy::Optional);
+    setup
+        .client
+        .web_auth_verify_struct(&typed_challenge(&setup));
+}
+
+#[test]
+fn test_too_many_arguments() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+    let mut args = challenge(&setup);
+    let mut extra = 0;
+    while args.len() <= 16 {
+        args.set(
+            Symbol::new(env, &std::format!("extra_{extra}")),
+            String::from_str(env, ""),
+        );
+        extra += 1;
+    }
+
+    assert_eq!(
+        setup.client.try_web_auth_verify(&args),
+        Err(Ok(WebAuthError::ArgumentTooLarge))
+    );
+}
+
+#[test]
+fn test_argument_too_long() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+    let mut args = challenge(&setup);
+    args.set(
+        Symbol::new(env, "home_domain"),
+        String::from_str(env, &"a".repeat(257)),
+    );
+
+    assert_eq!(
+        setup.client.try_web_auth_verify(&args),
+        Err(Ok(WebAuthError::ArgumentTooLarge))
+    );
+
+    args.set(
+        Symbol::new(env, "home_domain"),
+        String::from_str(env, &"a".repeat(256)),
+    );
+    assert_eq!(
+        setup.client.try_web_auth_verify(&args),
+        Err(Ok(WebAuthError::HomeDomainMismatch))
+    );
+}
+
+#[test]
+fn test_invalid_address() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+
+    // Empty, garbage, and a valid strkey with its checksum corrupted
+    let account = setup.account.to_string();
+    let mut corrupted = [0u8; 56];
+    account.copy_into_slice(&mut corrupted);
+    corrupted[55] = if corrupted[55] == b'A' { b'B' } else { b'A' };
+    for account in [
+        "",
+        "not an address",
+        std::str::from_utf8(&corrupted).unwrap(),
+    ] {
+        let mut args = challenge(&setup);
+        args.set(Symbol::new(env, "account"), String::from_str(env, account));
+        assert_eq!(
+            setup.client.try_web_auth_verify(&args),
+            Err(Ok(WebAuthError::InvalidAddress))
+        );
+    }
+}
+
+#[test]
+fn test_admin() {
+    let setup = setup(&[]);
+    assert_eq!(setup.client.admin(), setup.admin);
+}
+
+fn typed_challenge(setup: &Setup) -> Challenge {
+    let env = &setup.env;
+    Challenge {
+        account: setup.account.clone(),
+        home_domain: String::from_str(env, HOME_DOMAIN),
+        web_auth_domain: String::from_str(env, HOME_DOMAIN),
+        web_auth_domain_account: setup.server.clone(),
+        client_domain_account: None,
+        nonce: String::from_str(env, &NONCE.fetch_add(1, Ordering::Relaxed).to_string()),
+        exp: NOW + 300,
+    }
+}
+
+#[test]
+fn test_verify_struct() {
+    let setup = setup(&[]);
+    let challenge = typed_challenge(&setup);
+
+    assert_eq!(
+        setup.client.web_auth_verify_struct(&challenge),
+        setup.account
+    );
+    assert_eq!(
+        setup.client.try_web_auth_verify_struct(&challenge),
+        Err(Ok(WebAuthError::NonceReused))
+    );
+}
+
+#[test]
+fn test_verify_struct_validation() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+
+    let mut challenge = typed_challenge(&setup);
+    challenge.exp = NOW - 1;
+    assert_eq!(
+        setup.client.try_web_auth_verify_struct(&challenge),
+        Err(Ok(WebAuthError::ChallengeExpired))
+    );
+
+    let mut challenge = typed_challenge(&setup);
+    challenge.web_auth_domain_account = Address::generate(env);
+    assert_eq!(
+        setup.client.try_web_auth_verify_struct(&challenge),
+        Err(Ok(WebAuthError::DomainAccountMismatch))
+    );
+
+    let mut challenge = typed_challenge(&setup);
+    setup.client.add_client_domain(&Address::generate(env));
+    challenge.client_domain_account = Some(Address::generate(env));
+    assert_eq!(
+        setup.client.try_web_auth_verify_struct(&challenge),
+        Err(Ok(WebAuthError::ClientDomainNotAllowed))
+    );
+}
+
+#[test]
+fn test_is_fresh() {
+    let setup = setup(&[]);
+    setup.client.set_validity_window(&60, &600);
+
+    for (exp, fresh) in [
+        (NOW + 60, true),
+        (NOW + 600, true),
+        (NOW + 59, false),
+        (NOW + 601, false),
+        (NOW - 1, false),
+        (0, false),
+        (u64::MAX, false),
+    ] {
+        assert_eq!(setup.client.is_fresh(&exp), fresh, "exp {exp}");
+    }
+
+    // The same skew allowance applies as when verifying
+    setup.client.set_clock_skew(&10);
+    assert!(setup.client.is_fresh(&(NOW + 50)));
+    assert!(!setup.client.is_fresh(&(NOW + 49)));
+
+    setup.client.break_glass(&10);
+    assert!(setup.client.is_fresh(&(NOW - 1)));
+}
+
+#[test]
+fn test_clock_skew() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+
+    // Deployments tolerate 30 seconds unless configured otherwise
+    let fresh = WebAuthContractClient::new(
+        env,
+        &env.register(
+            WebAuthContract,
+            (
+                setup.admin.clone(),
+                String::from_str(env, HOME_DOMAIN),
+                String::from_str(env, HOME_DOMAIN),
+                setup.server.clone(),
+                MAX_VALIDITY,
+                Vec::<Symbol>::new(env),
+                ClientDomainPolicy::Optional,
+            ),
+        ),
+    );
+    assert_eq!(fresh.clock_skew(), 30);
+
+    let expired = challenge_with_exp(&setup, "1699999990");
+    assert_eq!(
+        setup.client.try_validate_challenge(&expired),
+        Err(Ok(WebAuthError::ChallengeExpired))
+    );
+    setup.client.set_clock_skew(&10);
+    assert_eq!(setup.client.clock_skew(), 10);
+    // Accepted at the edge of the allowance, rejected a second past it
+    setup.client.validate_challenge(&expired);
+    assert_eq!(
+        setup
+            .client
+            .try_validate_challenge(&challenge_with_exp(&setup, "1699999989")),
+        Err(Ok(WebAuthError::ChallengeExpired))
+    );
+    assert_eq!(setup.client.web_auth_verify(&expired), setup.account);
+
+    // The minimum validity gets the same allowance, the maximum none
+    setup.client.set_validity_window(&60, &600);
+    for (exp, expected) in [
+        ("1700000050", Ok(())),
+        ("1700000049", Err(Ok(WebAuthError::ValidityWindowViolation))),
+        ("1700000600", Ok(())),
+        ("1700000601", Err(Ok(WebAuthError::ValidityWindowViolation))),
+    ] {
+        let args = challenge_with_exp(&setup, exp);
+        assert_eq!(setup.client.try_validate_challenge(&args), expected.map(Ok));
+    }
+}
+
+#[test]
+fn test_nonce_outlives_clock_skew() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+    setup.client.set_clock_skew(&300);
+    let args = challenge_with_exp(&setup, "1700000003");
+    setup.client.web_auth_verify(&args);
+
+    // Past the challenge's own expiry, and past the lifetime the nonce would
+    // need for that alone, but inside the skew allowance it is still
+    // remembered
+    let sequence = env.ledger().sequence();
+    env.ledger().set_sequence_number(sequence + 20);
+    env.ledger().set_timestamp(NOW + 100);
+    assert_eq!(
+        setup.client.try_web_auth_verify(&args),
+        Err(Ok(WebAuthError::NonceReused))
+    );
+}
+
+#[test]
+fn test_validity_window() {
+    let setup = setup(&[]);
+    setup.client.set_validity_window(&60, &600);
+    assert_eq!(setup.client.min_validity(), 60);
+    assert_eq!(setup.client.max_validity(), 600);
+
+    for (exp, expected) in [
+        ("1700000059", Err(Ok(WebAuthError::ValidityWindowViolation))),
+        ("1700000601", Err(Ok(WebAuthError::ValidityWindowViolation))),
+        ("1700000060", Ok(Ok(setup.account.clone()))),
+        ("1700000600", Ok(Ok(setup.account.clone()))),
+    ] {
+        let args = challenge_with_exp(&setup, exp);
+        assert_eq!(setup.client.try_web_auth_verify(&args), expected);
+    }
+}
+
+#[test]
+#[should_panic(expected = "Error(Auth, InvalidAction)")]
+fn test_upgrade_requires_admin() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+    env.set_auths(&[]);
+
+    setup.client.upgrade(&BytesN::from_array(env, &[0; 32]));
+}
+
+#[test]
+fn test_upgrade_without_admin() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+    env.as_contract(&setup.client.address, || {
+        env.storage().instance().remove(&DataKey::Admin);
+    });
+
+    assert_eq!(
+        setup.client.try_upgrade(&BytesN::from_array(env, &[0; 32])),
+        Err(Ok(WebAuthError::AdminNotSet))
+    );
+    assert_eq!(setup.client.try_admin(), Err(Ok(WebAuthError::AdminNotSet)));
+    assert_eq!(
+        setup.client.try_config(),
+        Err(Ok(WebAuthError::AdminNotSet))
+    );
+}
+
+#[test]
+#[ignore = "requires the web_auth WASM, run `stellar contract build` first"]
+fn test_upgrade_event() {
+    let setup = setup(&[]);
+    let env = &setup.env;
+    let web_auth_wasm = std::fs::read(WEB_AUTH_WASM).unwrap();
+    let new_wasm_hash = env
+        .deployer()
+        .upload_contract_wasm(web_auth_wasm.as_slice());
+
+    setup.client.upgrade(&new_wasm_hash);
+    let upgraded = Upgraded {
+        new_wasm_hash,
+        admin: setup.admin.clone(),
+    };
+    assert_eq!(
+        env.events().all(),
+        vec![
+            env,
+            (
+                setup.client.address.clone(),
+                upgraded.topics(env),
+                upgraded.data(env)
+            )
+        ]
+    );
+}
+
+#[test]
+fn test_upgrade_same_wasm_hash() {
+    let setup = setup(&[]);
+    let Some(Executable::Wasm(current_wasm_hash)) = setup.client.address.executable() else {
+        panic!("web_auth should be a wasm contract");
+    };
+
+    assert_eq!(
+        setup.client.try_upgrade(&current_wasm_hash),
+        Err(Ok(WebAuthError::SameWasmHash))
+    );
+}
+
+#[test]
+fn test_client_domain_policy() {
+    for (policy, absent, present) in [
+        (
+            ClientDomainPolicy::Forbidden,
+            Ok(()),
+            Err(WebAuthError::ClientDomainNotAllowed),
+        ),
+        (ClientDomainPolicy::Optional, Ok(()), Ok(())),
+        (
+            ClientDomainPolicy::Required,
+            Err(WebAuthError::MissingArgument),
+            Ok(()),
+        ),
+    ] {
+        let setup = setup_with_policy(&[], policy);
+        let env = &setup.env;
+        assert_eq!(setup.client.client_domain_policy(), policy);
+
+        let verify = |args: &Map<Symbol, String>| match setup.client.try_web_auth_verify(args) {
+            Ok(_) => Ok(()),
+            Err(error) => Err(error.unwrap()),
+        };
+        assert_eq!(verify(&challenge(&setup)), absent);
+        assert_eq!(
+            verify(&with_client_domain(&setup, &Address::generate(env))),
+            present
+        );
+    }
+}
+
+#[test]
+fn test_version() {
+    let setup = setup(&[]);
+    assert_eq!(setup.client.version(), VERSION);
+}
+
+#[test]
+fn test_error_messages() {
+    let errors = [
+        WebAuthError::MissingArgument,
+        WebAuthError::HomeDomainMismatch,
+        WebAuthError::InvalidExpiration,
+        WebAuthError::ChallengeExpired,
+        WebAuthError::ValidityWindowViolation,
+        WebAuthError::UnexpectedArgument,
+        WebAuthError::NonceReused,
+        WebAuthError::DomainAccountMismatch,
+        WebAuthError::ClientDomainNotAllowed,
+        WebAuthError::MissingAccount,
+        WebAuthError::MissingWebAuthDomainAccount,
+        WebAuthError::InvalidMemo,
+        WebAuthError::ConflictingMemo,
+        WebAuthError::InvalidAddress,
+        WebAuthError::SameWasmHash,
+        WebAuthError::NetworkMismatch,
+        WebAuthError::WebAuthDomainMismatch,
+        WebAuthError::ArgumentTooLarge,
+        WebAuthError::AlreadyInitialized,
+        WebAuthError::ClassicAccountNotAllowed,
+        WebAuthError::UnknownArgumentKey,
+        WebAuthError::NoAccounts,
+        WebAuthError::AdminNotSet,
+        WebAuthError::ChallengeBindingMismatch,
+        WebAuthError::EmptyDomain,
+        WebAuthError::PolicyRejected,
+        WebAuthError::AccountNotRegistered,
+        WebAuthError::InvalidValidityWindow,
+    ];
+    let unknown = error_message(0);
+    let mut messages = std::collections::BTreeSet::new();
+    for error in errors {
+        let message = error_message(error as u32);
+        assert!(!message.is_empty());
+        assert_ne!(message, unknown);
+        assert!(messages.insert(message), "{error:?} shares its message");
+    }
+    assert_eq!(error_message(errors.len() as u32 + 1), unknown);
+}
+
+/// The `(key, value)` entries of a WASM's `contractmetav0` custom section
+fn contract_meta(wasm: &[u8]) -> std::vec::Vec<(std::string::String, std::string::String)> {
+    fn leb128(bytes: &[u8], pos: &mut usize) -> usize {
+        let mut value = 0;
+        let mut shift = 0;
+        loop {
+            let byte = bytes[*pos];
+            *pos += 1;
+            value |= ((byte & 0x7f) as usize) << shift;
+            if byte & 0x80 == 0 {
+                return value;
+            }
+            shift += 7;
+        }
+    }
+
+    let mut meta = std::vec::Vec::new();
+    // Past the magic number and version
+    let mut pos = 8;
+    while pos < wasm.len() {
+        let id = wasm[pos];
+        pos += 1;
+        let size = leb128(wasm, &mut pos);
+        let end = pos + size;
+        if id == 0 {
+            let mut data = pos;
+            let name_len = leb128(wasm, &mut data);
+            if &wasm[data..data + name_len] == b"contractmetav0" {
+                let mut entries = Limited::new(&wasm[data + name_len..end], Limits::none());
+                for entry in ScMetaEntry::read_xdr_iter(&mut entries) {
+                    let ScMetaEntry::ScMetaV0(ScMetaV0 { key, val }) = entry.unwrap();
+                    meta.push((key.to_utf8_string_lossy(), val.to_utf8_string_lossy()));
+                }
+            }
+        }
+        pos = end;
+    }
+    meta
+}
+
+#[test]
+#[ignore = "requires the web_auth WASM, run `stellar contract build` first"]
+fn test_contract_meta() {
+    use std::string::ToString;
+
+    let meta = contract_meta(&std::fs::read(WEB_AUTH_WASM).unwrap());
+    for (key, val) in [
+        ("name", "web_auth"),
+        ("version", VERSION.to_string().as_str()),
+        ("sep", "45"),
+    ] {
+        assert!(
+            meta.contains(&(key.to_string(), val.to_string())),
+            "missing {key}={val} in {meta:?}"
+        );
+    }
+}
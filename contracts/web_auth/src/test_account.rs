@@ -0,0 +1,223 @@
+#![cfg(test)]
+extern crate std;
+
+use std::string::ToString;
+
+use account::{
+    testutils::{random_signer, sign_payload, web_auth_message},
+    Account, AccountClient, AccountSignature, AdminMode,
+};
+use ed25519_dalek::SigningKey;
+use soroban_sdk::{
+    testutils::{Address as _, Ledger},
+    vec,
+    xdr::{
+        HashIdPreimage, HashIdPreimageSorobanAuthorization, InvokeContractArgs, Limits, ScAddress,
+        ScSymbol, ScVal, SorobanAddressCredentials, SorobanAuthorizationEntry,
+        SorobanAuthorizedFunction, SorobanAuthorizedInvocation, SorobanCredentials, WriteXdr,
+    },
+    Address, Bytes, BytesN, Env, IntoVal, Map, String, Symbol, TryFromVal, Val, Vec,
+};
+
+use crate::{ClientDomainPolicy, WebAuthContract, WebAuthContractClient};
+
+const HOME_DOMAIN: &str = "localhost:8080";
+const NOW: u64 = 1_700_000_000;
+
+/// Deploys an `Account` controlled by a single fresh ed25519 signer
+fn deploy_account(env: &Env) -> (Address, SigningKey) {
+    let (signing_key, signer) = random_signer(env);
+    let address = env.register(
+        Account,
+        (
+            AdminMode::External(Address::generate(env)),
+            vec![env, signer],
+            1u32,
+            1u32,
+        ),
+    );
+    (address, signing_key)
+}
+
+/// The payload the host hands to `__check_auth` for `invocation`
+fn host_payload(
+    env: &Env,
+    nonce: i64,
+    signature_expiration_ledger: u32,
+    invocation: &SorobanAuthorizedInvocation,
+) -> BytesN<32> {
+    let preimage = HashIdPreimage::SorobanAuthorization(HashIdPreimageSorobanAuthorization {
+        network_id: env.ledger().network_id().to_array().into(),
+        nonce,
+        signature_expiration_ledger,
+        invocation: invocation.clone(),
+    });
+    let preimage = Bytes::from_slice(env, &preimage.to_xdr(Limits::none()).unwrap());
+    env.crypto().sha256(&preimage).into()
+}
+
+/// A root invocation of `web_auth_verify(args)` on `contract_id`
+fn verify_invocation(
+    env: &Env,
+    contract_id: &Address,
+    args: &Map<Symbol, String>,
+) -> SorobanAuthorizedInvocation {
+    let args_val: Val = args.clone().into_val(env);
+    SorobanAuthorizedInvocation {
+        function: SorobanAuthorizedFunction::ContractFn(InvokeContractArgs {
+            contract_address: ScAddress::from(contract_id),
+            function_name: ScSymbol("web_auth_verify".try_into().unwrap()),
+            args: std::vec![ScVal::try_from_val(env, &args_val).unwrap()]
+                .try_into()
+                .unwrap(),
+        }),
+        sub_invocations: Default::default(),
+    }
+}
+
+/// Signs `invocation` on behalf of `account`, ready for its `__check_auth`,
+/// tagging the payload as a web auth assertion
+fn authorize(
+    env: &Env,
+    account: &Address,
+    signing_key: &SigningKey,
+    nonce: i64,
+    invocation: &SorobanAuthorizedInvocation,
+) -> SorobanAuthorizationEntry {
+    let signature_expiration_ledger = env.ledger().sequence() + 100;
+    let payload = host_payload(env, nonce, signature_expiration_ledger, invocation);
+    let message = web_auth_message(env, &payload);
+
+    let signatures: Vec<AccountSignature> = vec![
+        env,
+        AccountSignature::Ed25519(sign_payload(env, signing_key, &message, 0)),
+    ];
+    let signatures: Val = signatures.into_val(env);
+
+    SorobanAuthorizationEntry {
+        credentials: SorobanCredentials::Address(SorobanAddressCredentials {
+            address: ScAddress::from(account),
+            nonce,
+            signature_expiration_ledger,
+            signature: ScVal::try_from_val(env, &signatures).unwrap(),
+        }),
+        root_invocation: invocation.clone(),
+    }
+}
+
+#[test]
+fn test_verify_account_contract() {
+    let env = Env::default();
+    env.ledger().set_timestamp(NOW);
+
+    // Both the client and the server authenticate as `Account` contracts
+    let (account, account_key) = deploy_account(&env);
+    let (server, server_key) = deploy_account(&env);
+    let contract_id = env.register(
+        WebAuthContract,
+        (
+            Address::generate(&env),
+            String::from_str(&env, HOME_DOMAIN),
+            String::from_str(&env, HOME_DOMAIN),
+            server.clone(),
+            900u64,
+            Vec::<Symbol>::new(&env),
+            ClientDomainPolicy::Optional,
+        ),
+    );
+    let client = WebAuthContractClient::new(&env, &contract_id);
+
+    let mut args: Map<Symbol, String> = Map::new(&env);
+    args.set(Symbol::new(&env, "account"), account.to_string());
+    args.set(
+        Symbol::new(&env, "home_domain"),
+        String::from_str(&env, HOME_DOMAIN),
+    );
+    args.set(
+        Symbol::new(&env, "web_auth_domain"),
+        String::from_str(&env, HOME_DOMAIN),
+    );
+    args.set(
+        Symbol::new(&env, "web_auth_domain_account"),
+        server.to_string(),
+    );
+    args.set(Symbol::new(&env, "nonce"), String::from_str(&env, "1"));
+    args.set(
+        Symbol::new(&env, "exp"),
+        String::from_str(&env, &(NOW + 300).to_string()),
+    );
+
+    let invocation = verify_invocation(&env, &contract_id, &args);
+    env.set_auths(&[
+        authorize(&env, &account, &account_key, 1, &invocation),
+        authorize(&env, &server, &server_key, 2, &invocation),
+    ]);
+
+    assert_eq!(client.web_auth_verify(&args), account);
+    // Each account's `__check_auth` consumed its signature
+    assert_eq!(AccountClient::new(&env, &account).nonce(), 1);
+    assert_eq!(AccountClient::new(&env, &server).nonce(), 1);
+}
+
+#[test]
+fn test_expected_payload() {
+    let env = Env::default();
+    env.ledger().set_timestamp(NOW);
+
+    let (account, account_key) = deploy_account(&env);
+    let (server, server_key) = deploy_account(&env);
+    let contract_id = env.register(
+        WebAuthContract,
+        (
+            Address::generate(&env),
+            String::from_str(&env, HOME_DOMAIN),
+            String::from_str(&env, HOME_DOMAIN),
+            server.clone(),
+            900u64,
+            Vec::<Symbol>::new(&env),
+            ClientDomainPolicy::Optional,
+        ),
+    );
+    let client = WebAuthContractClient::new(&env, &contract_id);
+
+    let mut args: Map<Symbol, String> = Map::new(&env);
+    args.set(Symbol::new(&env, "account"), account.to_string());
+    args.set(
+        Symbol::new(&env, "home_domain"),
+        String::from_str(&env, HOME_DOMAIN),
+    );
+    args.set(
+        Symbol::new(&env, "web_auth_domain"),
+        String::from_str(&env, HOME_DOMAIN),
+    );
+    args.set(
+        Symbol::new(&env, "web_auth_domain_account"),
+        server.to_string(),
+    );
+    args.set(Symbol::new(&env, "nonce"), String::from_str(&env, "1"));
+    args.set(
+        Symbol::new(&env, "exp"),
+        String::from_str(&env, &(NOW + 300).to_string()),
+    );
+    let invocation = verify_invocation(&env, &contract_id, &args);
+
+    let signature_expiration_ledger = env.ledger().sequence() + 100;
+    for nonce in [1i64, 2, -7] {
+        assert_eq!(
+            client.expected_payload(&args, &nonce, &signature_expiration_ledger),
+            host_payload(&env, nonce, signature_expiration_ledger, &invocation)
+        );
+    }
+    // A different expiration ledger commits to a different payload
+    assert_ne!(
+        client.expected_payload(&args, &1, &(signature_expiration_ledger + 1)),
+        host_payload(&env, 1, signature_expiration_ledger, &invocation)
+    );
+
+    // Entries signed over that payload pass both accounts' `__check_auth`
+    env.set_auths(&[
+        authorize(&env, &account, &account_key, 1, &invocation),
+        authorize(&env, &server, &server_key, 2, &invocation),
+    ]);
+    assert_eq!(client.web_auth_verify(&args), account);
+}
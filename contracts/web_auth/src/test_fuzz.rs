@@ -0,0 +1,231 @@
+#![cfg(test)]
+//! Randomized challenges for `web_auth_verify`, checking malformed arguments
+//! are refused with a `WebAuthError` rather than trapping
+//!
+//! Each case edits a valid challenge, so cases reach the later checks as well
+//! as the parsing. The seed corpus below runs on every `cargo test`, followed
+//! by random cases decoded from bytes with `arbitrary`, as many as
+//! `FUZZ_ITERATIONS` sets. A libFuzzer target can drive `verify_bytes` with
+//! its own bytes.
+extern crate std;
+
+use arbitrary::{Arbitrary, Unstructured};
+use rand::{rngs::OsRng, RngCore};
+use soroban_sdk::{
+    testutils::{Address as _, Ledger},
+    Address, Env, Map, String, Symbol, Vec,
+};
+use std::{env, string::ToString, vec::Vec as StdVec};
+
+use crate::{ClientDomainPolicy, WebAuthContract, WebAuthContractClient, RECOGNIZED_ARGS};
+
+const HOME_DOMAIN: &str = "localhost:8080";
+const NOW: u64 = 1_700_000_000;
+
+/// Random cases run after the corpus unless `FUZZ_ITERATIONS` says otherwise
+const DEFAULT_ITERATIONS: usize = 64;
+
+/// Bytes of randomness each random case is decoded from
+const RANDOM_INPUT_LEN: usize = 2048;
+
+/// Characters a `Symbol` may hold
+const SYMBOL_CHARS: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789_";
+
+#[derive(Arbitrary, Debug)]
+enum Key {
+    /// One of the arguments the contract recognizes
+    Recognized(u8),
+    /// Any other symbol, from up to 32 characters
+    Other(StdVec<u8>),
+}
+
+#[derive(Arbitrary, Debug)]
+enum Value {
+    Account,
+    Server,
+    OtherAddress,
+    Number(u64),
+    /// Seconds from now, possibly in the past
+    Offset(i32),
+    Raw(StdVec<u8>),
+    /// A run of digits this long
+    Long(u16),
+}
+
+#[derive(Arbitrary, Debug)]
+enum Edit {
+    Remove(Key),
+    Set(Key, Value),
+}
+
+#[derive(Arbitrary, Debug)]
+struct VerifyInput {
+    edits: StdVec<Edit>,
+}
+
+fn symbol(env: &Env, key: &Key) -> Symbol {
+    match key {
+        Key::Recognized(index) => Symbol::new(
+            env,
+            RECOGNIZED_ARGS[*index as usize % RECOGNIZED_ARGS.len()],
+        ),
+        Key::Other(chars) => {
+            let name: std::string::String = chars
+                .iter()
+                .take(32)
+                .map(|c| SYMBOL_CHARS[*c as usize % SYMBOL_CHARS.len()] as char)
+                .collect();
+            Symbol::new(env, &name)
+        }
+    }
+}
+
+/// Runs `web_auth_verify` for the case `data` decodes to
+fn verify_bytes(data: &[u8]) {
+    if let Ok(input) = VerifyInput::arbitrary(&mut Unstructured::new(data)) {
+        verify_input(&input);
+    }
+}
+
+/// Runs `web_auth_verify` for `input`, panicking if it fails any way other
+/// than with a `WebAuthError`
+fn verify_input(input: &VerifyInput) {
+    let env = Env::default();
+    env.mock_all_auths();
+    env.ledger().set_timestamp(NOW);
+
+    let server = Address::generate(&env);
+    let contract_id = env.register(
+        WebAuthContract,
+        (
+            Address::generate(&env),
+            String::from_str(&env, HOME_DOMAIN),
+            String::from_str(&env, HOME_DOMAIN),
+            server.clone(),
+            900u64,
+            Vec::<Symbol>::new(&env),
+            ClientDomainPolicy::Optional,
+        ),
+    );
+    let client = WebAuthContractClient::new(&env, &contract_id);
+    let account = Address::generate(&env);
+
+    let mut args: Map<Symbol, String> = Map::new(&env);
+    for (key, value) in [
+        ("account", account.to_string()),
+        ("home_domain", String::from_str(&env, HOME_DOMAIN)),
+        ("web_auth_domain", String::from_str(&env, HOME_DOMAIN)),
+        ("web_auth_domain_account", server.to_string()),
+        ("nonce", String::from_str(&env, "1")),
+        ("exp", String::from_str(&env, &(NOW + 300).to_string())),
+    ] {
+        args.set(Symbol::new(&env, key), value);
+    }
+
+    for edit in &input.edits {
+        match edit {
+            Edit::Remove(key) => {
+                args.remove(symbol(&env, key));
+            }
+            Edit::Set(key, value) => {
+                let value = match value {
+                    Value::Account => account.to_string(),
+                    Value::Server => server.to_string(),
+                    Value::OtherAddress => Address::generate(&env).to_string(),
+                    Value::Number(n) => String::from_str(&env, &n.to_string()),
+                    Value::Offset(offset) => String::from_str(
+                        &env,
+                        &NOW.saturating_add_signed(*offset as i64).to_string(),
+                    ),
+                    Value::Raw(bytes) => String::from_bytes(&env, bytes),
+                    Value::Long(len) => String::from_str(&env, &"9".repeat(*len as usize)),
+                };
+                args.set(symbol(&env, key), value);
+            }
+        }
+    }
+
+    let result = client.try_web_auth_verify(&args);
+    assert!(
+        !matches!(result, Err(Err(_))),
+        "{input:?} failed without a WebAuthError: {result:?}"
+    );
+}
+
+fn seed_corpus() -> StdVec<VerifyInput> {
+    let recognized =
+        |key: &str| Key::Recognized(RECOGNIZED_ARGS.iter().position(|k| *k == key).unwrap() as u8);
+    let set = |key: &str, value| Edit::Set(recognized(key), value);
+    let input = |edits| VerifyInput { edits };
+    std::vec![
+        // The valid challenge itself
+        input(std::vec![]),
+        // Empty values and a challenge stripped of every argument
+        input(
+            RECOGNIZED_ARGS
+                .iter()
+                .map(|key| set(key, Value::Raw(std::vec![])))
+                .collect()
+        ),
+        input(
+            RECOGNIZED_ARGS
+                .iter()
+                .map(|key| Edit::Remove(recognized(key)))
+                .collect()
+        ),
+        // Oversized values and too many arguments
+        input(std::vec![set("home_domain", Value::Long(u16::MAX))]),
+        input(std::vec![set("exp", Value::Long(21))]),
+        input(std::vec![set("memo", Value::Long(29))]),
+        input(
+            (0..32u8)
+                .map(|i| Edit::Set(Key::Other(std::vec![i, i]), Value::Number(i as u64)))
+                .collect()
+        ),
+        // Values that aren't what the argument holds
+        input(std::vec![set("account", Value::Raw(b"GABC".to_vec()))]),
+        input(std::vec![set("client_domain_account", Value::Number(0))]),
+        input(std::vec![set("exp", Value::Raw(b"-1".to_vec()))]),
+        input(std::vec![set("max_ledger", Value::Number(u64::MAX))]),
+        input(std::vec![set(
+            "account_memo",
+            Value::Raw(std::vec![0xff; 8])
+        )]),
+        input(std::vec![set(
+            "client_data_hash",
+            Value::Raw(std::vec![b'g'; 64])
+        )]),
+        // Arguments repeated with other values, the last one set winning
+        input(std::vec![
+            set("web_auth_domain_account", Value::Account),
+            set("account", Value::Server),
+        ]),
+        input(std::vec![
+            set("exp", Value::Offset(-1)),
+            set("exp", Value::Offset(i32::MAX)),
+        ]),
+    ]
+}
+
+fn iterations() -> usize {
+    env::var("FUZZ_ITERATIONS")
+        .ok()
+        .and_then(|iterations| iterations.parse().ok())
+        .unwrap_or(DEFAULT_ITERATIONS)
+}
+
+#[test]
+fn fuzz_verify_corpus() {
+    for input in seed_corpus() {
+        verify_input(&input);
+    }
+}
+
+#[test]
+fn fuzz_verify_random() {
+    let mut data = [0u8; RANDOM_INPUT_LEN];
+    for _ in 0..iterations() {
+        OsRng.fill_bytes(&mut data);
+        verify_bytes(&data);
+    }
+}
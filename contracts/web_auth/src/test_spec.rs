@@ -0,0 +1,183 @@
+#![cfg(test)]
+//! The contract's interface, checked against the committed `spec.json`
+//!
+//! Client generators read `spec.json` in place of the spec in the WASM, so
+//! they needn't build it. The entries are those the SDK generates for each
+//! function, type and event, sorted by kind and name. After changing the
+//! interface on purpose, run the tests with `UPDATE_SPEC` set to rewrite the
+//! file.
+extern crate std;
+
+use soroban_sdk::xdr::{Limited, Limits, ReadXdr, ScSpecEntry};
+use std::{env, fs, string::String, vec::Vec};
+
+use crate::{
+    events, AccountType, Challenge, ClientDomainPolicy, DataKey, Memo, MemoPolicy, ServerAccount,
+    WebAuthConfig, WebAuthContract, WebAuthError, WebAuthResult,
+};
+
+const SPEC_JSON: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/spec.json");
+
+/// Produced by `stellar contract build`
+const WEB_AUTH_WASM: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/../target/wasm32v1-none/release/web_auth.wasm"
+);
+
+fn entry(xdr: &[u8]) -> ScSpecEntry {
+    ScSpecEntry::from_xdr(xdr, Limits::none()).unwrap()
+}
+
+/// Every entry in the contract's spec, listed by hand as the SDK exposes
+/// them one at a time outside the WASM
+fn spec_entries() -> Vec<ScSpecEntry> {
+    std::vec![
+        entry(&WebAuthContract::spec_xdr_upgrade()),
+        entry(&WebAuthContract::spec_xdr___constructor()),
+        entry(&WebAuthContract::spec_xdr_network_id()),
+        entry(&WebAuthContract::spec_xdr_client_domain_policy()),
+        entry(&WebAuthContract::spec_xdr_allow_classic()),
+        entry(&WebAuthContract::spec_xdr_set_allow_classic()),
+        entry(&WebAuthContract::spec_xdr_clock_skew()),
+        entry(&WebAuthContract::spec_xdr_set_clock_skew()),
+        entry(&WebAuthContract::spec_xdr_memo_policy()),
+        entry(&WebAuthContract::spec_xdr_set_memo_policy()),
+        entry(&WebAuthContract::spec_xdr_version()),
+        entry(&WebAuthContract::spec_xdr_admin()),
+        entry(&WebAuthContract::spec_xdr_set_strict_args()),
+        entry(&WebAuthContract::spec_xdr_strict_args()),
+        entry(&WebAuthContract::spec_xdr_break_glass()),
+        entry(&WebAuthContract::spec_xdr_end_break_glass()),
+        entry(&WebAuthContract::spec_xdr_break_glass_until()),
+        entry(&WebAuthContract::spec_xdr_config()),
+        entry(&WebAuthContract::spec_xdr_manifest()),
+        entry(&WebAuthContract::spec_xdr_set_validity_window()),
+        entry(&WebAuthContract::spec_xdr_min_validity()),
+        entry(&WebAuthContract::spec_xdr_max_validity()),
+        entry(&WebAuthContract::spec_xdr_home_domain()),
+        entry(&WebAuthContract::spec_xdr_set_home_domain()),
+        entry(&WebAuthContract::spec_xdr_web_auth_domain()),
+        entry(&WebAuthContract::spec_xdr_set_web_auth_domain()),
+        entry(&WebAuthContract::spec_xdr_set_domains()),
+        entry(&WebAuthContract::spec_xdr_web_auth_domain_account()),
+        entry(&WebAuthContract::spec_xdr_set_web_auth_domain_account()),
+        entry(&WebAuthContract::spec_xdr_add_server_account()),
+        entry(&WebAuthContract::spec_xdr_expire_server_account()),
+        entry(&WebAuthContract::spec_xdr_server_accounts()),
+        entry(&WebAuthContract::spec_xdr_add_client_domain()),
+        entry(&WebAuthContract::spec_xdr_remove_client_domain()),
+        entry(&WebAuthContract::spec_xdr_client_domains()),
+        entry(&WebAuthContract::spec_xdr_required_args()),
+        entry(&WebAuthContract::spec_xdr_set_policy_contract()),
+        entry(&WebAuthContract::spec_xdr_policy_contract()),
+        entry(&WebAuthContract::spec_xdr_set_account_registry()),
+        entry(&WebAuthContract::spec_xdr_account_registry()),
+        entry(&WebAuthContract::spec_xdr_set_required_args()),
+        entry(&WebAuthContract::spec_xdr_web_auth_verify()),
+        entry(&WebAuthContract::spec_xdr_login_count()),
+        entry(&WebAuthContract::spec_xdr_web_auth_verify_many()),
+        entry(&WebAuthContract::spec_xdr_web_auth_verify_full()),
+        entry(&WebAuthContract::spec_xdr_web_auth_verify_reporting()),
+        entry(&WebAuthContract::spec_xdr_canonical_challenge_bytes()),
+        entry(&WebAuthContract::spec_xdr_challenge_binding()),
+        entry(&WebAuthContract::spec_xdr_expected_payload()),
+        entry(&WebAuthContract::spec_xdr_is_fresh()),
+        entry(&WebAuthContract::spec_xdr_validate_challenge()),
+        entry(&WebAuthContract::spec_xdr_diagnose()),
+        entry(&WebAuthContract::spec_xdr_auth_addresses()),
+        entry(&WebAuthContract::spec_xdr_validate_and_collect()),
+        entry(&WebAuthContract::spec_xdr_require_auth_for()),
+        entry(&WebAuthContract::spec_xdr_web_auth_verify_struct()),
+        entry(&DataKey::spec_xdr()),
+        entry(&WebAuthError::spec_xdr()),
+        entry(&ClientDomainPolicy::spec_xdr()),
+        entry(&MemoPolicy::spec_xdr()),
+        entry(&AccountType::spec_xdr()),
+        entry(&Memo::spec_xdr()),
+        entry(&Challenge::spec_xdr()),
+        entry(&WebAuthResult::spec_xdr()),
+        entry(&ServerAccount::spec_xdr()),
+        entry(&WebAuthConfig::spec_xdr()),
+        entry(&events::Verified::spec_xdr()),
+        entry(&events::BreakGlassUsed::spec_xdr()),
+        entry(&events::Upgraded::spec_xdr()),
+        entry(&events::RequiredArgsSet::spec_xdr()),
+        entry(&events::DomainsSet::spec_xdr()),
+    ]
+}
+
+/// `entries` as the pretty-printed JSON `spec.json` holds, in a stable order
+fn spec_json(entries: Vec<ScSpecEntry>) -> String {
+    let mut entries: Vec<serde_json::Value> = entries
+        .iter()
+        .map(|entry| serde_json::to_value(entry).unwrap())
+        .collect();
+    entries.sort_by_cached_key(|entry| {
+        let (kind, body) = entry.as_object().unwrap().iter().next().unwrap();
+        (
+            kind.clone(),
+            String::from(body["name"].as_str().unwrap_or_default()),
+        )
+    });
+    serde_json::to_string_pretty(&entries).unwrap() + "\n"
+}
+
+/// The contents of the custom section `name` in `wasm`
+fn custom_section<'a>(wasm: &'a [u8], name: &[u8]) -> Option<&'a [u8]> {
+    fn leb128(bytes: &[u8], pos: &mut usize) -> usize {
+        let mut value = 0;
+        let mut shift = 0;
+        loop {
+            let byte = bytes[*pos];
+            *pos += 1;
+            value |= ((byte & 0x7f) as usize) << shift;
+            if byte & 0x80 == 0 {
+                return value;
+            }
+            shift += 7;
+        }
+    }
+
+    // Past the magic number and version
+    let mut pos = 8;
+    while pos < wasm.len() {
+        let id = wasm[pos];
+        pos += 1;
+        let size = leb128(wasm, &mut pos);
+        let end = pos + size;
+        if id == 0 {
+            let mut data = pos;
+            let name_len = leb128(wasm, &mut data);
+            if &wasm[data..data + name_len] == name {
+                return Some(&wasm[data + name_len..end]);
+            }
+        }
+        pos = end;
+    }
+    None
+}
+
+#[test]
+fn test_spec_matches_golden() {
+    let spec = spec_json(spec_entries());
+    if env::var_os("UPDATE_SPEC").is_some() {
+        fs::write(SPEC_JSON, &spec).unwrap();
+        return;
+    }
+    assert!(
+        fs::read_to_string(SPEC_JSON).unwrap() == spec,
+        "the interface no longer matches spec.json, rerun with UPDATE_SPEC=1 if that's intended"
+    );
+}
+
+#[test]
+#[ignore = "requires the web_auth WASM, run `stellar contract build` first"]
+fn test_spec_matches_wasm() {
+    // Catches an entry missing from `spec_entries`
+    let wasm = fs::read(WEB_AUTH_WASM).unwrap();
+    let section = custom_section(&wasm, b"contractspecv0").unwrap();
+    let entries = ScSpecEntry::read_xdr_iter(&mut Limited::new(section, Limits::none()))
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(spec_json(entries), spec_json(spec_entries()));
+}
@@ -74,6 +74,10 @@ export async function getChallenge(
         }),
       ]
       : []),
+    new xdr.ScMapEntry({
+      key: xdr.ScVal.scvSymbol("exp"),
+      val: nativeToScVal((Math.floor(Date.now() / 1000) + 300).toString()),
+    }),
     new xdr.ScMapEntry({
       key: xdr.ScVal.scvSymbol("home_domain"),
       val: nativeToScVal(request.home_domain),